@@ -0,0 +1,55 @@
+// timings for the world update and the collision code on a synthetic level, no window, textures or sounds needed.
+// run with: cargo bench
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use rustario_2d::bench::{look_around, resolve, synthetic_world, Surroundings, GOOMBAS};
+use rustario_2d::input::InputState;
+use rustario_2d::world::World;
+
+const SCALING_GOOMBAS: [usize; 4] = [100, 200, 400, 800];
+const WARMUP_FRAMES: u32 = 60;
+const FRAMES_PER_ITERATION: u32 = 60;
+
+// a world whose goombas and powerups have settled on the floor
+fn settled_world(goombas: usize) -> World {
+    let mut world = synthetic_world(goombas);
+    for _ in 0..WARMUP_FRAMES {
+        world.step(InputState::default());
+    }
+    world
+}
+
+// every iteration steps a fresh settled world, so the goombas never walk off their spread
+fn bench_update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("world update");
+    group.throughput(Throughput::Elements(FRAMES_PER_ITERATION as u64));
+    for goombas in SCALING_GOOMBAS {
+        group.bench_with_input(BenchmarkId::from_parameter(goombas), &goombas, |b, &goombas| {
+            b.iter_batched_ref(
+                || settled_world(goombas),
+                |world| {
+                    for _ in 0..FRAMES_PER_ITERATION {
+                        world.step(black_box(InputState::default()));
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+// once over every goomba and powerup of a settled world per iteration
+fn bench_collision(c: &mut Criterion) {
+    let world = settled_world(GOOMBAS);
+    let mut surroundings = Surroundings::default();
+    c.bench_function("get_surrounding_objects", |b| {
+        b.iter(|| look_around(black_box(&world), &mut surroundings))
+    });
+    look_around(&world, &mut surroundings);
+    c.bench_function("get_collision_response", |b| b.iter(|| resolve(black_box(&world), &surroundings)));
+}
+
+criterion_group!(benches, bench_update, bench_collision);
+criterion_main!(benches);
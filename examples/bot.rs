@@ -0,0 +1,76 @@
+// runs a scripted agent through the level headlessly and reports how it went and how fast the game stepped.
+// usage: cargo run --release --example bot [level file] [--episodes <n>]
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Instant;
+
+use rustario_2d::game::{Cell, Game, Observation, Outcome};
+use rustario_2d::input::InputState;
+use rustario_2d::logging;
+use rustario_2d::preparation::LEVEL_DATA_PATH;
+
+// the longest a run can take before the clock runs out, so a stuck agent still ends
+const MAX_FRAMES: u32 = 60 * 60 * 10;
+
+fn blocks_the_way(cell: Cell) -> bool {
+    matches!(cell, Cell::Solid | Cell::PowerupBlock | Cell::Enemy | Cell::OutOfLevel)
+}
+
+// run right, jump over whatever is in the way and over pits, let go of jump once landed so the next one is a press
+fn scripted_agent(observation: &Observation, jumping: &mut bool) -> InputState {
+    // at full run it takes off a tile earlier than a pit needs to clear what is in the way
+    let obstacle_ahead = (1..=3).any(|dx| blocks_the_way(observation.cell(dx, 0)));
+    let pit_ahead = (1..=2).all(|dx| matches!(observation.cell(dx, 1), Cell::Empty | Cell::OutOfLevel | Cell::Coin));
+    if observation.player.grounded {
+        *jumping = !*jumping && (obstacle_ahead || pit_ahead);
+    }
+    InputState {
+        right: true,
+        run: true,
+        jump: *jumping,
+        ..InputState::default()
+    }
+}
+
+fn main() -> ExitCode {
+    logging::init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let episodes = args
+        .iter()
+        .position(|arg| arg == "--episodes")
+        .and_then(|index| args.get(index + 1))
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(1);
+    let level_path = args
+        .iter()
+        .enumerate()
+        .find(|(index, arg)| !arg.starts_with("--") && (*index == 0 || args[index - 1] != "--episodes"))
+        .map_or(LEVEL_DATA_PATH, |(_, arg)| arg.as_str());
+    let mut game = match Game::new(Path::new(level_path)) {
+        Ok(game) => game,
+        Err(error) => {
+            eprintln!("Failed to load the level {}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut frames = 0u64;
+    let mut won = 0;
+    let started = Instant::now();
+    for episode in 0..episodes {
+        let mut observation = game.reset();
+        let mut jumping = false;
+        while !observation.done() && observation.frame < MAX_FRAMES {
+            let input = scripted_agent(&observation, &mut jumping);
+            observation = game.step(input);
+        }
+        frames += observation.frame as u64;
+        won += (observation.outcome == Outcome::Won) as u32;
+        println!(
+            "episode {}: {:?} after {} frames at x {:.0}, score {}, coins {}",
+            episode, observation.outcome, observation.frame, observation.player.pos.x, observation.score, observation.coins
+        );
+    }
+    let seconds = started.elapsed().as_secs_f64();
+    println!("won {}/{}, {} frames in {:.2}s, {:.0} frames per second", won, episodes, frames, seconds, frames as f64 / seconds);
+    ExitCode::SUCCESS
+}
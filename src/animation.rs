@@ -1,132 +1,365 @@
-pub mod animation {
+use macroquad::color::WHITE;
+use macroquad::math::{Rect, Vec2};
+use macroquad::texture::{draw_texture_ex, DrawTextureParams};
 
-    use macroquad::{math::Vec2, texture::Texture2D};
+use crate::assets::{Assets, TextureFrames, TextureId};
+use crate::config::{PHYSICS_FRAME_TIME, SCALE_IMAGE_FACTOR};
 
-    #[derive(Clone)]
-    pub enum FrameType {
-        Height(Vec<usize>),
-        Width(Vec<usize>),
-        PosOffset(Vec<Vec2>),
-    }
+#[derive(Clone)]
+pub enum FrameType {
+    Height(Vec<usize>),
+    Width(Vec<usize>),
+    PosOffset(Vec<Vec2>),
+}
 
-    #[derive(Clone)]
-    pub struct PlayAnimation {
+#[derive(Clone)]
+pub struct PlayAnimation {
 
-        pub frame_type: Option<FrameType>,
-        pub texture_frames: Vec<Texture2D>,
-        pub frame_index: usize,
-        pub loop_for: Option<f32>,
-    }
+    pub frame_type: Option<FrameType>,
+    pub texture_frames: TextureFrames,
+    pub frame_index: usize,
+    pub loop_for: Option<f32>,
+}
 
-    impl PlayAnimation {
-        pub fn new(
-            frame_type: Option<FrameType>,
-            texture_frames: Vec<Texture2D>,
-            loop_for: Option<f32>,
-        ) -> Self {
-        PlayAnimation{
-                frame_type,
-                texture_frames,
-                frame_index: 0,
-                loop_for,
-            }
+impl PlayAnimation {
+    pub fn new(
+        frame_type: Option<FrameType>,
+        texture_frames: TextureFrames,
+        loop_for: Option<f32>,
+    ) -> Self {
+    PlayAnimation{
+            frame_type,
+            texture_frames,
+            frame_index: 0,
+            loop_for,
         }
+    }
 
-        pub fn next_frame(&mut self) -> bool {
-            let max_index = match &self.frame_type {
-                Some(FrameType::Height(frames)) => frames.len(),
-                Some(FrameType::Width(frames)) => frames.len(),
-                Some(FrameType::PosOffset(frames)) => frames.len(),
-                None => self.texture_frames.len(),
-            };
-
-            if self.frame_index + 1 < max_index {
-                self.frame_index += 1;
-                true
-            } else if let Some(loop_for) = self.loop_for {
-                if loop_for == 0.0 {
-                    return false;
-                }
-                self.frame_index = 0;
-                true
-            } else {
+    pub fn next_frame(&mut self) -> bool {
+        let max_index = match &self.frame_type {
+            Some(FrameType::Height(frames)) => frames.len(),
+            Some(FrameType::Width(frames)) => frames.len(),
+            Some(FrameType::PosOffset(frames)) => frames.len(),
+            None => self.texture_frames.len(),
+        };
 
-                false
+        if self.frame_index + 1 < max_index {
+            self.frame_index += 1;
+            true
+        } else if let Some(loop_for) = self.loop_for {
+            if loop_for == 0.0 {
+                return false;
             }
-        }
+            self.frame_index = 0;
+            true
+        } else {
 
+            false
+        }
     }
 
+}
+
 pub struct PlayAnimationBuilder {
-    loop_for: Option<f32>,
-    height_frames: Option<Vec<usize>>,
-    width_frames: Option<Vec<usize>>,
-    pos_offset_frames: Option<Vec<Vec2>>,
-    texture_frames: Vec<Texture2D>,
-    frame_index: Option<usize>,
-    
+loop_for: Option<f32>,
+height_frames: Option<Vec<usize>>,
+width_frames: Option<Vec<usize>>,
+pos_offset_frames: Option<Vec<Vec2>>,
+texture_frames: TextureFrames,
+frame_index: Option<usize>,
+
 }
 
 impl PlayAnimationBuilder {
-    pub fn new(texture_frames: Vec<Texture2D>) -> Self {
+pub fn new(texture_frames: TextureFrames) -> Self {
 
-        Self {
-            loop_for: None,
-            height_frames: None,
-            width_frames: None,
-            pos_offset_frames: None,
-            texture_frames,
-            frame_index: None,
+    Self {
+        loop_for: None,
+        height_frames: None,
+        width_frames: None,
+        pos_offset_frames: None,
+        texture_frames,
+        frame_index: None,
 
-        }
     }
-    pub fn loop_for(mut self, loop_for: f32) -> Self {
-        self.loop_for = Some(loop_for);
+}
+pub fn loop_for(mut self, loop_for: f32) -> Self {
+    self.loop_for = Some(loop_for);
+    self
+}
+pub fn height_frames(mut self, frames: Vec<usize>) -> Self {
+    self.height_frames = Some(frames);
+    self.width_frames = None;
+    self.pos_offset_frames = None;
+    self.frame_index = Some(0);
+    self
+}
+
+pub fn width_frames(mut self, frames: Vec<usize>) -> Self {
+    self.width_frames = Some(frames);
+    self.height_frames = None;
+    self.pos_offset_frames = None;
+    self.frame_index = Some(0);
+    self
+}
+
+pub fn pos_offset_frames(mut self, frames: Vec<Vec2>) -> Self {
+    self.pos_offset_frames = Some(frames);
+    self.height_frames = None;
+    self.width_frames = None;
+    self.frame_index = Some(0);
+    self
+}
+
+
+pub fn build(self) -> PlayAnimation {
+    let frame_type = if let Some(frames) = self.height_frames {
+        Some(FrameType::Height(frames))
+    } else if let Some(frames) = self.width_frames {
+        Some(FrameType::Width(frames))
+    } else if let Some(frames) = self.pos_offset_frames {
+        Some(FrameType::PosOffset(frames))
+    } else {
+        None
+    };
+
+    PlayAnimation {
+        frame_type,
+        texture_frames: self.texture_frames,
+        frame_index: self.frame_index.unwrap_or(0),
+        loop_for: self.loop_for,
+    }
+}
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy)]
+pub(crate) enum DrawPortion {
+    Top(f32),
+    Bottom(f32), 
+    Left(f32),
+    Right(f32),
+}
+
+// where Animate::draw puts the current frame, in level pixels; facing and portion are optional
+#[derive(Clone, Copy)]
+pub(crate) struct DrawParams {
+    pub(crate) pos: Vec2,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) flip_x: bool,
+    pub(crate) portion: Option<DrawPortion>,
+}
+
+impl DrawParams {
+    pub(crate) fn new(pos: Vec2, width: usize, height: usize) -> Self {
+        DrawParams { pos, width, height, flip_x: false, portion: None }
+    }
+    // the sprites look right, moving left mirrors them
+    pub(crate) fn facing(mut self, velocity: &Vec2) -> Self {
+        self.flip_x = velocity.x < -0.1;
         self
     }
-    pub fn height_frames(mut self, frames: Vec<usize>) -> Self {
-        self.height_frames = Some(frames);
-        self.width_frames = None;
-        self.pos_offset_frames = None;
-        self.frame_index = Some(0);
+    pub(crate) fn portion(mut self, portion: DrawPortion) -> Self {
+        self.portion = Some(portion);
         self
     }
+}
 
-    pub fn width_frames(mut self, frames: Vec<usize>) -> Self {
-        self.width_frames = Some(frames);
-        self.height_frames = None;
-        self.pos_offset_frames = None;
-        self.frame_index = Some(0);
-        self
+#[derive(Clone)]
+pub(crate) struct Animate {
+    pub(crate) frames: TextureFrames,
+    pub(crate) animation: Option<PlayAnimation>,
+    pub(crate) current_frame_index: usize,
+    pub(crate) speed_factor: f32,
+    pub(crate) time_to_change: f32,
+    pub(crate) time_elapsed: f32,
+}
+
+impl Animate {
+    pub(crate) fn new(speed_factor: f32) -> Self {
+        assert!(speed_factor > 0.0);
+        Animate {
+            frames: TextureFrames::default(),
+            animation: None,
+            current_frame_index: 0,
+            speed_factor,
+            time_to_change: (PHYSICS_FRAME_TIME * 5.0) / speed_factor,
+            time_elapsed: 0.0,
+        }
     }
 
-    pub fn pos_offset_frames(mut self, frames: Vec<Vec2>) -> Self {
-        self.pos_offset_frames = Some(frames);
-        self.height_frames = None;
-        self.width_frames = None;
-        self.frame_index = Some(0);
-        self
+    pub(crate) fn change_animation_sprites(&mut self, new_frames: TextureFrames) {
+        if new_frames != self.frames {
+            self.frames = new_frames;
+            self.current_frame_index = 0;
+            self.time_elapsed = 0.0;
+        }
+    }
+
+    pub(crate) fn play_animation(&mut self, animation: PlayAnimation) {
+
+        self.animation = Some(animation);
+        self.current_frame_index = 0;
+        self.time_elapsed = 0.0;
+
+    }
+
+    // gameplay passes PHYSICS_FRAME_TIME, menus/UI can pass get_frame_time(); a large dt advances several frames
+    pub(crate) fn update(&mut self, dt: f32) {
+        self.time_elapsed += dt;
+        while self.time_elapsed >= self.time_to_change {
+            if !self.advance_frame() {
+                return;
+            }
+            self.time_elapsed -= self.time_to_change;
+        }
+    }
+
+    // returns false if the played animation finished and was reset
+    pub(crate) fn advance_frame(&mut self) -> bool {
+        if let Some(animation) = &mut self.animation {
+            if let Some(mut loop_for) = animation.loop_for {
+                if self.time_elapsed >= loop_for {
+                    self.reset_animation();
+                    return false;
+                }
+                animation.next_frame();
+                loop_for -= self.time_elapsed;
+                animation.loop_for = Some(loop_for);
+            }
+            else if !animation.next_frame() {
+                self.reset_animation();
+                return false;
+            }
+        } else if self.frames.len() > 1 {
+            self.current_frame_index = (self.current_frame_index + 1) % self.frames.len();
+        }
+        true
     }
 
+    pub(crate) fn scale_animation_speed(&mut self, factor: f32) {
+        assert!(factor > 0.0);
+        self.speed_factor = factor;
+        self.time_to_change = (PHYSICS_FRAME_TIME * 5.0) / self.speed_factor;
+    }
 
-    pub fn build(self) -> PlayAnimation {
-        let frame_type = if let Some(frames) = self.height_frames {
-            Some(FrameType::Height(frames))
-        } else if let Some(frames) = self.width_frames {
-            Some(FrameType::Width(frames))
-        } else if let Some(frames) = self.pos_offset_frames {
-            Some(FrameType::PosOffset(frames))
+    pub(crate) fn current_texture_frame(&self) -> Option<TextureId> {
+        if let Some(animation) = &self.animation {
+            animation.texture_frames.get(self.current_frame_index)
         } else {
-            None
-        };
+            self.frames.get(self.current_frame_index)
+        }
+    }
+    pub(crate) fn reset_animation(&mut self) {
+        self.animation = None;
+        self.current_frame_index = 0;
+        self.time_elapsed = 0.0;
+    }
+    pub(crate) fn draw(&self, assets: &Assets, params: DrawParams, camera_x: usize, camera_y: usize) {
+        let DrawParams { pos, width, height, flip_x, portion } = params;
+        if let Some(sprite_to_draw) = self.current_texture_frame().and_then(|id| assets.texture(id)) {
+            let mut src_rect = Rect::new(0.0, 0.0, sprite_to_draw.width(), sprite_to_draw.height());
+            let mut dest_size = Vec2::new(
+                (width * SCALE_IMAGE_FACTOR) as f32,
+                (height * SCALE_IMAGE_FACTOR) as f32
+            );
+            let mut pos_offset = Vec2::ZERO;
 
-        PlayAnimation {
-            frame_type,
-            texture_frames: self.texture_frames,
-            frame_index: self.frame_index.unwrap_or(0),
-            loop_for: self.loop_for,
+            if let Some(animation) = &self.animation {
+                match &animation.frame_type {
+                    Some(FrameType::Height(frames)) => {
+                        // the feet stay where they are, growing and shrinking happen at the top
+                        dest_size.y = frames[animation.frame_index] as f32 * SCALE_IMAGE_FACTOR as f32;
+                        pos_offset.y = height as f32 - frames[animation.frame_index] as f32;
+                    }
+                    Some(FrameType::Width(frames)) => {
+                        dest_size.x = frames[animation.frame_index] as f32 * SCALE_IMAGE_FACTOR as f32;
+                    }
+                    Some(FrameType::PosOffset(frames)) => {
+                        pos_offset = frames[animation.frame_index];
+                    }
+                    None => {}
+                }
+            }
+
+            if let Some(portion) = portion {
+                match portion {
+                    DrawPortion::Top(percentage) => {
+                        let clamped_percentage = percentage.clamp(0.0, 1.0);
+                        src_rect.h *= clamped_percentage;
+                        dest_size.y *= clamped_percentage;
+                    },
+                    DrawPortion::Bottom(percentage) => {
+                        let clamped_percentage = percentage.clamp(0.0, 1.0);
+                        src_rect.y += src_rect.h * (1.0 - clamped_percentage);
+                        src_rect.h *= clamped_percentage;
+                        dest_size.y *= clamped_percentage;
+                    },
+                    DrawPortion::Left(percentage) => {
+                        let clamped_percentage = percentage.clamp(0.0, 1.0);
+                        src_rect.w *= clamped_percentage;
+                        dest_size.x *= clamped_percentage;
+                    },
+                    DrawPortion::Right(percentage) => {
+                        let clamped_percentage = percentage.clamp(0.0, 1.0);
+                        src_rect.x += src_rect.w * (1.0 - clamped_percentage);
+                        src_rect.w *= clamped_percentage;
+                        dest_size.x *= clamped_percentage;
+                    },
+                }
+            }
+
+            draw_texture_ex(
+                sprite_to_draw,
+                (pos.x + pos_offset.x - camera_x as f32) * SCALE_IMAGE_FACTOR as f32,
+                (pos.y + pos_offset.y - camera_y as f32) * SCALE_IMAGE_FACTOR as f32,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(dest_size),
+                    source: Some(src_rect),
+                    flip_x,
+                    ..Default::default()
+                },
+            );
         }
     }
 }
 
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::MARIO_FRAMES;
+
+    #[test]
+    fn one_dt_of_three_and_a_half_intervals_advances_three_frames() {
+        let mut animate = Animate::new(1.0);
+        animate.change_animation_sprites(MARIO_FRAMES);
+        animate.update(animate.time_to_change * 3.5);
+        assert_eq!(animate.current_frame_index, 3);
+        // the half interval left over counts towards the next frame
+        animate.update(animate.time_to_change * 0.6);
+        assert_eq!(animate.current_frame_index, 4);
+    }
+
+    #[test]
+    fn small_dts_add_up_to_the_same_frames_as_one_big_one() {
+        let mut animate = Animate::new(2.0);
+        animate.change_animation_sprites(MARIO_FRAMES);
+        for _ in 0..7 {
+            animate.update(animate.time_to_change * 0.5);
+        }
+        assert_eq!(animate.current_frame_index, 3);
+    }
+
+    #[test]
+    fn a_big_dt_steps_a_played_animation_through_its_frames() {
+        let mut animate = Animate::new(1.0);
+        animate.play_animation(PlayAnimationBuilder::new(MARIO_FRAMES).height_frames(vec![16, 20, 24, 28, 32]).build());
+        animate.update(animate.time_to_change * 3.5);
+        assert_eq!(animate.animation.as_ref().map(|animation| animation.frame_index), Some(3));
+        // past its last frame it is over and the sprites are back
+        animate.update(animate.time_to_change * 2.0);
+        assert!(animate.animation.is_none());
+    }
+}
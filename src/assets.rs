@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use macroquad::audio::{load_sound, Sound};
+use macroquad::prelude::*;
+
+use crate::audio::{MusicId, SfxId};
+use crate::config::MARIO_SPRITE_BLOCK_SIZE;
+use crate::image_utils::load_recolored_texture;
+use crate::preparation::TILESHEET_PATH;
+use crate::world::fetch_level_file;
+
+// why a file the game needs couldn't be used, always with the file it is about
+#[derive(Clone, Debug)]
+pub enum AssetError {
+    MissingFile(PathBuf),
+    Io { path: PathBuf, message: String }, // there, but couldn't be read
+    Decode { path: PathBuf, message: String }, // read, but not what it should be
+}
+
+impl AssetError {
+    pub(crate) fn io(path: &Path, error: io::Error) -> AssetError {
+        match error.kind() {
+            io::ErrorKind::NotFound => AssetError::MissingFile(path.to_owned()),
+            _ => AssetError::Io { path: path.to_owned(), message: error.to_string() },
+        }
+    }
+
+    pub(crate) fn decode(path: &Path, error: impl fmt::Display) -> AssetError {
+        AssetError::Decode { path: path.to_owned(), message: error.to_string() }
+    }
+
+    // macroquad wraps the io error, unwrapped a missing file can be told apart from a broken one
+    pub(crate) fn from_macroquad(path: &Path, error: macroquad::Error) -> AssetError {
+        match error {
+            macroquad::Error::FileError { kind: macroquad::miniquad::fs::Error::IOError(error), .. } => AssetError::io(path, error),
+            macroquad::Error::FileError { kind, .. } => AssetError::Io { path: path.to_owned(), message: kind.to_string() },
+            error => AssetError::decode(path, error),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            AssetError::MissingFile(path) | AssetError::Io { path, .. } | AssetError::Decode { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetError::MissingFile(path) => write!(f, "{} is missing", path.display()),
+            AssetError::Io { path, message } => write!(f, "{}: {}", path.display(), message),
+            AssetError::Decode { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+// the character sprites are compiled in, only the tilesheet and the sounds can go missing
+const MARIO_SPRITES: [&[u8]; 6] = [
+    include_bytes!("../sprites/Mario.png"),
+    include_bytes!("../sprites/Mario_Run1.png"),
+    include_bytes!("../sprites/Mario_Run2.png"),
+    include_bytes!("../sprites/Mario_Jump1.png"),
+    include_bytes!("../sprites/Mario_Turn.png"),
+    include_bytes!("../sprites/Mario_Jump_HMomentum.png"),
+];
+const GOOMBA_SPRITES: [&[u8]; 3] = [
+    include_bytes!("../sprites/Goomba1.png"),
+    include_bytes!("../sprites/Goomba2.png"),
+    include_bytes!("../sprites/Goomba3.png"),
+];
+const POWERUP_SPRITES: [&[u8]; 1] = [include_bytes!("../sprites/Mushroom.png")];
+const COIN_SPRITES: [&[u8]; 2] = [include_bytes!("../sprites/Coin1.png"), include_bytes!("../sprites/Coin2.png")];
+const FIRE_FLOWER_SPRITES: [&[u8]; 1] = [include_bytes!("../sprites/FireFlower.png")];
+const FIREBALL_SPRITES: [&[u8]; 2] = [include_bytes!("../sprites/Fireball1.png"), include_bytes!("../sprites/Fireball2.png")];
+// half a tile taller than the others, the head sticks out over the tile it walks in
+const KOOPA_SPRITES: [&[u8]; 2] = [include_bytes!("../sprites/Koopa1.png"), include_bytes!("../sprites/Koopa2.png")];
+const SHELL_SPRITES: [&[u8]; 1] = [include_bytes!("../sprites/KoopaShell.png")];
+// the head on a stalk, a tile and a half high like the Koopa
+const PLANT_SPRITES: [&[u8]; 2] = [include_bytes!("../sprites/PiranhaPlant1.png"), include_bytes!("../sprites/PiranhaPlant2.png")];
+const BULLET_SPRITES: [&[u8]; 1] = [include_bytes!("../sprites/BulletBill.png")];
+// the egg, then walking
+const SPINY_SPRITES: [&[u8]; 3] = [
+    include_bytes!("../sprites/SpinyEgg.png"),
+    include_bytes!("../sprites/Spiny1.png"),
+    include_bytes!("../sprites/Spiny2.png"),
+];
+// on its cloud, a tile and a half high
+const LAKITU_SPRITES: [&[u8]; 1] = [include_bytes!("../sprites/Lakitu.png")];
+const STAR_SPRITES: [&[u8]; 2] = [include_bytes!("../sprites/Star1.png"), include_bytes!("../sprites/Star2.png")];
+// the launcher, then the puff of smoke as it fires
+const LAUNCHER_SPRITES: [&[u8]; 3] = [
+    include_bytes!("../sprites/Launcher.png"),
+    include_bytes!("../sprites/LauncherPuff1.png"),
+    include_bytes!("../sprites/LauncherPuff2.png"),
+];
+// player two is Mario in Luigi's colors, the red of the cap and shirt turned green
+const LUIGI_PALETTE: &[([u8; 3], [u8; 3])] = &[([247, 57, 16], [56, 168, 0])];
+// with fire the cap and shirt go pale and the overalls red, for either player
+const FIRE_PALETTE: &[([u8; 3], [u8; 3])] = &[([247, 57, 16], [252, 216, 168]), ([140, 115, 24], [216, 40, 0])];
+
+// a texture of the assets, resolved only when drawing. the ids are fixed, a world without textures animates the same
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct TextureId(u16);
+
+impl TextureId {
+    pub(crate) const fn tile(sprite_id: u8) -> TextureId {
+        TextureId(sprite_id as u16)
+    }
+}
+
+// consecutive textures an animation cycles through, copied around instead of the textures themselves
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TextureFrames {
+    first: u16,
+    count: u16,
+}
+
+impl TextureFrames {
+    const fn new(first: u16, count: u16) -> TextureFrames {
+        TextureFrames { first, count }
+    }
+
+    pub(crate) const fn single(id: TextureId) -> TextureFrames {
+        TextureFrames::new(id.0, 1)
+    }
+
+    pub(crate) fn len(self) -> usize {
+        self.count as usize
+    }
+
+    pub(crate) fn get(self, index: usize) -> Option<TextureId> {
+        (index < self.len()).then(|| TextureId(self.first + index as u16))
+    }
+
+    // frames start..end of the set
+    pub(crate) const fn part(self, start: u16, end: u16) -> TextureFrames {
+        assert!(start <= end && end <= self.count);
+        TextureFrames::new(self.first + start, end - start)
+    }
+
+    fn ids(self) -> impl Iterator<Item = TextureId> {
+        (self.first..self.first + self.count).map(TextureId)
+    }
+}
+
+// the character sprites come after the 256 possible tiles
+pub(crate) const MARIO_FRAMES: TextureFrames = TextureFrames::new(256, 6);
+pub(crate) const GOOMBA_FRAMES: TextureFrames = TextureFrames::new(262, 3);
+pub(crate) const POWERUP_FRAMES: TextureFrames = TextureFrames::new(265, 1);
+pub(crate) const COIN_FRAMES: TextureFrames = TextureFrames::new(266, 2);
+pub(crate) const LUIGI_FRAMES: TextureFrames = TextureFrames::new(268, 6);
+pub(crate) const FIRE_FRAMES: TextureFrames = TextureFrames::new(274, 6);
+pub(crate) const FIRE_FLOWER_FRAMES: TextureFrames = TextureFrames::new(280, 1);
+pub(crate) const FIREBALL_FRAMES: TextureFrames = TextureFrames::new(281, 2);
+pub(crate) const KOOPA_FRAMES: TextureFrames = TextureFrames::new(283, 2);
+pub(crate) const SHELL_FRAMES: TextureFrames = TextureFrames::new(285, 1);
+pub(crate) const PLANT_FRAMES: TextureFrames = TextureFrames::new(286, 2);
+pub(crate) const BULLET_FRAMES: TextureFrames = TextureFrames::new(288, 1);
+pub(crate) const LAUNCHER_FRAMES: TextureFrames = TextureFrames::new(289, 3);
+pub(crate) const SPINY_FRAMES: TextureFrames = TextureFrames::new(292, 3);
+pub(crate) const LAKITU_FRAMES: TextureFrames = TextureFrames::new(295, 1);
+pub(crate) const STAR_FRAMES: TextureFrames = TextureFrames::new(296, 2);
+
+// every texture and sound the game uses, loaded once before the first frame. a headless world has none
+pub struct Assets {
+    pub(crate) textures: HashMap<TextureId, Texture2D>,
+    pub(crate) tilesheet: Result<usize, AssetError>, // how many tiles it was cut into, a level can't load without them
+    pub(crate) sfx: HashMap<SfxId, Sound>,
+    pub(crate) music: HashMap<MusicId, Sound>,
+}
+
+impl Assets {
+    pub fn empty() -> Assets {
+        Assets {
+            textures: HashMap::new(),
+            tilesheet: Ok(0),
+            sfx: HashMap::new(),
+            music: HashMap::new(),
+        }
+    }
+
+    // None for textures that weren't loaded, drawing skips them
+    pub(crate) fn texture(&self, id: TextureId) -> Option<&Texture2D> {
+        self.textures.get(&id)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum AssetItem {
+    Tilesheet,
+    Sprites(TextureFrames, &'static [&'static [u8]], &'static [([u8; 3], [u8; 3])]), // the colors swapped while loading
+    Sfx(SfxId),
+    Music(MusicId),
+}
+
+// loads the assets one at a time, so a loading screen can draw between them
+pub struct AssetLoader {
+    items: Vec<AssetItem>,
+    loaded: usize,
+    assets: Assets,
+    errors: Vec<AssetError>,
+}
+
+impl Default for AssetLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AssetLoader {
+    pub fn new() -> AssetLoader {
+        let mut items = vec![
+            AssetItem::Tilesheet,
+            AssetItem::Sprites(MARIO_FRAMES, &MARIO_SPRITES, &[]),
+            AssetItem::Sprites(GOOMBA_FRAMES, &GOOMBA_SPRITES, &[]),
+            AssetItem::Sprites(POWERUP_FRAMES, &POWERUP_SPRITES, &[]),
+            AssetItem::Sprites(COIN_FRAMES, &COIN_SPRITES, &[]),
+            AssetItem::Sprites(LUIGI_FRAMES, &MARIO_SPRITES, LUIGI_PALETTE),
+            AssetItem::Sprites(FIRE_FRAMES, &MARIO_SPRITES, FIRE_PALETTE),
+            AssetItem::Sprites(FIRE_FLOWER_FRAMES, &FIRE_FLOWER_SPRITES, &[]),
+            AssetItem::Sprites(FIREBALL_FRAMES, &FIREBALL_SPRITES, &[]),
+            AssetItem::Sprites(KOOPA_FRAMES, &KOOPA_SPRITES, &[]),
+            AssetItem::Sprites(SHELL_FRAMES, &SHELL_SPRITES, &[]),
+            AssetItem::Sprites(PLANT_FRAMES, &PLANT_SPRITES, &[]),
+            AssetItem::Sprites(BULLET_FRAMES, &BULLET_SPRITES, &[]),
+            AssetItem::Sprites(LAUNCHER_FRAMES, &LAUNCHER_SPRITES, &[]),
+            AssetItem::Sprites(SPINY_FRAMES, &SPINY_SPRITES, &[]),
+            AssetItem::Sprites(LAKITU_FRAMES, &LAKITU_SPRITES, &[]),
+            AssetItem::Sprites(STAR_FRAMES, &STAR_SPRITES, &[]),
+        ];
+        items.extend(SfxId::ALL.map(AssetItem::Sfx));
+        items.extend(MusicId::ALL.map(AssetItem::Music));
+        AssetLoader {
+            items,
+            loaded: 0,
+            assets: Assets::empty(),
+            errors: Vec::new(),
+        }
+    }
+
+    // from 0.0 to 1.0
+    pub fn progress(&self) -> f32 {
+        self.loaded as f32 / self.items.len() as f32
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.loaded == self.items.len()
+    }
+
+    pub async fn load_next(&mut self) {
+        let Some(item) = self.items.get(self.loaded).copied() else {
+            return;
+        };
+        self.loaded += 1;
+        match item {
+            AssetItem::Tilesheet => {
+                self.assets.tilesheet = load_tilesheet().await.map(|tiles| {
+                    let count = tiles.len();
+                    self.assets.textures.extend((0..=u8::MAX).map(TextureId::tile).zip(tiles));
+                    count
+                })
+            }
+            AssetItem::Sprites(frames, sprites, palette) => {
+                let textures = sprites.iter().map(|bytes| load_recolored_texture(bytes, ImageFormat::Png, palette));
+                self.assets.textures.extend(frames.ids().zip(textures));
+            }
+            AssetItem::Sfx(id) => match load_sound_asset(id.path()).await {
+                Ok(sound) => _ = self.assets.sfx.insert(id, sound),
+                Err(error) => self.errors.push(error),
+            },
+            AssetItem::Music(id) => match load_sound_asset(id.path()).await {
+                Ok(sound) => _ = self.assets.music.insert(id, sound),
+                Err(error) => self.errors.push(error),
+            },
+        }
+    }
+
+    // the sounds that failed to load are left out and listed, playing them does nothing.
+    // a broken tilesheet is kept in the assets, the level it is needed for reports it
+    pub fn finish(self) -> (Assets, Vec<AssetError>) {
+        (self.assets, self.errors)
+    }
+}
+
+// cuts the tilesheet into one texture per sprite id
+async fn load_tilesheet() -> Result<Vec<Texture2D>, AssetError> {
+    let tilesheet_path = Path::new(TILESHEET_PATH);
+    let tilesheet_bytes = fetch_level_file(tilesheet_path).await?;
+    let tilesheet = Image::from_file_with_format(&tilesheet_bytes, Some(ImageFormat::Png))
+        .map_err(|error| AssetError::decode(tilesheet_path, error))?;
+    let amount_of_tiles = tilesheet.height() / MARIO_SPRITE_BLOCK_SIZE;
+    if amount_of_tiles > 256 {
+        return Err(AssetError::decode(tilesheet_path, format!("{} tiles, sprite ids only go up to 255", amount_of_tiles)));
+    }
+    let mut tiles = Vec::new();
+    for i in 0..amount_of_tiles {
+        let mut tile_image = Image::gen_image_color(16, 16, Color::new(0.0, 0.0, 0.0, 0.0));
+        for y in 0..16 {
+            for x in 0..16 {
+                let color = tilesheet.get_pixel(x, y + (MARIO_SPRITE_BLOCK_SIZE * i) as u32);
+                tile_image.set_pixel(x, y, color);
+            }
+        }
+        let tile_texture = Texture2D::from_image(&tile_image);
+        tile_texture.set_filter(FilterMode::Nearest);
+        tiles.push(tile_texture);
+    }
+    Ok(tiles)
+}
+
+async fn load_sound_asset(path: &str) -> Result<Sound, AssetError> {
+    load_sound(path).await.map_err(|error| AssetError::from_macroquad(Path::new(path), error))
+}
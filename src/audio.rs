@@ -0,0 +1,292 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::rc::Rc;
+
+use log::warn;
+use macroquad::audio::{play_sound, set_sound_volume, stop_sound, PlaySoundParams};
+use serde::{Deserialize, Serialize};
+
+use crate::assets::Assets;
+use crate::config::{MARIO_NON_MUSIC_VOLUME, SOUND_VOLUME};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SfxId {
+    Jump,
+    Powerup,
+    Stomp,
+    Coin,
+    Bump,
+    #[allow(dead_code)] // nothing breaks yet
+    BrickBreak,
+    PowerDown,
+    Death,
+    Hurry,
+    Pipe,
+}
+
+impl SfxId {
+    pub(crate) const ALL: [SfxId; 10] = [
+        SfxId::Jump,
+        SfxId::Powerup,
+        SfxId::Stomp,
+        SfxId::Coin,
+        SfxId::Bump,
+        SfxId::BrickBreak,
+        SfxId::PowerDown,
+        SfxId::Death,
+        SfxId::Hurry,
+        SfxId::Pipe,
+    ];
+
+    pub(crate) fn path(self) -> &'static str {
+        match self {
+            SfxId::Jump => "sounds/mario_jump.wav",
+            SfxId::Powerup => "sounds/powerup.wav",
+            SfxId::Stomp => "sounds/stomp.wav",
+            SfxId::Coin => "sounds/coin.wav",
+            SfxId::Bump => "sounds/bump.wav",
+            SfxId::BrickBreak => "sounds/break.wav",
+            SfxId::PowerDown => "sounds/power_down.wav",
+            SfxId::Death => "sounds/death.wav",
+            SfxId::Hurry => "sounds/hurry.wav",
+            SfxId::Pipe => "sounds/pipe.wav",
+        }
+    }
+
+    // relative to the sfx volume, the powerup chime is meant to stand out
+    fn gain(self) -> f32 {
+        match self {
+            SfxId::Powerup => 1.0,
+            _ => MARIO_NON_MUSIC_VOLUME,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MusicId {
+    Overworld,
+    Hurry,
+    Star,
+}
+
+impl MusicId {
+    pub(crate) const ALL: [MusicId; 3] = [MusicId::Overworld, MusicId::Hurry, MusicId::Star];
+
+    pub(crate) fn path(self) -> &'static str {
+        match self {
+            MusicId::Overworld => "sounds/overworld.wav",
+            MusicId::Hurry => "sounds/overworld_hurry.wav",
+            MusicId::Star => "sounds/star.wav",
+        }
+    }
+
+    // the names level files use for their music
+    pub fn from_name(name: &str) -> Option<MusicId> {
+        match name {
+            "overworld" => Some(MusicId::Overworld),
+            "hurry" => Some(MusicId::Hurry),
+            "star" => Some(MusicId::Star),
+            _ => None,
+        }
+    }
+
+    // what plays once the clock runs low, tracks without a faster version keep playing
+    pub fn hurried(self) -> MusicId {
+        match self {
+            MusicId::Overworld => MusicId::Hurry,
+            other => other,
+        }
+    }
+}
+
+pub const AUDIO_SETTINGS_PATH: &str = "config/audio_settings.json";
+pub const VOLUME_STEP: f32 = 0.1;
+// macroquad can't pause a single sound, so while the game stands still the music drops to this much of its volume
+const DUCKED_MUSIC_FACTOR: f32 = 0.2;
+
+// volumes go from 0.0 to 1.0, the constants in config are only the defaults
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub music_volume: f32,
+    pub sfx_volume: f32,
+    pub muted: bool, // the volumes are kept while muted, unmuting goes back to them
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            music_volume: SOUND_VOLUME,
+            sfx_volume: SOUND_VOLUME,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    // a missing or broken file means the defaults
+    pub fn load(path: &str) -> AudioSettings {
+        let mut settings_string = String::new();
+        if File::open(path).and_then(|mut file| file.read_to_string(&mut settings_string)).is_err() {
+            return AudioSettings::default();
+        }
+        match serde_json::from_str(&settings_string) {
+            Ok(settings) => settings,
+            Err(error) => {
+                warn!("Ignoring {}, failed to parse audio settings: {}", path, error);
+                AudioSettings::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        File::create(path)?.write_all(json.as_bytes())
+    }
+}
+
+// plays the sounds of the assets, ones that failed to load are left out and playing them does nothing
+pub struct AudioManager {
+    assets: Rc<Assets>,
+    current_music: Option<MusicId>,
+    settings: AudioSettings,
+    ducked: bool,
+    music_paused: bool,
+    played_sfx: Option<Vec<SfxId>>, // only kept when recording
+}
+
+impl AudioManager {
+    pub fn new(assets: Rc<Assets>, settings: AudioSettings) -> AudioManager {
+        AudioManager {
+            assets,
+            current_music: None,
+            settings,
+            ducked: false,
+            music_paused: false,
+            played_sfx: None,
+        }
+    }
+
+    // no sounds at all
+    pub fn silent() -> AudioManager {
+        AudioManager::new(Rc::new(Assets::empty()), AudioSettings::default())
+    }
+
+    // silent, but remembers every effect asked for so headless runs can check what would have played
+    pub fn recording() -> AudioManager {
+        AudioManager {
+            played_sfx: Some(Vec::new()),
+            ..AudioManager::silent()
+        }
+    }
+
+    pub fn played_sfx(&self) -> &[SfxId] {
+        self.played_sfx.as_deref().unwrap_or_default()
+    }
+
+    pub fn current_music(&self) -> Option<MusicId> {
+        self.current_music
+    }
+
+    // every call starts its own voice in the mixer, the same effect in quick succession overlaps instead of cutting off
+    pub fn play_sfx(&mut self, id: SfxId) {
+        if let Some(played_sfx) = &mut self.played_sfx {
+            played_sfx.push(id);
+        }
+        if self.settings.muted {
+            return;
+        }
+        if let Some(sound) = self.assets.sfx.get(&id) {
+            play_sound(
+                sound,
+                PlaySoundParams {
+                    volume: id.gain() * self.settings.sfx_volume,
+                    looped: false,
+                },
+            );
+        }
+    }
+
+    // loops the track; asking for the one already playing keeps it going instead of restarting it.
+    // macroquad can't seek, so a track that comes back starts over from the beginning
+    pub fn play_music(&mut self, id: MusicId) {
+        if self.current_music == Some(id) {
+            return;
+        }
+        self.stop_music();
+        if let Some(sound) = self.assets.music.get(&id) {
+            play_sound(
+                sound,
+                PlaySoundParams {
+                    looped: true,
+                    volume: self.music_volume(),
+                },
+            );
+        }
+        self.current_music = Some(id);
+    }
+
+    pub fn stop_music(&mut self) {
+        if let Some(sound) = self.current_music.take().and_then(|id| self.assets.music.get(&id)) {
+            stop_sound(sound);
+        }
+    }
+
+    pub fn settings(&self) -> AudioSettings {
+        self.settings
+    }
+
+    // what the music plays at right now, silence while muted or paused
+    fn music_volume(&self) -> f32 {
+        if self.settings.muted || self.music_paused {
+            0.0
+        } else if self.ducked {
+            self.settings.music_volume * DUCKED_MUSIC_FACTOR
+        } else {
+            self.settings.music_volume
+        }
+    }
+
+    // the playing track changes volume right away, no restart
+    fn apply_music_volume(&self) {
+        if let Some(sound) = self.current_music.and_then(|id| self.assets.music.get(&id)) {
+            set_sound_volume(sound, self.music_volume());
+        }
+    }
+
+    pub fn set_music_volume(&mut self, volume: f32) {
+        self.settings.music_volume = round_to_step(volume);
+        self.apply_music_volume();
+    }
+
+    // cheap to call every frame, the volume is only touched when it changes
+    pub fn set_ducked(&mut self, ducked: bool) {
+        if self.ducked != ducked {
+            self.ducked = ducked;
+            self.apply_music_volume();
+        }
+    }
+
+    // macroquad can't pause a sound, the track runs on silently instead
+    pub fn set_music_paused(&mut self, paused: bool) {
+        if self.music_paused != paused {
+            self.music_paused = paused;
+            self.apply_music_volume();
+        }
+    }
+
+    // the music keeps running silently, so unmuting picks it up where it is
+    pub fn toggle_mute(&mut self) {
+        self.settings.muted = !self.settings.muted;
+        self.apply_music_volume();
+    }
+
+    pub fn set_sfx_volume(&mut self, volume: f32) {
+        self.settings.sfx_volume = round_to_step(volume);
+    }
+}
+
+// keeps repeated steps from drifting off the 10% grid
+fn round_to_step(volume: f32) -> f32 {
+    ((volume / VOLUME_STEP).round() * VOLUME_STEP).clamp(0.0, 1.0)
+}
@@ -0,0 +1,78 @@
+// what benches/world_update.rs needs from inside the crate: a synthetic level, and the collision code
+// that is private otherwise. always built so a plain cargo bench runs, hidden from the docs
+use crate::config::{MARIO_SPRITE_BLOCK_SIZE, MARIO_WORLD_SIZE};
+use crate::collision::{get_collision_response, SurroundingObject};
+use crate::entities::{EnemyType, Object, ObjectType, PowerupType};
+use crate::world::{ObjectReference, World};
+
+const SOLID_TILE: u8 = 10;
+pub const GOOMBAS: usize = 100;
+pub const POWERUPS: usize = 20;
+
+// a flat floor the whole level long, a wall keeps the goombas away from the idle player left of it.
+// past GOOMBAS the level gets longer, so every goomba keeps the same room to walk
+pub fn synthetic_world(goombas: usize) -> World {
+    let height = MARIO_WORLD_SIZE.height;
+    let width = MARIO_WORLD_SIZE.width * goombas.div_ceil(GOOMBAS);
+    let mut world = World::new_headless(height, width);
+    let tiles_per_row = width / MARIO_SPRITE_BLOCK_SIZE;
+    let rows = height / MARIO_SPRITE_BLOCK_SIZE;
+    let mut tiles = vec![0; tiles_per_row * rows];
+    for (index, tile) in tiles.iter_mut().enumerate() {
+        let (x, y) = (index % tiles_per_row, index / tiles_per_row);
+        if y >= rows - 2 || x == 4 {
+            *tile = SOLID_TILE;
+        }
+    }
+    assert!(world.load_tiles(&tiles).is_empty(), "floor tiles overlap");
+    let floor_y = (rows - 3) * MARIO_SPRITE_BLOCK_SIZE;
+    for index in 0..goombas {
+        let x = 6 * MARIO_SPRITE_BLOCK_SIZE + index * (width - 8 * MARIO_SPRITE_BLOCK_SIZE) / goombas;
+        world.add_object(Object::new(x, floor_y, ObjectType::Enemy(EnemyType::Goomba))).expect("goomba cell is free");
+    }
+    for index in 0..POWERUPS {
+        let x = 8 * MARIO_SPRITE_BLOCK_SIZE + index * (width - 10 * MARIO_SPRITE_BLOCK_SIZE) / POWERUPS;
+        world.add_object(Object::new(x, floor_y - 3 * MARIO_SPRITE_BLOCK_SIZE, ObjectType::Powerup(PowerupType::Mushroom))).expect("powerup cell is free");
+    }
+    world.players[0].object.pos.x = MARIO_SPRITE_BLOCK_SIZE as f32;
+    world.players[0].object.pos.y = floor_y as f32;
+    world.time_left_frames = u32::MAX;
+    world
+}
+
+// what every enemy and powerup had around it, in entity order
+#[derive(Default)]
+pub struct Surroundings {
+    per_entity: Vec<Vec<SurroundingObject>>,
+}
+
+// get_surrounding_objects once for every entity
+pub fn look_around(world: &World, surroundings: &mut Surroundings) {
+    surroundings.per_entity.resize_with(world.entities.len(), Vec::new);
+    for (id, buffer) in world.entities.keys().zip(&mut surroundings.per_entity) {
+        World::get_surrounding_objects(
+            &world.grid,
+            &world.entities,
+            &world.coins,
+            &world.blocks,
+            world.entities[id].object(),
+            &ObjectReference::Entity(id),
+            buffer,
+        );
+    }
+}
+
+// get_collision_response for every entity against everything look_around found next to it, returns how many touched
+pub fn resolve(world: &World, surroundings: &Surroundings) -> usize {
+    world
+        .entities
+        .values()
+        .zip(&surroundings.per_entity)
+        .map(|(entity, others)| {
+            others
+                .iter()
+                .filter(|other| get_collision_response(entity.object(), entity.updatable().velocity(), other).collided)
+                .count()
+        })
+        .sum()
+}
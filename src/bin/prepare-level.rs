@@ -0,0 +1,103 @@
+// turns a level image into the tilesheet, level data and tile types the game loads.
+// desktop only, the web build loads what it wrote
+#![cfg_attr(target_arch = "wasm32", allow(dead_code, unused_imports))]
+use std::path::PathBuf;
+
+use rustario_2d::logging;
+use rustario_2d::preparation::{foreground_image_for, prepare, prepare_directory, PreparationOptions, PreparationPaths, TileEncoding};
+
+const USAGE: &str = "usage: prepare-level [image] [--foreground <image>] [--all <directory>] [--level-data <file>] [--tilesheet <file>] [--tile-types <file>] [--hash <file>] [--format plain|rle] [--tolerance <pixels>] [--channel-delta <n>]
+  --format rle writes the tiles run length encoded, the game reads either
+  --tolerance and --channel-delta give tiles that differ in up to <pixels> pixels, or by up to <n> per color channel, the same sprite id
+  --foreground defaults to <image>_fg.png when that exists, drawn in front of the player and enemies
+  --all prepares every level<N>.png in the directory into level_data_<N>.json next to --level-data, with a levels.json manifest";
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    logging::init();
+    let mut paths = PreparationPaths::default();
+    let mut directory = None;
+    let mut options = PreparationOptions::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let target = match arg.as_str() {
+            "--level-data" => &mut paths.level_data,
+            "--tilesheet" => &mut paths.tilesheet,
+            "--tile-types" => &mut paths.tile_types,
+            "--hash" => &mut paths.source_hash,
+            "--all" => directory.get_or_insert_with(PathBuf::new),
+            "--foreground" => paths.foreground_image.get_or_insert_with(PathBuf::new),
+            "--format" => {
+                options.encoding = match args.next().as_deref() {
+                    Some("plain") => TileEncoding::Plain,
+                    Some("rle") => TileEncoding::Rle,
+                    _ => {
+                        eprintln!("--format is plain or rle\n{}", USAGE);
+                        std::process::exit(2);
+                    }
+                };
+                continue;
+            }
+            "--tolerance" => {
+                options.tolerance.pixels = number_argument(&arg, args.next());
+                continue;
+            }
+            "--channel-delta" => {
+                options.tolerance.channel_delta = number_argument(&arg, args.next());
+                continue;
+            }
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if !arg.starts_with("--") => {
+                paths.image = PathBuf::from(arg);
+                continue;
+            }
+            _ => {
+                eprintln!("Unknown option {}\n{}", arg, USAGE);
+                std::process::exit(2);
+            }
+        };
+        let Some(value) = args.next() else {
+            eprintln!("{} needs a path\n{}", arg, USAGE);
+            std::process::exit(2);
+        };
+        *target = PathBuf::from(value);
+    }
+    if let Some(directory) = directory {
+        match prepare_directory(&directory, &paths, options) {
+            Ok(manifest) => {
+                for entry in manifest {
+                    println!("{} -> {}", entry.name, entry.level_data.display());
+                }
+            }
+            Err(error) => {
+                eprintln!("Failed to prepare the levels in {}: {}", directory.display(), error);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if paths.foreground_image.is_none() {
+        paths.foreground_image = foreground_image_for(&paths.image);
+    }
+    if prepare(&paths, options) {
+        println!("Prepared {} into {}", paths.image.display(), paths.level_data.display());
+    } else {
+        println!("{} is unchanged, nothing to do", paths.image.display());
+    }
+}
+
+fn number_argument<T: std::str::FromStr>(flag: &str, value: Option<String>) -> T {
+    match value.and_then(|value| value.parse().ok()) {
+        Some(number) => number,
+        None => {
+            eprintln!("{} needs a number\n{}", flag, USAGE);
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use macroquad::prelude::*;
+
+use crate::preparation::Room;
+
+#[derive(Clone)]
+pub struct Camera {
+    pub(crate) x: usize,
+    pub(crate) y: usize,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    // the camera never shows outside the current room, the whole level if it has no rooms
+    pub(crate) min_x: usize,
+    pub(crate) max_x: usize,
+    pub(crate) backward_scroll: bool, // follow the player back left instead of only ever scrolling right
+}
+
+impl Camera {
+    pub(crate) fn new(width: usize, height: usize, world_width: usize) -> Camera {
+        Camera {
+            x: 0,
+            y: 0,
+            width,
+            height,
+            min_x: 0,
+            max_x: world_width,
+            backward_scroll: false,
+        }
+    }
+
+    pub(crate) fn update(&mut self, player_x: usize, player_y: usize) {
+        let new_x = player_x.saturating_sub(self.width / 4);
+        if new_x >= self.x || self.backward_scroll {
+            self.x = new_x;
+            self.clamp_to_bounds();
+        }    
+        self.y = player_y.saturating_sub(self.height);
+    }
+
+    pub(crate) fn clamp_to_bounds(&mut self) {
+        self.x = self.x.clamp(self.min_x, self.max_x.saturating_sub(self.width).max(self.min_x));
+    }
+
+    // a warp moved the player, the camera jumps along instead of scrolling there
+    pub(crate) fn enter_room(&mut self, room: &Room, player_x: usize) {
+        self.min_x = room.x;
+        self.max_x = room.x + room.width;
+        self.x = player_x.saturating_sub(self.width / 4);
+        self.clamp_to_bounds();
+    }
+}
@@ -0,0 +1,627 @@
+use macroquad::prelude::*;
+
+use crate::config::{FIREBALL_BOUNCE_VELOCITY, HEAD_BUMP_MIN_OVERLAP, STOMP_BOUNCINESS};
+use crate::entities::{BlockType, ContactPolicy, EnemyType, KoopaState, Object, ObjectType};
+use crate::physics::PhysicsConfig;
+use crate::world::ObjectReference;
+
+#[derive(Debug)]
+pub(crate) enum CollisionType {
+    PlayerWithBlock,
+    PlayerKillEnemy,
+    PlayerHitBy,
+    PlayerWithPowerupBlock,
+    PlayerWithPowerup,
+    PlayerWithCoin,
+    EnemyWithBlock,
+    EnemyWithEnemy,
+    FireballKillEnemy,
+    PlayerKickShell,
+    ShellKillEnemy,
+}
+pub(crate) struct CollisionResponse {
+    pub(crate) new_pos: Vec2,
+    pub(crate) new_velocity: Vec2,
+    pub(crate) collided: bool,
+    pub(crate) collision_type: Option<CollisionType>,
+    pub(crate) kind: ResponseKind,
+    pub(crate) contact: Option<Contact>, // None when nothing overlapped
+}
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Contact {
+    pub(crate) normal: Vec2, // unit axis pointing away from the other object, the direction we were pushed
+    pub(crate) depth: f32, // penetration along the normal before resolution
+}
+impl Contact {
+    // hit from below with the head, and the block is the one above the center of the head. jumping up past a block's
+    // corner also pushes down, the head has to be under the block more than it is inside it
+    pub(crate) fn is_head_bump(&self, object: &Object, block: &Object) -> bool {
+        let (head, block_hitbox) = (object.hitbox(), block.hitbox());
+        let head_center_x = head.center().x;
+        let x_overlap = head.right().min(block_hitbox.right()) - head.left().max(block_hitbox.left());
+        self.normal.y > 0.0
+            && head_center_x >= block_hitbox.left()
+            && head_center_x <= block_hitbox.right()
+            && x_overlap >= HEAD_BUMP_MIN_OVERLAP
+            && x_overlap > self.depth
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ResponseKind {
+    Stop,        // kill all velocity
+    Slide,       // take new_velocity as is, the resolved axis is zeroed
+    Bounce(f32), // reflect whatever the resolution stopped, scaled by the restitution factor
+    Overlap,     // trigger contact: report the collision type but push nothing
+}
+pub(crate) fn overlap_area(object: &Object, other: &Object) -> f32 {
+    let (object, other) = (object.hitbox(), other.hitbox());
+    let x_overlap = (object.x + object.w).min(other.x + other.w) - object.x.max(other.x);
+    let y_overlap = (object.y + object.h).min(other.y + other.h) - object.y.max(other.y);
+    x_overlap.max(0.0) * y_overlap.max(0.0)
+}
+pub(crate) fn get_collision_response(
+    object: &Object,
+    velocity: &Vec2,
+    other: &SurroundingObject,
+) -> CollisionResponse { 
+    let relative_direction_to_object = other.relative_direction;
+    let (self_hitbox, other_hitbox) = (object.hitbox(), other.object.hitbox());
+    let self_center = self_hitbox.center();
+    let other_center = other_hitbox.center();
+
+    let x_overlap =
+        (self_hitbox.w + other_hitbox.w) / 2.0 - (self_center.x - other_center.x).abs();
+    let y_overlap =
+        (self_hitbox.h + other_hitbox.h) / 2.0 - (self_center.y - other_center.y).abs();
+
+    let mut new_pos = object.pos;
+    let mut new_velocity = *velocity;
+    let mut contact = None;
+
+    if x_overlap > 0.0 && y_overlap > 0.0 {
+        match relative_direction_to_object {
+            (0, -1) | (0, 1) => {
+                new_pos.x -= x_overlap * relative_direction_to_object.1 as f32;
+                if velocity.x.signum() == relative_direction_to_object.1 as f32 {
+                    new_velocity.x = 0.0;
+                }
+                contact = Some(Contact { normal: Vec2::new(-relative_direction_to_object.1 as f32, 0.0), depth: x_overlap });
+            },
+            (-1, 0) | (1, 0) => {
+                new_pos.y -= y_overlap * relative_direction_to_object.0 as f32;
+                new_velocity.y = 0.0;
+                contact = Some(Contact { normal: Vec2::new(0.0, -relative_direction_to_object.0 as f32), depth: y_overlap });
+            },
+            _ => {
+                // diagonal neighbours: after integration the grid direction can disagree with where the
+                // other object actually is, so push away along the real center to center delta
+                let center_delta = self_center - other_center;
+                if x_overlap < y_overlap  {
+                    let push_direction = center_delta.x.signum();
+                    new_pos.x += x_overlap * push_direction;
+                    if velocity.x.signum() == -push_direction {
+                        new_velocity.x = 0.0;
+                    }
+                    contact = Some(Contact { normal: Vec2::new(push_direction, 0.0), depth: x_overlap });
+                } else {
+                    let push_direction = center_delta.y.signum();
+                    new_pos.y += y_overlap * push_direction;
+                    if velocity.y.signum() == -push_direction {
+                        new_velocity.y = 0.0;
+                    }
+                    contact = Some(Contact { normal: Vec2::new(0.0, push_direction), depth: y_overlap });
+                }
+
+            }
+        }
+    }
+
+    CollisionResponse {
+        new_pos,
+        new_velocity,
+        collided: x_overlap > 0.0 && y_overlap > 0.0,
+        kind: ResponseKind::Slide,
+        contact,
+        collision_type: None,
+    }
+}
+pub(crate) struct Surface {
+    pub(crate) friction: f32,
+    pub(crate) conveyor_velocity: f32,
+    pub(crate) carry: Vec2, // frame displacement of the supporting object, added to the rider before it integrates
+}
+impl Surface {
+    pub(crate) fn air(physics: &PhysicsConfig) -> Surface {
+        Surface {
+            friction: physics.air_friction,
+            conveyor_velocity: 0.0,
+            carry: Vec2::ZERO,
+        }
+    }
+    pub(crate) fn of(support: &Object, physics: &PhysicsConfig) -> Surface {
+        // every block is plain ground for now, ice and conveyor blocks hook in here
+        Surface {
+            friction: physics.ground_friction,
+            conveyor_velocity: 0.0,
+            carry: support.displacement,
+        }
+    }
+}
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum CollisionLayer {
+    Player,
+    Enemy,
+    Block,
+    Powerup,
+    Projectile,
+    Trigger,
+}
+impl CollisionLayer {
+    pub(crate) fn of(object_type: ObjectType) -> CollisionLayer {
+        match object_type {
+            ObjectType::Block(_) => CollisionLayer::Block,
+            ObjectType::Enemy(_) => CollisionLayer::Enemy,
+            ObjectType::Powerup(_) => CollisionLayer::Powerup,
+            ObjectType::Coin => CollisionLayer::Trigger,
+            ObjectType::Player => CollisionLayer::Player,
+            ObjectType::Fireball => CollisionLayer::Projectile,
+        }
+    }
+}
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct CollisionMask(u8);
+impl CollisionMask {
+    pub(crate) const fn of(layers: &[CollisionLayer]) -> CollisionMask {
+        let mut bits = 0;
+        let mut i = 0;
+        while i < layers.len() {
+            bits |= 1 << layers[i] as u8;
+            i += 1;
+        }
+        CollisionMask(bits)
+    }
+    pub(crate) fn contains(&self, layer: CollisionLayer) -> bool {
+        self.0 & (1 << layer as u8) != 0
+    }
+}
+
+// what happens when an entity runs into something, picked per pair of object types by get_collision_handler
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CollisionBehavior {
+    DoNothing,
+    Powerup,
+    Coin,
+    Block,
+    Enemy,
+    EnemyBlock,
+    PlayerEnemy,
+    PlayerEnemyInvincible, // just hit, stomps still count but touching one from the side does nothing
+    Fireball,
+    Shell, // a kicked one
+}
+
+impl CollisionBehavior {
+    pub(crate) fn resolve(self, object: &Object, velocity: &Vec2, other: &SurroundingObject) -> CollisionResponse {
+        match self {
+            CollisionBehavior::DoNothing => resolve_do_nothing_collision(object, velocity, other),
+            CollisionBehavior::Powerup => resolve_powerup_collision(object, velocity, other),
+            CollisionBehavior::Coin => resolve_coin_collision(object, velocity, other),
+            CollisionBehavior::Block => resolve_block_collision(object, velocity, other),
+            CollisionBehavior::Enemy => resolve_enemy_collision(object, velocity, other),
+            CollisionBehavior::EnemyBlock => resolve_enemy_block_collision(object, velocity, other),
+            CollisionBehavior::PlayerEnemy => resolve_player_enemy_collision(object, velocity, other),
+            CollisionBehavior::PlayerEnemyInvincible => resolve_player_enemy_invincible_collision(object, velocity, other),
+            CollisionBehavior::Fireball => resolve_fireball_collision(object, velocity, other),
+            CollisionBehavior::Shell => resolve_shell_collision(object, velocity, other),
+        }
+    }
+}
+pub(crate) fn resolve_do_nothing_collision(object: &Object, velocity: &Vec2, _: &SurroundingObject) -> CollisionResponse {
+    CollisionResponse {
+        new_pos: object.pos,
+        new_velocity: *velocity,
+        collided: false,
+        kind: ResponseKind::Slide,
+        contact: None,
+        collision_type: None,
+    }
+}
+pub(crate) fn resolve_powerup_collision(object: &Object, velocity: &Vec2, other: &SurroundingObject) -> CollisionResponse {
+    let collision_response = get_collision_response(object, velocity, other);
+
+    if collision_response.collided {
+        return CollisionResponse {
+            new_pos: object.pos,
+            new_velocity: *velocity,
+            collided: collision_response.collided,
+            kind: ResponseKind::Overlap,
+            contact: collision_response.contact,
+            collision_type: Some(CollisionType::PlayerWithPowerup),
+        };
+    }
+    collision_response
+}
+pub(crate) fn resolve_coin_collision(object: &Object, velocity: &Vec2, other: &SurroundingObject) -> CollisionResponse {
+    let collision_response = get_collision_response(object, velocity, other);
+    CollisionResponse {
+        new_pos: object.pos,
+        new_velocity: *velocity,
+        collided: collision_response.collided,
+        kind: ResponseKind::Overlap,
+        contact: collision_response.contact,
+        collision_type: match collision_response.collided {
+            true => Some(CollisionType::PlayerWithCoin),
+            false => None,
+        },
+    }
+}
+pub(crate) fn resolve_block_collision(object: &Object, velocity: &Vec2, other: &SurroundingObject) -> CollisionResponse {
+    let collision_response = get_collision_response(object, velocity, other);
+    match other.object.object_type {
+        ObjectType::Block(BlockType::Block | BlockType::Launcher) => {
+            if collision_response.collided {
+                return CollisionResponse {
+                    new_pos: collision_response.new_pos,
+                    new_velocity: collision_response.new_velocity,
+                    collided: collision_response.collided,
+                    kind: ResponseKind::Slide,
+                    contact: collision_response.contact,
+                    collision_type: Some(CollisionType::PlayerWithBlock),
+                };
+            }
+        }
+        ObjectType::Block(BlockType::PowerupBlock) => {
+            if collision_response.collided {
+
+                return CollisionResponse {
+                    new_pos: collision_response.new_pos,
+                    new_velocity: collision_response.new_velocity,
+                    collided: collision_response.collided,
+                    kind: ResponseKind::Slide,
+                    contact: collision_response.contact,
+                    collision_type: {
+                        let head_bump = collision_response
+                            .contact
+                            .is_some_and(|contact| contact.is_head_bump(object, &other.object));
+                        if head_bump && velocity.y < 0.0 && object.object_type == ObjectType::Player {
+                            Some(CollisionType::PlayerWithPowerupBlock)
+                        } else {
+                            Some(CollisionType::PlayerWithBlock)
+                        }
+                    },
+                };
+            }
+        }
+        _ => {}
+    }
+    collision_response
+}
+pub(crate) fn resolve_enemy_collision(object: &Object, velocity: &Vec2, other: &SurroundingObject) -> CollisionResponse {
+    let collision_response = get_collision_response(object, velocity, other);
+    let new_velo = Vec2::new(-velocity.x, velocity.y);
+    let new_pos = Vec2::new(object.pos.x, object.pos.y);
+
+    CollisionResponse {
+        new_pos: new_pos,       // move goomba back a bit, otherwise it will get stuck
+        new_velocity: new_velo, // reverse direction, typical mario goomba | goomba collision
+        collided: collision_response.collided,
+        kind: ResponseKind::Slide,
+        contact: collision_response.contact,
+        collision_type: match collision_response.collided {
+            true => Some(CollisionType::EnemyWithEnemy),
+            false => None,
+        },
+    }
+}
+pub(crate) fn resolve_enemy_block_collision(object: &Object, velocity: &Vec2, other: &SurroundingObject) -> CollisionResponse {
+    let collision_response = get_collision_response(object, velocity, other);
+    // a wall it walks into turns it around, pushed out of the wall first so it doesn't stay half inside.
+    // pushed sideways while walking away (or by the floor) it keeps going
+    let walked_into_wall = collision_response.contact.is_some_and(|contact| contact.normal.x * velocity.x < 0.0);
+    if walked_into_wall {
+        return CollisionResponse {
+            new_pos: collision_response.new_pos,
+            new_velocity: Vec2::new(-velocity.x, velocity.y),
+            collided: collision_response.collided,
+            kind: ResponseKind::Slide,
+            contact: collision_response.contact,
+            collision_type: Some(CollisionType::EnemyWithBlock),
+        };
+    }
+    collision_response
+}
+pub(crate) fn resolve_player_enemy_collision(object: &Object, velocity: &Vec2, other: &SurroundingObject) -> CollisionResponse {
+    let collision_response = get_collision_response(object, velocity, other);
+    if collision_response.collided {
+        // on top if the feet were at or above the enemy's head before this frame's move, by the hitboxes not the sprites
+        let (player, enemy) = (object.hitbox(), other.object.hitbox());
+        let landed_on_top = player.bottom() - velocity.y <= enemy.top();
+        let top_policy = match other.object.object_type {
+            ObjectType::Enemy(enemy_type) => enemy_type.contact_policy(),
+            _ => ContactPolicy::Stompable,
+        };
+        if top_policy == ContactPolicy::Solid {
+            // pushed out like by a block, on top ground_surface keeps us there afterwards. walking into a shell kicks it
+            let kicked = !landed_on_top && other.object.object_type == ObjectType::Enemy(EnemyType::Koopa(KoopaState::Shell));
+            return CollisionResponse {
+                collision_type: kicked.then_some(CollisionType::PlayerKickShell),
+                ..collision_response
+            };
+        }
+        if landed_on_top && top_policy == ContactPolicy::Stompable {
+
+            return CollisionResponse {
+                new_pos: collision_response.new_pos,
+                new_velocity: Vec2::new(velocity.x, 0.0), // stopped vertically, the bounce reflects the fall
+                collided: collision_response.collided,
+                kind: ResponseKind::Bounce(STOMP_BOUNCINESS),
+                contact: collision_response.contact,
+                collision_type: Some(CollisionType::PlayerKillEnemy),
+            };
+        } else {
+            return CollisionResponse {
+                new_pos: collision_response.new_pos,
+                new_velocity: collision_response.new_velocity,
+                collided: collision_response.collided,
+                kind: ResponseKind::Slide,
+                contact: collision_response.contact,
+                collision_type: Some(CollisionType::PlayerHitBy),
+            };
+        }
+    }
+    return collision_response;
+}
+pub(crate) fn resolve_player_enemy_invincible_collision(object: &Object, velocity: &Vec2, other: &SurroundingObject) -> CollisionResponse {
+    let collision_response = resolve_player_enemy_collision(object, velocity, other);
+    match collision_response.collision_type {
+        Some(CollisionType::PlayerHitBy) => resolve_do_nothing_collision(object, velocity, other),
+        _ => collision_response,
+    }
+}
+// an enemy it touches dies and the fireball stops dead. the floor bounces it back up, a wall or a ceiling stops it
+// like any other object would be
+pub(crate) fn resolve_fireball_collision(object: &Object, velocity: &Vec2, other: &SurroundingObject) -> CollisionResponse {
+    let collision_response = get_collision_response(object, velocity, other);
+    if !collision_response.collided {
+        return collision_response;
+    }
+    match other.object.object_type {
+        // stopped by the first one already, it takes only one enemy with it
+        ObjectType::Enemy(_) if velocity.x == 0.0 => resolve_do_nothing_collision(object, velocity, other),
+        ObjectType::Enemy(_) => CollisionResponse {
+            new_pos: object.pos,
+            new_velocity: Vec2::ZERO,
+            collided: true,
+            kind: ResponseKind::Stop,
+            contact: collision_response.contact,
+            collision_type: Some(CollisionType::FireballKillEnemy),
+        },
+        _ => {
+            let landed = collision_response.contact.is_some_and(|contact| contact.normal.y < 0.0);
+            CollisionResponse {
+                new_velocity: if landed {
+                    Vec2::new(velocity.x, -FIREBALL_BOUNCE_VELOCITY)
+                } else {
+                    collision_response.new_velocity
+                },
+                ..collision_response
+            }
+        }
+    }
+}
+// a kicked shell turns around at walls like a walking enemy, every enemy it runs into dies and it keeps going
+pub(crate) fn resolve_shell_collision(object: &Object, velocity: &Vec2, other: &SurroundingObject) -> CollisionResponse {
+    match other.object.object_type {
+        ObjectType::Enemy(_) => {
+            let collision_response = get_collision_response(object, velocity, other);
+            CollisionResponse {
+                new_pos: object.pos,
+                new_velocity: *velocity,
+                collided: collision_response.collided,
+                kind: ResponseKind::Overlap,
+                contact: collision_response.contact,
+                collision_type: collision_response.collided.then_some(CollisionType::ShellKillEnemy),
+            }
+        }
+        _ => resolve_enemy_block_collision(object, velocity, other),
+    }
+}
+#[derive(Clone, Copy)]
+pub(crate) struct SurroundingObject {
+    pub(crate) object: Object,
+    pub(crate) reference: ObjectReference, // where the object lives, each one is only reported once
+    pub(crate) relative_direction: (isize, isize),
+}
+impl SurroundingObject {
+    pub(crate) fn new(object: Object, reference: ObjectReference, relative_direction: (isize, isize)) -> SurroundingObject {
+        assert!(relative_direction.0.abs() <= 1 && relative_direction.1.abs() <= 1);
+        assert!(relative_direction != (0, 0));
+        SurroundingObject {
+            object,
+            reference,
+            relative_direction,
+        }
+    }
+}
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RayHit {
+    pub(crate) reference: ObjectReference,
+    pub(crate) distance: f32, // along the ray to where it enters the block, the point is origin + dir * distance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{MARIO_SPRITE_BLOCK_SIZE, PLAYER_HITBOX_WIDTH};
+
+    const TILE: f32 = MARIO_SPRITE_BLOCK_SIZE as f32;
+    const BLOCK: Vec2 = Vec2::new(64.0, 64.0);
+    const DIRECTIONS: [(isize, isize); 8] = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+
+    fn block() -> Object {
+        let mut block = Object::new(0, 0, ObjectType::Block(BlockType::Block));
+        block.pos = BLOCK;
+        block
+    }
+
+    // a player with the block in `direction` (row, column) from it, sunk `overlap` pixels into it on each axis it
+    // approaches along, lined up with it on the others
+    fn player_next_to_block(direction: (isize, isize), overlap: Vec2) -> Object {
+        let mut player = Object::new(0, 0, ObjectType::Player);
+        player.pos = BLOCK
+            - Vec2::new(direction.1 as f32 * (TILE - overlap.x), direction.0 as f32 * (TILE - overlap.y));
+        player
+    }
+
+    fn respond(player: &Object, velocity: Vec2, direction: (isize, isize)) -> CollisionResponse {
+        get_collision_response(player, &velocity, &SurroundingObject::new(block(), ObjectReference::Block(0), direction))
+    }
+
+    #[test]
+    fn every_direction_pushes_out_of_the_block_and_away_from_it() {
+        let speeds = [0.0, 0.5, 2.8, 6.0];
+        for direction in DIRECTIONS {
+            for overlap in [Vec2::new(3.0, 5.0), Vec2::new(5.0, 3.0), Vec2::new(1.0, 1.0)] {
+                for (vx, vy) in speeds.iter().flat_map(|&x| speeds.iter().flat_map(move |&y| [(x, y), (-x, y), (x, -y), (-x, -y)])) {
+                    let velocity = Vec2::new(vx, vy);
+                    let player = player_next_to_block(direction, overlap);
+                    let response = respond(&player, velocity, direction);
+                    let case = format!("{:?} sunk {} moving {}", direction, overlap, velocity);
+                    assert!(response.collided, "{}", case);
+                    let contact = response.contact.unwrap();
+                    let mut resolved = player;
+                    resolved.pos = response.new_pos;
+                    assert_eq!(overlap_area(&resolved, &block()), 0.0, "{} ends up at {}", case, response.new_pos);
+                    // pushed along the contact normal only, which points from the block towards the player
+                    let push = response.new_pos - player.pos;
+                    assert_eq!(push, contact.normal * contact.depth, "{}", case);
+                    let away = player.hitbox().center() - block().hitbox().center();
+                    assert!(contact.normal.dot(away) > 0.0, "{} pushed {} into the block", case, contact.normal);
+                    // whatever moved into the block along the normal stops, everything else keeps going
+                    let into_block = velocity.dot(contact.normal) < 0.0;
+                    let stopped = if contact.normal.x != 0.0 { response.new_velocity.x } else { response.new_velocity.y };
+                    if into_block {
+                        assert_eq!(stopped, 0.0, "{}", case);
+                    }
+                    if contact.normal.x != 0.0 {
+                        assert_eq!(response.new_velocity.y, velocity.y, "{}", case);
+                    } else {
+                        assert_eq!(response.new_velocity.x, velocity.x, "{}", case);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_diagonal_resolves_along_the_shallower_overlap() {
+        for direction in DIRECTIONS.into_iter().filter(|(row, column)| *row != 0 && *column != 0) {
+            let sideways = respond(&player_next_to_block(direction, Vec2::new(2.0, 6.0)), Vec2::ZERO, direction);
+            assert_eq!(sideways.contact.unwrap().normal, Vec2::new(-direction.1 as f32, 0.0), "{:?}", direction);
+            let vertical = respond(&player_next_to_block(direction, Vec2::new(6.0, 2.0)), Vec2::ZERO, direction);
+            assert_eq!(vertical.contact.unwrap().normal, Vec2::new(0.0, -direction.0 as f32), "{:?}", direction);
+        }
+    }
+
+    #[test]
+    fn a_diagonal_pushes_by_where_the_block_is_not_by_the_grid() {
+        // integration carried the player past the block's center, the grid still has the block down and to the right.
+        // pushing left by the grid would leave it deeper in the block than before
+        let mut player = Object::new(0, 0, ObjectType::Player);
+        player.pos = BLOCK + Vec2::new(6.0, -4.0);
+        let response = respond(&player, Vec2::new(-2.0, 4.0), (1, 1));
+        assert_eq!(response.contact.unwrap().normal, Vec2::new(1.0, 0.0));
+        assert_eq!(response.new_pos, Vec2::new(BLOCK.x + TILE, player.pos.y));
+        assert_eq!(response.new_velocity, Vec2::new(0.0, 4.0));
+    }
+
+    #[test]
+    fn objects_that_only_touch_do_not_collide() {
+        for direction in DIRECTIONS {
+            let player = player_next_to_block(direction, Vec2::ZERO);
+            let response = respond(&player, Vec2::new(1.0, 1.0), direction);
+            assert!(!response.collided, "{:?}", direction);
+            assert!(response.contact.is_none());
+            assert_eq!(response.new_pos, player.pos);
+        }
+    }
+
+    // a player under the block with `x_overlap` pixels of its head under it, from the left, and `depth` pixels into it
+    fn head_under_block(x_overlap: f32, head_width: f32, depth: f32) -> (Contact, Object) {
+        let mut player = Object::new(0, 0, ObjectType::Player);
+        let inset = (TILE - head_width) / 2.0;
+        player.hitbox = Some(Rect::new(inset, 0.0, head_width, TILE));
+        player.pos = Vec2::new(BLOCK.x + x_overlap - head_width - inset, BLOCK.y + TILE - depth);
+        (Contact { normal: Vec2::new(0.0, 1.0), depth }, player)
+    }
+
+    #[test]
+    fn a_clean_head_hit_bumps_the_block() {
+        for x_overlap in [PLAYER_HITBOX_WIDTH, PLAYER_HITBOX_WIDTH / 2.0 + 1.0] {
+            let (contact, player) = head_under_block(x_overlap, PLAYER_HITBOX_WIDTH, 2.0);
+            assert!(contact.is_head_bump(&player, &block()), "{} under it", x_overlap);
+        }
+    }
+
+    #[test]
+    fn grazing_the_corner_on_the_way_up_does_not_bump() {
+        // the middle of the head is under the block, but it is further into it than under it
+        let (contact, player) = head_under_block(PLAYER_HITBOX_WIDTH / 2.0 + 1.0, PLAYER_HITBOX_WIDTH, 8.0);
+        assert!(!contact.is_head_bump(&player, &block()));
+        // the middle of the head is past the corner
+        let (contact, player) = head_under_block(PLAYER_HITBOX_WIDTH / 2.0 - 1.0, PLAYER_HITBOX_WIDTH, 1.0);
+        assert!(!contact.is_head_bump(&player, &block()));
+    }
+
+    #[test]
+    fn a_side_hit_does_not_bump() {
+        let (contact, player) = head_under_block(2.0, PLAYER_HITBOX_WIDTH, 6.0);
+        for normal in [Vec2::new(-1.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(0.0, -1.0)] {
+            assert!(!Contact { normal, ..contact }.is_head_bump(&player, &block()), "{}", normal);
+        }
+        // and resolving it says it was from the side
+        let response = respond(&player, Vec2::new(1.0, -4.0), (-1, 1));
+        assert_eq!(response.contact.unwrap().normal, Vec2::new(-1.0, 0.0));
+        assert!(!response.contact.unwrap().is_head_bump(&player, &block()));
+    }
+
+    #[test]
+    fn a_narrow_head_needs_the_minimum_overlap() {
+        // narrow enough that its middle is under the block with less than the minimum of it there
+        let head_width = HEAD_BUMP_MIN_OVERLAP + 2.0;
+        let (contact, player) = head_under_block(HEAD_BUMP_MIN_OVERLAP - 0.5, head_width, 0.25);
+        assert!(!contact.is_head_bump(&player, &block()));
+        let (contact, player) = head_under_block(HEAD_BUMP_MIN_OVERLAP, head_width, 0.25);
+        assert!(contact.is_head_bump(&player, &block()));
+    }
+
+    // a Goomba on the block's spot whose body is 4 pixels lower than its sprite, like a shell
+    fn low_enemy() -> Object {
+        let mut enemy = Object::new(0, 0, ObjectType::Enemy(EnemyType::Goomba));
+        enemy.pos = BLOCK;
+        enemy.hitbox = Some(Rect::new(1.0, 4.0, TILE - 2.0, TILE - 4.0));
+        enemy
+    }
+
+    fn meet_enemy(player: &Object, velocity: Vec2, direction: (isize, isize)) -> Option<CollisionType> {
+        let enemy = SurroundingObject::new(low_enemy(), ObjectReference::Entity(Default::default()), direction);
+        resolve_player_enemy_collision(player, &velocity, &enemy).collision_type
+    }
+
+    #[test]
+    fn landing_on_the_body_stomps_even_below_the_sprites_top() {
+        // feet 2 pixels into the body after falling 3, they were above it but already below the sprite's top
+        let mut player = Object::new(0, 0, ObjectType::Player);
+        player.pos = Vec2::new(BLOCK.x, BLOCK.y + 4.0 + 2.0 - TILE);
+        assert!(matches!(meet_enemy(&player, Vec2::new(0.0, 3.0), (1, 0)), Some(CollisionType::PlayerKillEnemy)));
+        // falling 1 they were already inside it
+        assert!(matches!(meet_enemy(&player, Vec2::new(0.0, 1.0), (1, 0)), Some(CollisionType::PlayerHitBy)));
+    }
+
+    #[test]
+    fn walking_into_the_enemy_is_a_hit() {
+        let mut player = Object::new(0, 0, ObjectType::Player);
+        player.pos = Vec2::new(BLOCK.x - TILE + 2.0, BLOCK.y);
+        assert!(matches!(meet_enemy(&player, Vec2::new(2.0, 0.0), (0, 1)), Some(CollisionType::PlayerHitBy)));
+    }
+}
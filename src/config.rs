@@ -0,0 +1,69 @@
+pub const GRAVITY: usize = 16;
+pub struct WorldDimensions {
+    pub width: usize,
+    pub height: usize,
+}
+pub const MARIO_NON_MUSIC_VOLUME: f32 = 0.1;
+pub const SOUND_VOLUME: f32 = 0.3;
+pub const SCALE_IMAGE_FACTOR: usize = 2;
+pub const MARIO_SPRITE_BLOCK_SIZE: usize = 16;
+pub const MARIO_WORLD_SIZE: WorldDimensions = WorldDimensions {
+    width: 3392,
+    height: 224,
+};
+
+pub const PHYSICS_FRAME_PER_SECOND: f32 = 60.0;
+pub const PHYSICS_FRAME_TIME: f32 = 1.0 / PHYSICS_FRAME_PER_SECOND;
+pub const MAX_PHYSICS_STEPS_PER_FRAME: u32 = 5; // after a longer hitch the game slows down instead of catching up all at once
+pub const REWIND_SECONDS: f32 = 5.0; // how far back holding the rewind key goes
+pub const REWIND_SNAPSHOT_FRAMES: u32 = 4; // physics frames between rewind snapshots
+pub const MAX_RUN_VELOCITY_X: f32 = 3.5; // top speed while holding run
+pub const MAX_WALK_VELOCITY_X: f32 = 2.8;
+pub const ACCELERATION: f32 = 3.0;
+pub const RUN_ACCELERATION: f32 = 3.5; // while holding run
+pub const GROUND_FRICTION: f32 = 2.0;
+pub const AIR_FRICTION: f32 = 1.0;
+pub const JUMP_STRENGTH: f32 = 8.0; // with the lighter gravity while rising, what carries a held jump up
+pub const RISING_GRAVITY_FACTOR: f32 = 0.75; // while a jump rises with the key held
+pub const MAX_FALL_VELOCITY: f32 = 5.0; // pixels per physics frame, below a tile so nothing falls through a block
+pub const JUMP_BUFFER_FRAMES: u32 = 5; // a press this many frames before landing still jumps on touchdown
+pub const COYOTE_FRAMES: u32 = 5; // jumping still works this many frames after walking off a ledge
+pub const JUMP_HOLD_FRAMES: u32 = 45; // holding jump carries a jump higher for at most this many frames after take off
+pub const FAST_FALL_GRAVITY_FACTOR: f32 = 1.5; // holding down while airborne
+pub const LEVEL_TIME: u32 = 400; // in time units, like the original's clock
+pub const TIME_UNIT_FRAMES: u32 = 24; // physics frames per time unit
+pub const HURRY_UP_TIME: u32 = 100; // from here on the warning plays and the music speeds up
+pub const MAX_PLAYERS: usize = 2; // co-op is two players on one keyboard
+pub const HIT_INVINCIBLE_FRAMES: u32 = 120; // physics frames a player blinks and can't be hit again after a hit, 2 seconds
+pub const DYING_TIME: f32 = 2.0; // seconds between dying and game over, the length of the death jingle
+pub const DEATH_PAUSE_FRAMES: usize = 30; // physics frames a dead player hangs in the air before the hop
+pub const DEATH_HOP_VELOCITY: f32 = 4.0; // pixels per physics frame up, gravity takes the player down through the floor
+pub const STAR_POWER_FRAMES: u32 = 600; // invincibility from a star, 10 seconds
+pub const STAR_BOUNCE_VELOCITY: f32 = 4.0; // pixels per physics frame up off the floor, a star bounces about two tiles high
+pub const STOMP_BOUNCINESS: f32 = 1.0; // stomping reflects the fall speed
+pub const SHELL_WAKE_FRAMES: u32 = 300; // physics frames a stomped Koopa stays in its shell before walking again, 5 seconds
+pub const SHELL_SPEED: f32 = 2.0; // pixels per physics frame, a kicked shell is twice as fast as a walking enemy
+pub const KICK_GRACE_FRAMES: u32 = 10; // physics frames a shell the player just kicked can't hurt them, it is still right next to them
+pub const PLANT_SPEED: f32 = 0.5; // pixels per physics frame a Piranha Plant rises and sinks, out of its pipe in 48 frames
+pub const PLANT_OUT_FRAMES: u32 = 90; // physics frames a Piranha Plant stays all the way out
+pub const PLANT_HIDDEN_FRAMES: u32 = 90; // physics frames at least between sinking into the pipe and coming out again
+pub const LAUNCHER_COOLDOWN_FRAMES: u32 = 180; // physics frames between two shots of a Bullet Bill launcher, 3 seconds
+pub const BULLET_SPEED: f32 = 1.5; // pixels per physics frame, sideways
+pub const LAKITU_SPEED: f32 = 4.0; // pixels per physics frame, a little faster than the player runs so it keeps up
+pub const LAKITU_THROW_FRAMES: u32 = 240; // physics frames between two Spiny eggs, 4 seconds
+pub const LAKITU_LEAVES_BEFORE_GOAL: usize = 480; // pixels, a player this close to the goal sends the Lakitus away unless the level says otherwise
+pub const MAX_SPINIES: usize = 3; // Spinies out at once, a Lakitu holds on to its egg while they are
+pub const SPINY_THROW_VELOCITY: f32 = 3.0; // pixels per physics frame up, the egg arcs down from the cloud
+pub const FIREBALL_SPEED: f32 = 4.0; // pixels per physics frame, sideways
+pub const FIREBALL_BOUNCE_VELOCITY: f32 = 2.5; // upwards off the floor, about half a tile high
+pub const MAX_FIREBALLS: usize = 2; // a player's fireballs on screen at once, throwing does nothing while they are out
+// points, like the original's
+pub const STOMP_CHAIN_POINTS: [u32; 10] = [100, 200, 400, 500, 800, 1000, 2000, 4000, 5000, 8000]; // each stomp before landing
+pub const FIREBALL_KILL_POINTS: u32 = 100;
+pub const COIN_POINTS: u32 = 200;
+pub const POWERUP_POINTS: u32 = 1000;
+pub const BRICK_POINTS: u32 = 50;
+pub const FLAGPOLE_POINTS: [u32; 5] = [100, 400, 800, 2000, 5000]; // from the bottom fifth of the level to the top one
+pub const TIME_BONUS_POINTS: u32 = 50; // per time unit left on the clock at the goal
+pub const PLAYER_HITBOX_WIDTH: f32 = 12.0; // sprite is 16 wide, narrower hitbox forgives corner hits
+pub const HEAD_BUMP_MIN_OVERLAP: f32 = 4.0; // pixels of the head under a block for a jump into it to bump it
@@ -0,0 +1,2022 @@
+use std::any::Any;
+
+use macroquad::prelude::*;
+
+use crate::animation::{Animate, DrawParams, DrawPortion, PlayAnimationBuilder};
+use crate::assets::{Assets, TextureFrames, TextureId, BULLET_FRAMES, COIN_FRAMES, FIREBALL_FRAMES, FIRE_FLOWER_FRAMES, FIRE_FRAMES, GOOMBA_FRAMES, KOOPA_FRAMES, LAKITU_FRAMES, LAUNCHER_FRAMES, PLANT_FRAMES, POWERUP_FRAMES, SHELL_FRAMES, SPINY_FRAMES, STAR_FRAMES};
+use crate::audio::{AudioManager, SfxId};
+use crate::collision::{overlap_area, CollisionBehavior, CollisionLayer, CollisionMask, CollisionResponse, CollisionType, Contact, ResponseKind, Surface, SurroundingObject};
+use crate::config::{BULLET_SPEED, COYOTE_FRAMES, DEATH_HOP_VELOCITY, DEATH_PAUSE_FRAMES, DYING_TIME, FIREBALL_SPEED, JUMP_BUFFER_FRAMES, JUMP_HOLD_FRAMES, LAKITU_SPEED, LAKITU_THROW_FRAMES, LAUNCHER_COOLDOWN_FRAMES, MARIO_SPRITE_BLOCK_SIZE, MAX_WALK_VELOCITY_X, PLANT_HIDDEN_FRAMES, PLANT_OUT_FRAMES, PLANT_SPEED, PLAYER_HITBOX_WIDTH, SHELL_SPEED, SHELL_WAKE_FRAMES, SPINY_THROW_VELOCITY, STAR_BOUNCE_VELOCITY, STAR_POWER_FRAMES};
+use crate::input::InputState;
+use crate::physics::PhysicsConfig;
+use crate::world::{GameEvent, GameEventType, WorldBounds};
+
+#[derive(Clone)]
+pub(crate) enum SpawnAnimation {
+    PowerUp,
+}
+pub(crate) struct SpawningObject {
+    pub(crate) object: Box<dyn Updatable>,
+    pub(crate) animation_progress: f32,
+    pub(crate) animation_finish: f32,
+    pub(crate) spawn_animation: SpawnAnimation, // only for spawning, animate is used for alive objects
+    pub(crate) draw_offset: Vec2,
+}
+
+
+impl SpawningObject {
+    pub(crate) fn new(object: impl Updatable) -> Self {
+        match object.object().object_type {
+            ObjectType::Powerup(_) => {
+                SpawningObject {
+                    object: Box::new(object),
+                    animation_progress: 0.0,
+                    animation_finish: MARIO_SPRITE_BLOCK_SIZE as f32,
+                    spawn_animation: SpawnAnimation::PowerUp,
+                    draw_offset: Vec2::new(0.0 , MARIO_SPRITE_BLOCK_SIZE as f32) // draw it where the block is then move up
+                }
+            }
+            _ => panic!("Spawning object with animation not implemented for object type: {:?}", object.object().object_type),
+        }
+    }
+
+    // the boxed object is cloned through its concrete type, like update_spawning_objects unpacks it
+    pub(crate) fn clone_spawning(&self) -> SpawningObject {
+        let object: Box<dyn Updatable> = match self.spawn_animation {
+            SpawnAnimation::PowerUp => Box::new(
+                self.object.as_any().downcast_ref::<PowerUp>().expect("Failed to downcast powerup").clone(),
+            ),
+        };
+        SpawningObject {
+            object,
+            animation_progress: self.animation_progress,
+            animation_finish: self.animation_finish,
+            spawn_animation: self.spawn_animation.clone(),
+            draw_offset: self.draw_offset,
+        }
+    }
+    pub(crate) fn update(&mut self) -> bool { 
+        match self.spawn_animation {
+            SpawnAnimation::PowerUp => {
+                let move_by = 0.5;
+                self.animation_progress += move_by;
+                if self.animation_progress >= self.animation_finish {
+                    return true;
+                } else {
+                    self.draw_offset.y -= move_by;
+                    return false;
+                }
+            }
+        }
+    }
+    pub(crate) fn draw(& self, assets: &Assets, camera_x: usize, camera_y: usize) {
+        match self.spawn_animation {
+            SpawnAnimation::PowerUp => {
+                let object = self.object.object();
+                let params = DrawParams::new(object.pos + self.draw_offset, object.width, object.height)
+                    .facing(self.object.velocity())
+                    .portion(DrawPortion::Top(self.animation_progress / self.animation_finish));
+                self.object.animate().draw(assets, params, camera_x, camera_y);
+            }
+
+        }
+    
+    }
+}
+
+pub(crate) trait Updatable: 'static{
+    fn as_any(&self) -> &dyn Any;
+    fn mut_object(&mut self) -> &mut Object;
+    fn mut_velocity(&mut self) -> &mut Vec2;
+    fn object(&self) -> &Object;
+    fn velocity(&self) -> &Vec2;
+
+    fn set_grounded(&mut self, grounded: bool);
+    fn animate(& self) -> & Animate;
+    fn mut_animate(&mut self) -> &mut Animate;
+    fn apply_gravity(&mut self, physics: &PhysicsConfig) {
+        let velocity = self.velocity().y + physics.gravity * self.object().gravity_scale * physics.frame_time;
+        self.mut_velocity().y = velocity.min(physics.max_fall_speed);
+    }
+
+    fn apply_x_axis_friction(&mut self, surface: &Surface, physics: &PhysicsConfig) {
+        // friction pulls the velocity relative to the surface towards zero, so conveyors carry you along
+        let relative_velocity = self.velocity().x - surface.conveyor_velocity;
+        let slowed_velocity = (relative_velocity.abs() - surface.friction * physics.frame_time).max(0.0)
+            * relative_velocity.signum();
+        self.mut_velocity().x = slowed_velocity + surface.conveyor_velocity;
+    }
+    // the surface we stand on, None while airborne
+    fn ground_surface(&self, surrounding_objects: &[SurroundingObject], physics: &PhysicsConfig) -> Option<Surface> {
+        // grounded if anything in the row below overlaps any part of our width, not just the center
+        let self_hitbox = self.object().hitbox();
+        surrounding_objects
+            .iter()
+            .find(|obj| {
+                let other_hitbox = obj.object.hitbox();
+                obj.relative_direction.0 == 1
+                    && obj.object.object_type.supports_riders()
+                    && other_hitbox.left() < self_hitbox.right()
+                    && other_hitbox.right() > self_hitbox.left()
+            })
+            .map(|obj| Surface::of(&obj.object, physics))
+    }
+    // picks the frames for what the entity is doing, time only moves on once per update, after it
+    fn update_animation(&mut self) {}
+    fn get_collision_handler(&self, object_type: ObjectType) -> CollisionBehavior;
+    fn collision_mask(&self) -> CollisionMask; // layers this entity collides with, everything else is ignored before handlers run
+    fn handle_world_border(&mut self, world_bounds: WorldBounds) -> Option<GameEvent>;
+    // the surrounding objects are used up, the buffer is left empty for the next entity. events go onto the world's list,
+    // returns how many of the surrounding objects it was resolved against
+    fn update(
+        &mut self,
+        surrounding_objects: &mut Vec<SurroundingObject>,
+        world_bounds: WorldBounds,
+        physics: &PhysicsConfig,
+        game_events: &mut Vec<GameEvent>,
+    ) -> u32 {
+        let collision_mask = self.collision_mask();
+        surrounding_objects.retain(|other| collision_mask.contains(CollisionLayer::of(other.object.object_type)));
+        let start_pos = self.object().pos;
+        match self.ground_surface(surrounding_objects, physics) {
+            None => {
+                self.apply_gravity(physics);
+                self.set_grounded(false);
+                self.apply_x_axis_friction(&Surface::air(physics), physics);
+            }
+            Some(surface) => {
+                self.set_grounded(true);
+                self.apply_x_axis_friction(&surface, physics);
+                self.mut_object().pos += surface.carry;
+            }
+        }
+
+        let velocity = self.velocity().clone();
+        self.mut_object().pos += velocity;
+        // resolve the deepest overlap first and re-measure the rest against the corrected position,
+        // otherwise a shallow side overlap on a ground seam can kill horizontal speed
+        let unresolved = surrounding_objects;
+        let collision_pairs = unresolved.len() as u32;
+        let mut turned_around = false;
+        while !unresolved.is_empty() {
+            let (deepest_index, _) = unresolved
+                .iter()
+                .enumerate()
+                .map(|(index, other)| (index, overlap_area(self.object(), &other.object)))
+                .fold((0, f32::MIN), |deepest, current| if current.1 > deepest.1 { current } else { deepest });
+            let other = unresolved.remove(deepest_index);
+            let collision_behavior = self.get_collision_handler(other.object.object_type);
+            let mut collision_response =
+                collision_behavior.resolve(self.object(), self.velocity(), &other);
+            if let Some(CollisionType::EnemyWithBlock) = collision_response.collision_type {
+                // squeezed between two walls it would turn around and straight back in the same frame
+                if turned_around {
+                    collision_response.new_velocity.x = self.velocity().x;
+                }
+                turned_around = true;
+            }
+
+            if let Some(ref collision_type) = collision_response.collision_type {
+                let game_event = self.create_game_event(collision_type, &other, collision_response.contact);
+                if let Some(event) = game_event {
+                    game_events.push(event);
+                }
+            }
+            if collision_response.collided && collision_response.kind != ResponseKind::Overlap {
+                self.update_position_and_velocity(&collision_response);
+            }
+        }
+        let game_event = self.handle_world_border(world_bounds);
+        if let Some(event) = game_event {
+            game_events.push(event);
+        }
+        self.mut_object().displacement = self.object().pos - start_pos;
+        self.update_animation();
+        // the one place an entity's animation advances
+        self.mut_animate().update(physics.frame_time);
+        collision_pairs
+    }
+
+    fn create_game_event(
+        &self,
+        collision_type: &CollisionType,
+        other: &SurroundingObject,
+        contact: Option<Contact>,
+    ) -> Option<GameEvent> {
+        let (entity, coin, other) = (other.reference.entity_id(), other.reference.coin_id(), &other.object);
+        match collision_type {
+            CollisionType::PlayerKillEnemy => Some(GameEvent {
+                // a Koopa isn't killed by a stomp, it hides in its shell
+                event: match other.object_type {
+                    ObjectType::Enemy(EnemyType::Koopa(_)) => GameEventType::EnemyStomped,
+                    _ => GameEventType::Kill,
+                },
+                triggered_by: *self.object(),
+                target: Some(*other),
+                entity,
+                coin: None,
+                source: None,
+                contact,
+                player: None,
+            }),
+            CollisionType::PlayerHitBy => Some(GameEvent {
+                event: GameEventType::PlayerHit,
+                triggered_by: other.clone(),
+                target: Some(*self.object()),
+                entity,
+                coin: None,
+                source: None,
+                contact, // seen from the player, the target
+                player: None,
+            }),
+            CollisionType::PlayerWithBlock=> Some(GameEvent {
+                event: GameEventType::PlayerHitBlock,
+                triggered_by: *self.object(),
+                target: Some(*other),
+                entity,
+                coin: None,
+                source: None,
+                contact,
+                player: None,
+            }),
+            CollisionType::PlayerWithPowerupBlock => Some(GameEvent {
+                event: GameEventType::PlayerHitPowerupBlock,
+                triggered_by: *self.object(),
+                target: Some(*other),
+                entity,
+                coin: None,
+                source: None,
+                contact,
+                player: None,
+            }),
+            CollisionType::EnemyWithBlock => None,
+            CollisionType::PlayerKickShell => Some(GameEvent {
+                event: GameEventType::ShellKicked,
+                triggered_by: *self.object(),
+                target: Some(*other),
+                entity,
+                coin: None,
+                source: None,
+                contact,
+                player: None,
+            }),
+            CollisionType::ShellKillEnemy => Some(GameEvent {
+                event: GameEventType::Kill,
+                triggered_by: *self.object(),
+                target: Some(*other),
+                entity,
+                coin: None,
+                source: None,
+                contact,
+                player: None,
+            }),
+            CollisionType::FireballKillEnemy => Some(GameEvent {
+                event: GameEventType::Kill,
+                triggered_by: *self.object(),
+                target: Some(*other),
+                entity,
+                coin: None,
+                source: None,
+                contact,
+                player: None,
+            }),
+            CollisionType::EnemyWithEnemy => {
+                // Goomba collision with goomba
+                Some(GameEvent {
+                    event: GameEventType::EnemyCollEnemy,
+                    triggered_by: *self.object(),
+                    target: Some(*other),
+                    entity,
+                    coin: None,
+                    source: None,
+                    contact,
+                    player: None,
+                })
+            }
+            CollisionType::PlayerWithPowerup => Some(GameEvent {
+                event: GameEventType::PlayerPowerUp,
+                triggered_by: *self.object(),
+                target: Some(*other),
+                entity,
+                coin: None,
+                source: None,
+                contact,
+                player: None,
+            }),
+            CollisionType::PlayerWithCoin => Some(GameEvent {
+                event: GameEventType::CoinCollected,
+                triggered_by: *self.object(),
+                target: Some(*other),
+                entity,
+                coin,
+                source: None,
+                contact,
+                player: None,
+            }),
+
+        }
+    }
+    fn update_position_and_velocity(&mut self, collision_response: &CollisionResponse) {
+        let incoming_velocity = *self.velocity();
+        *self.mut_velocity() = match collision_response.kind {
+            ResponseKind::Stop => Vec2::ZERO,
+            ResponseKind::Overlap => return,
+            ResponseKind::Slide => collision_response.new_velocity,
+            ResponseKind::Bounce(restitution) => {
+                let mut bounced_velocity = collision_response.new_velocity;
+                if bounced_velocity.x == 0.0 && incoming_velocity.x != 0.0 {
+                    bounced_velocity.x = -incoming_velocity.x * restitution;
+                }
+                if bounced_velocity.y == 0.0 && incoming_velocity.y != 0.0 {
+                    bounced_velocity.y = -incoming_velocity.y * restitution;
+                }
+                bounced_velocity
+            }
+        };
+        self.mut_object().pos = collision_response.new_pos;
+    }
+}
+
+
+#[derive(Clone, PartialEq, Copy, Debug)]
+pub enum BlockType {
+    Block,
+    PowerupBlock,
+    Launcher, // fires Bullet Bills, solid like any block
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum EnemyType {
+    Goomba,
+    Koopa(KoopaState),
+    PiranhaPlant(PlantState),
+    BulletBill,
+    Spiny,
+    Lakitu,
+}
+
+// part of the type, so what touches a Koopa can tell a shell from one walking
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum KoopaState {
+    Walking,
+    Shell, // stomped, sits still until it comes back out
+    Sliding, // a shell kicked by the player
+}
+
+// like a Koopa's, part of the type: down in its pipe a plant can't be touched
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum PlantState {
+    Hidden,
+    Rising,
+    Out,
+    Sinking,
+}
+
+// what comes out of a powerup block, the mushroom for a small player and the flower for one already big
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum PowerupType {
+    Mushroom,
+    FireFlower,
+    Star, // placed in the level, bounces along and gives star power instead of growing the player
+}
+// what landing on top of an enemy does. any other contact hurts the player, except with a Solid one, which is a block
+// from every side
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub(crate) enum ContactPolicy {
+    Stompable,
+    Solid,
+    Harmful,
+}
+impl EnemyType {
+    pub(crate) fn contact_policy(&self) -> ContactPolicy {
+        match self {
+            // stomping a sliding shell stops it
+            EnemyType::Goomba | EnemyType::Koopa(KoopaState::Walking | KoopaState::Sliding) | EnemyType::BulletBill
+            | EnemyType::Lakitu => ContactPolicy::Stompable,
+            EnemyType::Koopa(KoopaState::Shell) => ContactPolicy::Solid,
+            // the spikes, a stomp hurts like walking into it. the egg already has them
+            EnemyType::PiranhaPlant(_) | EnemyType::Spiny => ContactPolicy::Harmful,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ObjectType {
+    Block(BlockType),
+    Enemy(EnemyType),
+    Powerup(PowerupType),
+    Coin,
+    Player,
+    Fireball,
+}
+impl ObjectType {
+    // whether standing on top of it counts as grounded
+    pub(crate) fn supports_riders(&self) -> bool {
+        match self {
+            ObjectType::Block(_) => true,
+            ObjectType::Enemy(enemy_type) => enemy_type.contact_policy() == ContactPolicy::Solid,
+            ObjectType::Powerup(_) | ObjectType::Coin | ObjectType::Player | ObjectType::Fireball => false,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug)]
+pub struct Object {
+    pub(crate) pos: Vec2,
+    pub(crate) height: usize, // sprite size, used for drawing and grid registration
+    pub(crate) width: usize,
+    pub(crate) object_type: ObjectType,
+    // collision box relative to pos, None means the sprite size; blocks never set it, so level collision stays tile sized
+    pub(crate) hitbox: Option<Rect>,
+    pub(crate) gravity_scale: f32, // multiplies GRAVITY, 0.0 exempts the object from gravity
+    pub(crate) displacement: Vec2, // how far the object moved in its last update, whatever stands on it is carried along
+}
+
+impl Object {
+    pub(crate) fn new(x: usize, y: usize, object_type: ObjectType) -> Object {
+        Object {
+            pos: Vec2::new(x as f32, y as f32),
+            height: MARIO_SPRITE_BLOCK_SIZE,
+            width: MARIO_SPRITE_BLOCK_SIZE,
+            object_type,
+            hitbox: None,
+            gravity_scale: match object_type {
+                ObjectType::Block(_) | ObjectType::Coin => 0.0,
+                ObjectType::Enemy(_) | ObjectType::Powerup(_) | ObjectType::Player | ObjectType::Fireball => 1.0,
+            },
+            displacement: Vec2::ZERO,
+        }
+    }
+    // per spawn override of the type default
+    pub(crate) fn with_gravity_scale(mut self, gravity_scale: f32) -> Object {
+        self.gravity_scale = gravity_scale;
+        self
+    }
+    // collision box in world coordinates
+    pub(crate) fn hitbox(&self) -> Rect {
+        match self.hitbox {
+            Some(hitbox) => Rect::new(self.pos.x + hitbox.x, self.pos.y + hitbox.y, hitbox.w, hitbox.h),
+            None => Rect::new(self.pos.x, self.pos.y, self.width as f32, self.height as f32),
+        }
+    }
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        self.pos == other.pos && self.object_type == other.object_type
+    }
+}
+
+// decides when a jump press starts a jump, forgiving presses slightly too late (coyote) or too early (buffered)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum JumpState {
+    Grounded,
+    Coyote(u32),   // walked off a ledge, frames left in which a press still jumps
+    Airborne,
+    Buffered(u32), // pressed in the air, frames left in which landing jumps right away
+}
+impl JumpState {
+    // one physics frame, returns the next state and whether a jump starts this frame;
+    // every way to start a jump leaves through Airborne, so a buffered press during coyote time fires only once
+    pub(crate) fn step(self, grounded: bool, jump_pressed: bool) -> (JumpState, bool) {
+        match (self, grounded, jump_pressed) {
+            (_, true, true) | (JumpState::Buffered(_), true, false) => (JumpState::Airborne, true),
+            (_, true, false) => (JumpState::Grounded, false),
+            (JumpState::Grounded | JumpState::Coyote(_), false, true) => (JumpState::Airborne, true),
+            (JumpState::Grounded, false, false) => (JumpState::Coyote(COYOTE_FRAMES), false),
+            (JumpState::Coyote(frames), false, false) if frames > 1 => (JumpState::Coyote(frames - 1), false),
+            (JumpState::Airborne | JumpState::Buffered(_), false, true) => (JumpState::Buffered(JUMP_BUFFER_FRAMES), false),
+            (JumpState::Buffered(frames), false, false) if frames > 1 => (JumpState::Buffered(frames - 1), false),
+            (_, false, false) => (JumpState::Airborne, false),
+        }
+    }
+}
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum PlayerState {
+    Dead,
+    Small,
+    Big,
+    Fire, // big and throws fireballs with the run key
+}
+#[derive(Clone)]
+pub struct Player {
+    pub(crate) object: Object,
+    pub(crate) max_speed: f32,
+    pub(crate) velocity: Vec2,
+    pub(crate) is_grounded: bool,
+    pub(crate) power_state: PlayerState,
+    pub(crate) animate: Animate,
+    pub(crate) input_direction: f32, // -1.0 left, 1.0 right, 0.0 none; taken from the input each update, picks the turning sprite
+    pub(crate) holding_down: bool, // fast fall while airborne; crouching and pipes will read it too
+    pub(crate) running: bool, // run held, max_speed is only reached while running
+    pub(crate) jump_state: JumpState,
+    pub(crate) jump_hold_frames: u32, // left of the jump's boost, only a jump that started has one
+    pub(crate) boosting: bool, // the jump's boost was given this frame, gravity is lighter for it
+    pub(crate) star_frames: u32, // physics frames of star power left, enemies touched die instead of hurting
+    pub(crate) hit_invincible_frames: u32, // physics frames left after a hit in which enemies pass through, the sprite blinks
+    pub(crate) kick_grace_frames: u32, // physics frames left in which a sliding shell passes through, from a kick
+    pub(crate) sprites: TextureFrames, // Mario's, or the same frames in Luigi's colors for player two
+}
+impl Updatable for Player {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn mut_object(&mut self) -> &mut Object {
+        &mut self.object
+    }
+
+    fn mut_velocity(&mut self) -> &mut Vec2 {
+        &mut self.velocity
+    }
+
+    fn object(&self) -> &Object {
+        &self.object
+    }
+
+    fn velocity(&self) -> &Vec2 {
+        &self.velocity
+    }
+
+    fn set_grounded(&mut self, grounded: bool) {
+        self.is_grounded = grounded;
+    }
+
+    fn animate(& self) -> & Animate {
+        & self.animate
+    }
+    fn mut_animate(&mut self) -> &mut Animate {
+        &mut self.animate
+    }
+    fn apply_gravity(&mut self, physics: &PhysicsConfig) {
+        // only a jump's own rise floats, its boost is over once the key is let go and a stomp's bounce never has one
+        let factor = if self.holding_down {
+            physics.fast_fall_gravity_factor
+        } else if self.boosting && self.velocity.y < 0.0 {
+            physics.rising_gravity_factor
+        } else {
+            1.0
+        };
+        let velocity = self.velocity.y + physics.gravity * self.object.gravity_scale * factor * physics.frame_time;
+        self.velocity.y = velocity.min(physics.max_fall_speed);
+    }
+    fn collision_mask(&self) -> CollisionMask {
+        CollisionMask::of(&[CollisionLayer::Block, CollisionLayer::Enemy, CollisionLayer::Powerup, CollisionLayer::Trigger])
+    }
+    fn get_collision_handler(&self, object_type: ObjectType) -> CollisionBehavior {
+        match object_type {
+            ObjectType::Block(_) => CollisionBehavior::Block,
+            ObjectType::Enemy(EnemyType::PiranhaPlant(PlantState::Hidden)) => CollisionBehavior::DoNothing,
+            ObjectType::Enemy(_) if self.hit_invincible_frames > 0 => CollisionBehavior::PlayerEnemyInvincible,
+            ObjectType::Enemy(EnemyType::Koopa(KoopaState::Sliding)) if self.kick_grace_frames > 0 => CollisionBehavior::PlayerEnemyInvincible,
+            ObjectType::Enemy(_) => CollisionBehavior::PlayerEnemy,
+            ObjectType::Powerup(_) => CollisionBehavior::Powerup,
+            ObjectType::Coin => CollisionBehavior::Coin,
+            _ => panic!("No collision handler for object type: {:?}", object_type),
+        }
+    }
+    fn handle_world_border(&mut self, world_bounds: WorldBounds) -> Option<GameEvent> {
+        if self.object.pos.x < world_bounds.min_x as f32 {
+            self.object.pos.x = world_bounds.min_x as f32;
+            self.velocity.x = 0.0;
+        }
+        if world_bounds.goal_x.is_some_and(|goal_x| self.object.pos.x + self.object.width as f32 > goal_x as f32) {
+            return Some(GameEvent {
+                event: GameEventType::GameWon,
+                triggered_by: self.object,
+                target: None,
+                entity: None,
+                coin: None,
+                source: None,
+                contact: None,
+                player: None,
+            });
+        }
+        if self.object.pos.x + self.object.width as f32 > world_bounds.max_x as f32 {
+            self.object.pos.x = world_bounds.max_x as f32 - self.object.width as f32;
+            self.velocity.x = 0.0;
+        }
+        if self.object.pos.y > world_bounds.max_y as f32 {
+            return Some(GameEvent {
+                event: GameEventType::GameOver,
+                triggered_by: self.object,
+                target: None,
+                entity: None,
+                coin: None,
+                source: None,
+                contact: None,
+                player: None,
+            });
+        }
+        None
+    }
+    fn update_animation(&mut self) {
+        // Use velocity and the held direction to determine the correct animation frames
+        if self.velocity.y.abs() != 0.0 {
+            if self.velocity.x.abs() > MAX_WALK_VELOCITY_X {
+                // Running Jump, faster than walking gets
+                self.animate
+                    .change_animation_sprites(self.frames().part(5, 6));
+                return;
+            } else {
+                // Jumping
+                self.animate
+                    .change_animation_sprites(self.frames().part(3, 4));
+                return;
+            }
+        } else if self.velocity.x.abs() > 0.1 {
+            // Running
+            if self.input_direction > 0.0 {
+                if self.velocity.x < 0.0 {
+                    // Turning
+                    self.animate
+                        .change_animation_sprites(self.frames().part(4, 5));
+                    return;
+                }
+            } else if self.input_direction < 0.0 {
+                if self.velocity.x > 0.0 {
+                    // Turning
+                    self.animate
+                        .change_animation_sprites(self.frames().part(4, 5));
+                    return;
+                }
+            }
+            self.animate
+                .change_animation_sprites(self.frames().part(1, 3));
+                self.animate
+
+                .scale_animation_speed(self.velocity.x.abs() / self.max_speed as f32);
+
+        } else {
+            // Idle
+            self.animate
+                .change_animation_sprites(self.frames().part(0, 1));
+        }
+    }
+}
+
+impl Player {
+    pub(crate) fn new(x: usize, y: usize, max_speed: f32, sprites: TextureFrames) -> Player {
+        let mut player = Player {
+            object: Object::new(x, y, ObjectType::Player),
+            max_speed,
+            velocity: Vec2::new(0.0, 0.0),
+            is_grounded: false,
+            power_state: PlayerState::Small,
+            animate: Animate::new(1.0),
+            input_direction: 0.0,
+            holding_down: false,
+            running: false,
+            jump_state: JumpState::Airborne,
+            jump_hold_frames: 0,
+            boosting: false,
+            star_frames: 0,
+            hit_invincible_frames: 0,
+            kick_grace_frames: 0,
+            sprites,
+        };
+        player.set_height(MARIO_SPRITE_BLOCK_SIZE);
+        player
+            .animate
+            .change_animation_sprites(sprites.part(0, 1));
+        player
+    }
+    // keeps the narrower player hitbox in sync with the sprite height when growing/shrinking
+    pub(crate) fn set_height(&mut self, height: usize) {
+        self.object.height = height;
+        let inset_x = (self.object.width as f32 - PLAYER_HITBOX_WIDTH) / 2.0;
+        self.object.hitbox = Some(Rect::new(inset_x, 0.0, PLAYER_HITBOX_WIDTH, height as f32));
+    }
+    // a flower picked up while small grows the player and gives fire at once
+    pub(crate) fn power_up(&mut self, powerup_type: PowerupType) {
+        match (&self.power_state, powerup_type) {
+            (_, PowerupType::Star) => self.grant_star_power(),
+            (PlayerState::Small, _) => {
+                self.power_state = match powerup_type {
+                    PowerupType::FireFlower => PlayerState::Fire,
+                    _ => PlayerState::Big,
+                };
+                let new_height = self.object.height * 2;
+                let animation = PlayAnimationBuilder::new(self.animate.frames.get(self.animate.current_frame_index).map(TextureFrames::single).unwrap_or_default())
+                    .loop_for(0.5)
+                    .height_frames(vec![self.object.height, new_height])
+                    .build();
+                // grows upwards, grown into the floor it would see the blocks under its feet as its own cells and fall through
+                self.object.pos.y -= (new_height - self.object.height) as f32;
+                self.set_height(new_height);
+                self.animate.scale_animation_speed(0.8);
+                self.animate.play_animation(animation);
+            }
+            (PlayerState::Big, PowerupType::FireFlower) => {
+                self.power_state = PlayerState::Fire;
+            }
+            _ => {}
+        }
+    }
+    // picking up another star while one is active only refills the time
+    pub(crate) fn grant_star_power(&mut self) {
+        self.star_frames = STAR_POWER_FRAMES;
+    }
+    pub(crate) fn power_down(&mut self) {
+        match self.power_state {
+            PlayerState::Small => {
+                self.power_state = PlayerState::Dead;
+            }
+            PlayerState::Big => {
+                self.power_state = PlayerState::Small;
+                self.object.pos.y += (self.object.height - MARIO_SPRITE_BLOCK_SIZE) as f32;
+                self.set_height(MARIO_SPRITE_BLOCK_SIZE);
+            }
+            PlayerState::Fire => {
+                self.power_state = PlayerState::Big; // loses the fire, stays big
+            }
+            _ => {}
+        }
+    }
+    // the classic death, still for a moment then a hop and down through the floor. only drawn that way,
+    // the object stays where it died so nothing follows it down
+    pub(crate) fn play_death(&mut self, physics: &PhysicsConfig) {
+        let gravity = physics.gravity * physics.frame_time;
+        let offsets = (0..(DYING_TIME / physics.frame_time) as usize)
+            .map(|frame| {
+                let t = frame.saturating_sub(DEATH_PAUSE_FRAMES) as f32;
+                Vec2::new(0.0, -DEATH_HOP_VELOCITY * t + gravity * t * t / 2.0)
+            })
+            .collect();
+        let animation = PlayAnimationBuilder::new(self.frames().part(3, 4)).pos_offset_frames(offsets).build();
+        self.animate.scale_animation_speed(5.0); // an offset per physics frame
+        self.animate.play_animation(animation);
+    }
+    // fire looks the same for both players
+    pub(crate) fn frames(&self) -> TextureFrames {
+        match self.power_state {
+            PlayerState::Fire => FIRE_FRAMES,
+            _ => self.sprites,
+        }
+    }
+    // the way the sprite faces, Animate flips it only while moving left
+    pub(crate) fn facing(&self) -> f32 {
+        if self.velocity.x < -0.1 { -1.0 } else { 1.0 }
+    }
+    pub(crate) fn update(
+        &mut self,
+        input: &InputState,
+        surrounding_objects: &mut Vec<SurroundingObject>,
+        world_bounds: WorldBounds,
+        physics: &PhysicsConfig,
+        game_events: &mut Vec<GameEvent>,
+    ) -> u32 {
+        self.input_direction = match (input.left, input.right) {
+            (true, false) => -1.0,
+            (false, true) => 1.0,
+            _ => 0.0,
+        };
+        self.holding_down = input.down;
+        self.star_frames = self.star_frames.saturating_sub(1);
+        self.hit_invincible_frames = self.hit_invincible_frames.saturating_sub(1);
+        self.kick_grace_frames = self.kick_grace_frames.saturating_sub(1);
+        Updatable::update(self, surrounding_objects, world_bounds, physics, game_events)
+    }
+
+    pub(crate) fn add_horizontal_velocity(&mut self, velocity: f32, physics: &PhysicsConfig) {
+        let top_speed = if self.running { self.max_speed } else { self.max_speed.min(physics.max_walk_speed) };
+        // letting go of run keeps the speed you had, friction bleeds it off instead of a sudden stop
+        let limit = top_speed.max(self.velocity.x.abs());
+        self.velocity.x = (self.velocity.x + velocity).clamp(-limit, limit);
+    }
+
+    // every physics frame, starts jumps through the jump state on a press and boosts them while jump stays held on the way up.
+    // holding jump on the ground neither jumps again nor lifts the player
+    pub(crate) fn jump(&mut self, input: &InputState, physics: &PhysicsConfig, audio: &mut AudioManager) {
+        // the ground check right after take off still finds the floor, rising doesn't count as grounded.
+        // that also keeps a buffered press from firing off a stomp's bounce, only a real landing takes it
+        let grounded = self.is_grounded && self.velocity.y >= 0.0;
+        let (jump_state, starts_jump) = self.jump_state.step(grounded, input.jump_pressed);
+        self.jump_state = jump_state;
+        if starts_jump {
+            audio.play_sfx(SfxId::Jump);
+            self.velocity.y = -3.0;
+            self.is_grounded = false;
+            self.jump_hold_frames = JUMP_HOLD_FRAMES;
+        }
+        if !input.jump || (grounded && !starts_jump) || self.velocity.y > 0.0 {
+            // let go, landed or falling, pressing again in the air doesn't bring the boost back.
+            // a stomp's bounce comes while falling, so holding jump through it doesn't carry it higher
+            self.jump_hold_frames = 0;
+        }
+        self.boosting = self.jump_hold_frames > 0;
+        if !self.boosting {
+            return;
+        }
+        self.jump_hold_frames -= 1;
+        self.velocity.y += -physics.jump_strength * physics.frame_time;
+    }
+
+    pub(crate) fn draw(&self, assets: &Assets, camera_x: usize, camera_y: usize) {
+        if self.hit_invincible_frames % 2 == 1 {
+            return; // blinks, every other frame it isn't drawn
+        }
+        let params = DrawParams::new(self.object.pos, self.object.width, self.object.height).facing(&self.velocity);
+        self.animate.draw(assets, params, camera_x, camera_y)
+    }
+}
+#[derive(Clone)]
+pub struct Goomba {
+    pub(crate) object: Object,
+    pub(crate) max_speed: i32,
+    pub(crate) velocity: Vec2,
+    pub(crate) animate: Animate,
+    pub(crate) is_grounded: bool,
+}
+impl Updatable for Goomba {
+fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn mut_object(&mut self) -> &mut Object {
+        &mut self.object    
+    }
+
+    fn mut_velocity(&mut self) -> &mut Vec2 {
+        &mut self.velocity
+    }
+
+    fn object(&self) -> &Object {
+        &self.object
+    }
+
+    fn velocity(&self) -> &Vec2 {
+        &self.velocity
+    }
+
+    fn set_grounded(&mut self, grounded: bool) {
+        self.is_grounded = grounded;
+    }
+
+    fn animate(& self) -> & Animate {
+        & self.animate
+    }
+    fn mut_animate(&mut self) -> &mut Animate {
+        &mut self.animate
+    }
+    fn handle_world_border(&mut self, world_bounds: WorldBounds) -> Option<GameEvent> {
+        if self.object.pos.x < world_bounds.min_x as f32 {
+            self.object.pos.x = world_bounds.min_x as f32;
+            self.velocity.x = 0.0;
+        }
+        if self.object.pos.x + self.object.width as f32 > world_bounds.max_x as f32 {
+            self.object.pos.x = world_bounds.max_x as f32 - self.object.width as f32;
+            self.velocity.x = 0.0;
+        }
+        if self.object.pos.y > world_bounds.max_y as f32 {
+            return Some(GameEvent {
+                event: GameEventType::Kill,
+                triggered_by: self.object,
+                target: None,
+                entity: None,
+                coin: None,
+                source: None,
+                contact: None,
+                player: None,
+            });
+        }
+        None
+    }
+    fn collision_mask(&self) -> CollisionMask {
+        // the player resolves goomba contact itself, enemy pairs are resolved once per frame in World::resolve_enemy_pairs
+        CollisionMask::of(&[CollisionLayer::Block, CollisionLayer::Powerup, CollisionLayer::Projectile])
+    }
+    fn get_collision_handler(&self, object_type: ObjectType) -> CollisionBehavior {
+        match object_type {
+            ObjectType::Block(_) => CollisionBehavior::EnemyBlock,
+            ObjectType::Enemy(_) => CollisionBehavior::Enemy,
+            ObjectType::Player => CollisionBehavior::DoNothing, // Goomba does not interact with player, player will handle goomba collision
+            ObjectType::Powerup(_) => CollisionBehavior::Enemy,
+            ObjectType::Coin => CollisionBehavior::DoNothing,
+            ObjectType::Fireball => CollisionBehavior::DoNothing, // World::resolve_fireball_hits kills it
+        }
+    }
+    fn update_animation(&mut self) {
+        if self.velocity.x.abs() > 0.1 {
+            self.animate
+                .change_animation_sprites(GOOMBA_FRAMES);
+            self.animate
+                .scale_animation_speed(self.velocity.x.abs() / self.max_speed as f32);
+        } else {
+            self.animate
+                .change_animation_sprites(GOOMBA_FRAMES.part(0, 1));
+        }
+    }
+}
+impl Goomba {
+    pub(crate) fn new(x: usize, y: usize, max_speed: i32) -> Goomba {
+        let mut goomba = Goomba {
+            object: Object::new(x, y, ObjectType::Enemy(EnemyType::Goomba)),
+            max_speed,
+            velocity: Vec2::new(1.0, 0.0),
+            animate: Animate::new(1.0),
+            is_grounded: false,
+        };
+        goomba
+            .animate
+            .change_animation_sprites(GOOMBA_FRAMES);
+        goomba
+    }
+    pub(crate) fn update(
+        &mut self,
+        surrounding_objects: &mut Vec<SurroundingObject>,
+        world_bounds: WorldBounds,
+        physics: &PhysicsConfig,
+        game_events: &mut Vec<GameEvent>,
+    ) -> u32 {
+        self.velocity.x = 1.0 * self.velocity.x.signum(); // avoid friction atm;
+        Updatable::update(self, surrounding_objects, world_bounds, physics, game_events)
+    }
+    pub(crate) fn draw(&self, assets: &Assets, camera_x: usize, camera_y: usize) {
+        let params = DrawParams::new(self.object.pos, self.object.width, self.object.height).facing(&self.velocity);
+        self.animate.draw(assets, params, camera_x, camera_y)
+    }
+}
+// walks like a Goomba. stomped it hides in its shell, which anything can stand on and walking into kicks it
+#[derive(Clone)]
+pub struct Koopa {
+    pub(crate) object: Object,
+    pub(crate) velocity: Vec2,
+    pub(crate) animate: Animate,
+    pub(crate) is_grounded: bool,
+    pub(crate) direction: f32, // the way it walks, a shell comes back out going the same way
+    pub(crate) shell_frames: u32, // physics frames left in the shell
+    pub(crate) kills: usize, // enemies the shell took out since it was kicked, each one is worth more
+}
+impl Updatable for Koopa {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn mut_object(&mut self) -> &mut Object {
+        &mut self.object
+    }
+
+    fn mut_velocity(&mut self) -> &mut Vec2 {
+        &mut self.velocity
+    }
+
+    fn object(&self) -> &Object {
+        &self.object
+    }
+
+    fn velocity(&self) -> &Vec2 {
+        &self.velocity
+    }
+
+    fn set_grounded(&mut self, grounded: bool) {
+        self.is_grounded = grounded;
+    }
+
+    fn animate(& self) -> & Animate {
+        & self.animate
+    }
+    fn mut_animate(&mut self) -> &mut Animate {
+        &mut self.animate
+    }
+    fn handle_world_border(&mut self, world_bounds: WorldBounds) -> Option<GameEvent> {
+        if self.object.pos.x < world_bounds.min_x as f32 {
+            self.object.pos.x = world_bounds.min_x as f32;
+            self.velocity.x = 0.0;
+        }
+        if self.object.pos.x + self.object.width as f32 > world_bounds.max_x as f32 {
+            self.object.pos.x = world_bounds.max_x as f32 - self.object.width as f32;
+            self.velocity.x = 0.0;
+        }
+        if self.object.pos.y > world_bounds.max_y as f32 {
+            return Some(GameEvent {
+                event: GameEventType::Kill,
+                triggered_by: self.object,
+                target: None,
+                entity: None,
+                coin: None,
+                source: None,
+                contact: None,
+                player: None,
+            });
+        }
+        None
+    }
+    fn collision_mask(&self) -> CollisionMask {
+        CollisionMask::of(&[CollisionLayer::Block, CollisionLayer::Powerup, CollisionLayer::Projectile])
+    }
+    fn get_collision_handler(&self, object_type: ObjectType) -> CollisionBehavior {
+        match (self.state(), object_type) {
+            (KoopaState::Sliding, ObjectType::Block(_) | ObjectType::Enemy(_)) => CollisionBehavior::Shell,
+            (KoopaState::Sliding, ObjectType::Powerup(_)) => CollisionBehavior::DoNothing,
+            (_, ObjectType::Block(_)) => CollisionBehavior::EnemyBlock,
+            (_, ObjectType::Enemy(_) | ObjectType::Powerup(_)) => CollisionBehavior::Enemy,
+            (_, ObjectType::Player | ObjectType::Coin | ObjectType::Fireball) => CollisionBehavior::DoNothing,
+        }
+    }
+    fn update_animation(&mut self) {
+        match self.state() {
+            KoopaState::Walking => self.animate.change_animation_sprites(KOOPA_FRAMES),
+            KoopaState::Shell | KoopaState::Sliding => self.animate.change_animation_sprites(SHELL_FRAMES),
+        }
+    }
+}
+impl Koopa {
+    pub(crate) fn new(x: usize, y: usize) -> Koopa {
+        let mut koopa = Koopa {
+            object: Object::new(x, y, ObjectType::Enemy(EnemyType::Koopa(KoopaState::Walking))),
+            velocity: Vec2::new(1.0, 0.0),
+            animate: Animate::new(1.0),
+            is_grounded: false,
+            direction: 1.0,
+            shell_frames: 0,
+            kills: 0,
+        };
+        koopa
+            .animate
+            .change_animation_sprites(KOOPA_FRAMES);
+        koopa
+    }
+    pub(crate) fn state(&self) -> KoopaState {
+        match self.object.object_type {
+            ObjectType::Enemy(EnemyType::Koopa(state)) => state,
+            _ => unreachable!("a Koopa's object is always a Koopa"),
+        }
+    }
+    // the shell is lower and a little narrower than the tile
+    pub(crate) fn hide_in_shell(&mut self) {
+        self.object.object_type = ObjectType::Enemy(EnemyType::Koopa(KoopaState::Shell));
+        self.object.hitbox = Some(Rect::new(1.0, 4.0, MARIO_SPRITE_BLOCK_SIZE as f32 - 2.0, MARIO_SPRITE_BLOCK_SIZE as f32 - 4.0));
+        self.velocity.x = 0.0;
+        self.shell_frames = SHELL_WAKE_FRAMES;
+        self.animate.change_animation_sprites(SHELL_FRAMES);
+    }
+    // sends a shell sliding, direction is -1.0 for left and 1.0 for right
+    pub(crate) fn kick(&mut self, direction: f32) {
+        self.object.object_type = ObjectType::Enemy(EnemyType::Koopa(KoopaState::Sliding));
+        self.velocity.x = SHELL_SPEED * direction;
+        self.kills = 0;
+    }
+    fn come_out(&mut self) {
+        self.object.object_type = ObjectType::Enemy(EnemyType::Koopa(KoopaState::Walking));
+        self.object.hitbox = None;
+        self.velocity.x = self.direction;
+    }
+    pub(crate) fn update(
+        &mut self,
+        surrounding_objects: &mut Vec<SurroundingObject>,
+        world_bounds: WorldBounds,
+        physics: &PhysicsConfig,
+        game_events: &mut Vec<GameEvent>,
+    ) -> u32 {
+        match self.state() {
+            KoopaState::Walking => self.velocity.x = 1.0 * self.velocity.x.signum(), // avoid friction atm;
+            KoopaState::Sliding => self.velocity.x = SHELL_SPEED * self.velocity.x.signum(),
+            KoopaState::Shell => {
+                self.velocity.x = 0.0;
+                self.shell_frames = self.shell_frames.saturating_sub(1);
+                if self.shell_frames == 0 {
+                    self.come_out();
+                }
+            }
+        }
+        let collision_pairs = Updatable::update(self, surrounding_objects, world_bounds, physics, game_events);
+        if self.state() == KoopaState::Walking {
+            self.direction = self.velocity.x.signum();
+        }
+        collision_pairs
+    }
+    pub(crate) fn draw(&self, assets: &Assets, camera_x: usize, camera_y: usize) {
+        // walking it is drawn taller than its tile, the feet on the tile's bottom
+        let extra_height = match self.state() {
+            KoopaState::Walking => MARIO_SPRITE_BLOCK_SIZE / 2,
+            KoopaState::Shell | KoopaState::Sliding => 0,
+        };
+        let params = DrawParams::new(self.object.pos - Vec2::new(0.0, extra_height as f32), self.object.width, self.object.height + extra_height)
+            .facing(&self.velocity);
+        self.animate.draw(assets, params, camera_x, camera_y)
+    }
+}
+// comes up out of a pipe and sinks back in, over and over. it hurts from every side, down in the pipe nothing touches it
+#[derive(Clone)]
+pub struct PiranhaPlant {
+    pub(crate) object: Object,
+    pub(crate) velocity: Vec2,
+    pub(crate) animate: Animate,
+    pub(crate) pipe_top: f32, // all the way down the plant's top is here, out its bottom is
+    pub(crate) phase_frames: u32, // physics frames left hidden or out
+    pub(crate) player_near: bool, // set by the world before each update, the entities don't see the players
+}
+impl Updatable for PiranhaPlant {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn mut_object(&mut self) -> &mut Object {
+        &mut self.object
+    }
+
+    fn mut_velocity(&mut self) -> &mut Vec2 {
+        &mut self.velocity
+    }
+
+    fn object(&self) -> &Object {
+        &self.object
+    }
+
+    fn velocity(&self) -> &Vec2 {
+        &self.velocity
+    }
+
+    fn set_grounded(&mut self, _grounded: bool) {}
+
+    fn animate(& self) -> & Animate {
+        & self.animate
+    }
+    fn mut_animate(&mut self) -> &mut Animate {
+        &mut self.animate
+    }
+    fn handle_world_border(&mut self, _world_bounds: WorldBounds) -> Option<GameEvent> {
+        None // it only ever moves up and down in its pipe
+    }
+    fn collision_mask(&self) -> CollisionMask {
+        // it goes through the pipe, the player, fireballs and shells resolve their contact with it themselves
+        CollisionMask::of(&[])
+    }
+    fn get_collision_handler(&self, _object_type: ObjectType) -> CollisionBehavior {
+        CollisionBehavior::DoNothing
+    }
+}
+impl PiranhaPlant {
+    // x is the plant's left, in the middle of the two tile wide pipe, pipe_top the y of the pipe's top edge
+    pub(crate) fn new(x: usize, pipe_top: usize) -> PiranhaPlant {
+        let mut object = Object::new(x, pipe_top, ObjectType::Enemy(EnemyType::PiranhaPlant(PlantState::Hidden))).with_gravity_scale(0.0);
+        object.height = MARIO_SPRITE_BLOCK_SIZE * 3 / 2;
+        let mut plant = PiranhaPlant {
+            object,
+            velocity: Vec2::ZERO,
+            animate: Animate::new(0.5),
+            pipe_top: pipe_top as f32,
+            phase_frames: PLANT_HIDDEN_FRAMES,
+            player_near: false,
+        };
+        plant.update_hitbox();
+        plant.animate.change_animation_sprites(PLANT_FRAMES);
+        plant
+    }
+    pub(crate) fn state(&self) -> PlantState {
+        match self.object.object_type {
+            ObjectType::Enemy(EnemyType::PiranhaPlant(state)) => state,
+            _ => unreachable!("a Piranha Plant's object is always a Piranha Plant"),
+        }
+    }
+    fn set_state(&mut self, state: PlantState) {
+        self.object.object_type = ObjectType::Enemy(EnemyType::PiranhaPlant(state));
+    }
+    // how much of it is out of the pipe, in pixels
+    fn visible_height(&self) -> f32 {
+        (self.pipe_top - self.object.pos.y).clamp(0.0, self.object.height as f32)
+    }
+    // only the part out of the pipe can be touched, a little narrower than the head
+    fn update_hitbox(&mut self) {
+        self.object.hitbox = Some(Rect::new(2.0, 0.0, MARIO_SPRITE_BLOCK_SIZE as f32 - 4.0, self.visible_height()));
+    }
+    // a player on the pipe or right next to it, the plant stays down then. the pipe is a tile to each side of its middle
+    pub(crate) fn kept_down_by(&self, player: &Object) -> bool {
+        let middle = self.object.hitbox().center().x;
+        let reach = MARIO_SPRITE_BLOCK_SIZE as f32 * 2.0;
+        let hitbox = player.hitbox();
+        hitbox.right() > middle - reach && hitbox.left() < middle + reach
+    }
+    pub(crate) fn update(
+        &mut self,
+        surrounding_objects: &mut Vec<SurroundingObject>,
+        world_bounds: WorldBounds,
+        physics: &PhysicsConfig,
+        game_events: &mut Vec<GameEvent>,
+    ) -> u32 {
+        match self.state() {
+            PlantState::Hidden => {
+                self.phase_frames = self.phase_frames.saturating_sub(1);
+                if self.phase_frames == 0 && !self.player_near {
+                    self.set_state(PlantState::Rising);
+                }
+            }
+            PlantState::Out => {
+                self.phase_frames = self.phase_frames.saturating_sub(1);
+                if self.phase_frames == 0 {
+                    self.set_state(PlantState::Sinking);
+                }
+            }
+            PlantState::Rising | PlantState::Sinking => {}
+        }
+        self.velocity.y = match self.state() {
+            PlantState::Rising => -PLANT_SPEED,
+            PlantState::Sinking => PLANT_SPEED,
+            PlantState::Hidden | PlantState::Out => 0.0,
+        };
+        let collision_pairs = Updatable::update(self, surrounding_objects, world_bounds, physics, game_events);
+        let out_y = self.pipe_top - self.object.height as f32;
+        if self.state() == PlantState::Rising && self.object.pos.y <= out_y {
+            self.object.pos.y = out_y;
+            self.set_state(PlantState::Out);
+            self.phase_frames = PLANT_OUT_FRAMES;
+        } else if self.state() == PlantState::Sinking && self.object.pos.y >= self.pipe_top {
+            self.object.pos.y = self.pipe_top;
+            self.set_state(PlantState::Hidden);
+            self.phase_frames = PLANT_HIDDEN_FRAMES;
+        }
+        self.update_hitbox();
+        collision_pairs
+    }
+    pub(crate) fn draw(&self, assets: &Assets, camera_x: usize, camera_y: usize) {
+        // the part still in the pipe is cut off, like a powerup coming out of its block
+        let params = DrawParams::new(self.object.pos, self.object.width, self.object.height)
+            .facing(&self.velocity)
+            .portion(DrawPortion::Top(self.visible_height() / self.object.height as f32));
+        self.animate.draw(assets, params, camera_x, camera_y)
+    }
+}
+// fired by a launcher, flies straight on through blocks and other enemies. a stomp takes it down like a Goomba
+#[derive(Clone)]
+pub struct BulletBill {
+    pub(crate) object: Object,
+    pub(crate) velocity: Vec2,
+    pub(crate) animate: Animate,
+    pub(crate) direction: f32, // -1.0 left, 1.0 right
+}
+impl Updatable for BulletBill {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn mut_object(&mut self) -> &mut Object {
+        &mut self.object
+    }
+
+    fn mut_velocity(&mut self) -> &mut Vec2 {
+        &mut self.velocity
+    }
+
+    fn object(&self) -> &Object {
+        &self.object
+    }
+
+    fn velocity(&self) -> &Vec2 {
+        &self.velocity
+    }
+
+    fn set_grounded(&mut self, _: bool) {}
+
+    fn animate(& self) -> & Animate {
+        & self.animate
+    }
+    fn mut_animate(&mut self) -> &mut Animate {
+        &mut self.animate
+    }
+    // it flies level however long it is out
+    fn apply_gravity(&mut self, _: &PhysicsConfig) {}
+    fn handle_world_border(&mut self, world_bounds: WorldBounds) -> Option<GameEvent> {
+        // out of the room it is gone, without points like an enemy falling out of the world
+        let right = self.object.pos.x + self.object.width as f32;
+        if right < world_bounds.min_x as f32 || self.object.pos.x > world_bounds.max_x as f32 {
+            return Some(GameEvent {
+                event: GameEventType::Kill,
+                triggered_by: self.object,
+                target: None,
+                entity: None,
+                coin: None,
+                source: None,
+                contact: None,
+                player: None,
+            });
+        }
+        None
+    }
+    fn collision_mask(&self) -> CollisionMask {
+        // through everything, the player and shells resolve their contact with it themselves
+        CollisionMask::of(&[])
+    }
+    fn get_collision_handler(&self, _object_type: ObjectType) -> CollisionBehavior {
+        CollisionBehavior::DoNothing
+    }
+}
+impl BulletBill {
+    pub(crate) fn new(x: usize, y: usize, direction: f32) -> BulletBill {
+        let mut bullet = BulletBill {
+            object: Object::new(x, y, ObjectType::Enemy(EnemyType::BulletBill)).with_gravity_scale(0.0),
+            velocity: Vec2::new(BULLET_SPEED * direction, 0.0),
+            animate: Animate::new(1.0),
+            direction,
+        };
+        bullet.animate.change_animation_sprites(BULLET_FRAMES);
+        bullet
+    }
+    pub(crate) fn update(
+        &mut self,
+        surrounding_objects: &mut Vec<SurroundingObject>,
+        world_bounds: WorldBounds,
+        physics: &PhysicsConfig,
+        game_events: &mut Vec<GameEvent>,
+    ) -> u32 {
+        self.velocity.x = BULLET_SPEED * self.direction; // no air friction either
+        Updatable::update(self, surrounding_objects, world_bounds, physics, game_events)
+    }
+    pub(crate) fn draw(&self, assets: &Assets, camera_x: usize, camera_y: usize) {
+        let params = DrawParams::new(self.object.pos, self.object.width, self.object.height).facing(&self.velocity);
+        self.animate.draw(assets, params, camera_x, camera_y)
+    }
+}
+// thrown by a Lakitu as an egg, it hatches once it lands and walks like a Goomba towards where it was thrown
+#[derive(Clone)]
+pub struct Spiny {
+    pub(crate) object: Object,
+    pub(crate) velocity: Vec2,
+    pub(crate) animate: Animate,
+    pub(crate) is_grounded: bool,
+    pub(crate) hatched: bool,
+    pub(crate) direction: f32, // -1.0 left, 1.0 right
+}
+impl Updatable for Spiny {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn mut_object(&mut self) -> &mut Object {
+        &mut self.object
+    }
+
+    fn mut_velocity(&mut self) -> &mut Vec2 {
+        &mut self.velocity
+    }
+
+    fn object(&self) -> &Object {
+        &self.object
+    }
+
+    fn velocity(&self) -> &Vec2 {
+        &self.velocity
+    }
+
+    fn set_grounded(&mut self, grounded: bool) {
+        self.is_grounded = grounded;
+    }
+
+    fn animate(&self) -> &Animate {
+        &self.animate
+    }
+    fn mut_animate(&mut self) -> &mut Animate {
+        &mut self.animate
+    }
+    fn handle_world_border(&mut self, world_bounds: WorldBounds) -> Option<GameEvent> {
+        if self.object.pos.x < world_bounds.min_x as f32 {
+            self.object.pos.x = world_bounds.min_x as f32;
+            self.velocity.x = 0.0;
+        }
+        if self.object.pos.x + self.object.width as f32 > world_bounds.max_x as f32 {
+            self.object.pos.x = world_bounds.max_x as f32 - self.object.width as f32;
+            self.velocity.x = 0.0;
+        }
+        if self.object.pos.y > world_bounds.max_y as f32 {
+            return Some(GameEvent {
+                event: GameEventType::Kill,
+                triggered_by: self.object,
+                target: None,
+                entity: None,
+                coin: None,
+                source: None,
+                contact: None,
+                player: None,
+            });
+        }
+        None
+    }
+    fn collision_mask(&self) -> CollisionMask {
+        CollisionMask::of(&[CollisionLayer::Block, CollisionLayer::Powerup, CollisionLayer::Projectile])
+    }
+    fn get_collision_handler(&self, object_type: ObjectType) -> CollisionBehavior {
+        match object_type {
+            ObjectType::Block(_) => CollisionBehavior::EnemyBlock,
+            ObjectType::Enemy(_) => CollisionBehavior::Enemy,
+            ObjectType::Player => CollisionBehavior::DoNothing, // the player resolves it, like a Goomba
+            ObjectType::Powerup(_) => CollisionBehavior::Enemy,
+            ObjectType::Coin => CollisionBehavior::DoNothing,
+            ObjectType::Fireball => CollisionBehavior::DoNothing, // World::resolve_fireball_hits kills it
+        }
+    }
+    fn update_animation(&mut self) {
+        if !self.hatched {
+            self.animate.change_animation_sprites(SPINY_FRAMES.part(0, 1));
+        } else {
+            self.animate.change_animation_sprites(SPINY_FRAMES.part(1, 3));
+        }
+    }
+}
+impl Spiny {
+    // already hatched, for one placed in the level
+    pub(crate) fn new(x: usize, y: usize, direction: f32) -> Spiny {
+        let mut spiny = Spiny::new_egg(x, y, direction);
+        spiny.hatched = true;
+        spiny.velocity = Vec2::new(direction, 0.0);
+        spiny.update_animation();
+        spiny
+    }
+    pub(crate) fn new_egg(x: usize, y: usize, direction: f32) -> Spiny {
+        let mut spiny = Spiny {
+            object: Object::new(x, y, ObjectType::Enemy(EnemyType::Spiny)),
+            velocity: Vec2::new(direction, -SPINY_THROW_VELOCITY),
+            animate: Animate::new(1.0),
+            is_grounded: false,
+            hatched: false,
+            direction,
+        };
+        spiny.update_animation();
+        spiny
+    }
+    pub(crate) fn update(
+        &mut self,
+        surrounding_objects: &mut Vec<SurroundingObject>,
+        world_bounds: WorldBounds,
+        physics: &PhysicsConfig,
+        game_events: &mut Vec<GameEvent>,
+    ) -> u32 {
+        if self.hatched {
+            self.velocity.x = 1.0 * self.velocity.x.signum(); // no friction, like a Goomba
+        }
+        let collision_pairs = Updatable::update(self, surrounding_objects, world_bounds, physics, game_events);
+        if !self.hatched && self.is_grounded {
+            self.hatched = true;
+            self.velocity.x = self.direction;
+        }
+        collision_pairs
+    }
+    pub(crate) fn draw(&self, assets: &Assets, camera_x: usize, camera_y: usize) {
+        let params = DrawParams::new(self.object.pos, self.object.width, self.object.height).facing(&self.velocity);
+        self.animate.draw(assets, params, camera_x, camera_y)
+    }
+}
+// rides its cloud near the top, over the middle of the view once the camera came near. the world steers it and throws
+// its eggs, it only flies where it is told. nothing but the player touches it up there
+#[derive(Clone)]
+pub struct Lakitu {
+    pub(crate) object: Object,
+    pub(crate) velocity: Vec2,
+    pub(crate) animate: Animate,
+    pub(crate) target_x: Option<f32>, // set by the world before each update, None while it waits for the camera
+    pub(crate) throw_frames: u32, // physics frames until the next egg
+}
+impl Updatable for Lakitu {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn mut_object(&mut self) -> &mut Object {
+        &mut self.object
+    }
+
+    fn mut_velocity(&mut self) -> &mut Vec2 {
+        &mut self.velocity
+    }
+
+    fn object(&self) -> &Object {
+        &self.object
+    }
+
+    fn velocity(&self) -> &Vec2 {
+        &self.velocity
+    }
+
+    fn set_grounded(&mut self, _: bool) {}
+
+    fn animate(&self) -> &Animate {
+        &self.animate
+    }
+    fn mut_animate(&mut self) -> &mut Animate {
+        &mut self.animate
+    }
+    // the cloud holds it up
+    fn apply_gravity(&mut self, _: &PhysicsConfig) {}
+    fn handle_world_border(&mut self, world_bounds: WorldBounds) -> Option<GameEvent> {
+        let max_x = (world_bounds.max_x - self.object.width) as f32;
+        self.object.pos.x = self.object.pos.x.clamp(world_bounds.min_x as f32, max_x);
+        None
+    }
+    fn collision_mask(&self) -> CollisionMask {
+        // it flies over the blocks, the player resolves a stomp on it like on a Goomba
+        CollisionMask::of(&[])
+    }
+    fn get_collision_handler(&self, _object_type: ObjectType) -> CollisionBehavior {
+        CollisionBehavior::DoNothing
+    }
+}
+impl Lakitu {
+    pub(crate) fn new(x: usize, y: usize) -> Lakitu {
+        let mut object = Object::new(x, y, ObjectType::Enemy(EnemyType::Lakitu)).with_gravity_scale(0.0);
+        object.height = MARIO_SPRITE_BLOCK_SIZE * 3 / 2;
+        let mut lakitu = Lakitu {
+            object,
+            velocity: Vec2::ZERO,
+            animate: Animate::new(0.5),
+            target_x: None,
+            throw_frames: LAKITU_THROW_FRAMES,
+        };
+        // bobbing on the cloud the whole time, only drawn, the hitbox stays put
+        let bob = [0.0, -1.0, -2.0, -1.0].map(|y| Vec2::new(0.0, y)).to_vec();
+        let bobbing = PlayAnimationBuilder::new(LAKITU_FRAMES).pos_offset_frames(bob).loop_for(f32::INFINITY).build();
+        lakitu.animate.play_animation(bobbing);
+        lakitu
+    }
+    pub(crate) fn update(
+        &mut self,
+        surrounding_objects: &mut Vec<SurroundingObject>,
+        world_bounds: WorldBounds,
+        physics: &PhysicsConfig,
+        game_events: &mut Vec<GameEvent>,
+    ) -> u32 {
+        self.velocity.x = self.target_x.map_or(0.0, |target_x| (target_x - self.object.pos.x).clamp(-LAKITU_SPEED, LAKITU_SPEED));
+        Updatable::update(self, surrounding_objects, world_bounds, physics, game_events)
+    }
+    pub(crate) fn draw(&self, assets: &Assets, camera_x: usize, camera_y: usize) {
+        // it looks the way the camera goes, not the way it catches up
+        let params = DrawParams::new(self.object.pos, self.object.width, self.object.height);
+        self.animate.draw(assets, params, camera_x, camera_y)
+    }
+}
+#[derive(Clone)]
+pub struct PowerUp {
+    pub(crate) object: Object,
+    pub(crate) velocity: Vec2,
+    pub(crate) animate: Animate,
+}
+impl Updatable for PowerUp {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn mut_object(&mut self) -> &mut Object {
+        &mut self.object
+    }
+
+    fn mut_velocity(&mut self) -> &mut Vec2 {
+        &mut self.velocity
+    }
+
+    fn object(&self) -> &Object {
+        &self.object
+    }
+
+    fn velocity(&self) -> &Vec2 {
+        &self.velocity
+    }
+
+    fn set_grounded(&mut self, _: bool) {}
+
+    fn animate(& self) -> & Animate {
+        & self.animate
+    }
+    fn mut_animate(&mut self) -> &mut Animate {
+        &mut self.animate
+    }
+    fn collision_mask(&self) -> CollisionMask {
+        CollisionMask::of(&[CollisionLayer::Block, CollisionLayer::Enemy])
+    }
+    fn get_collision_handler(&self, other: ObjectType) -> CollisionBehavior {
+        match other {
+            ObjectType::Block(_) => CollisionBehavior::EnemyBlock, // powerup behaves like enemy
+            ObjectType::Enemy(_) => CollisionBehavior::Enemy,
+            _ => CollisionBehavior::DoNothing,
+        }
+    }
+
+    fn handle_world_border(&mut self, world_bounds: WorldBounds) -> Option<GameEvent> {
+        if self.object.pos.x < world_bounds.min_x as f32 {
+            self.object.pos.x = world_bounds.min_x as f32;
+            self.velocity.x = 0.0;
+        }
+        if self.object.pos.x + self.object.width as f32 > world_bounds.max_x as f32 {
+            self.object.pos.x = world_bounds.max_x as f32 - self.object.width as f32;
+            self.velocity.x = 0.0;
+        }
+        if self.object.pos.y > world_bounds.max_y as f32 {
+            return Some(GameEvent {
+                event: GameEventType::Kill,
+                triggered_by: self.object,
+                target: None,
+                entity: None,
+                coin: None,
+                source: None,
+                contact: None,
+                player: None,
+            });
+        }
+        None
+    }
+}
+
+impl PowerUp {
+    pub(crate) fn new(x: usize, y: usize, powerup_type: PowerupType) -> PowerUp {
+        let (velocity, frames) = match powerup_type {
+            PowerupType::Mushroom => (Vec2::new(1.0, 0.0), POWERUP_FRAMES),
+            PowerupType::FireFlower => (Vec2::ZERO, FIRE_FLOWER_FRAMES), // stays on its block
+            PowerupType::Star => (Vec2::new(1.0, 0.0), STAR_FRAMES),
+        };
+        let mut powerup = PowerUp {
+            object: Object::new(x, y, ObjectType::Powerup(powerup_type)),
+            velocity,
+            animate: Animate::new(1.0),
+        };
+        powerup
+            .animate
+            .change_animation_sprites(frames);
+        powerup
+    }
+    pub(crate) fn update(
+        &mut self,
+        surrounding_objects: &mut Vec<SurroundingObject>,
+        world_bounds: WorldBounds,
+        physics: &PhysicsConfig,
+        game_events: &mut Vec<GameEvent>,
+    ) -> u32 {
+        if matches!(self.object.object_type, ObjectType::Powerup(PowerupType::Mushroom | PowerupType::Star)) {
+            self.velocity.x = 1.0 * self.velocity.x.signum(); // avoid friction atm;
+        }
+        let falling = self.velocity.y > 0.0;
+        let collision_pairs = Updatable::update(self, surrounding_objects, world_bounds, physics, game_events);
+        // a star goes straight back up off whatever it lands on
+        if self.object.object_type == ObjectType::Powerup(PowerupType::Star) && falling && self.velocity.y == 0.0 {
+            self.velocity.y = -STAR_BOUNCE_VELOCITY;
+        }
+        collision_pairs
+    }
+    pub(crate) fn draw(&self, assets: &Assets, camera_x: usize, camera_y: usize) {
+        let params = DrawParams::new(self.object.pos, self.object.width, self.object.height).facing(&self.velocity);
+        self.animate.draw(assets, params, camera_x, camera_y)
+    }
+}
+// thrown by a player with fire, bounces along the ground until it hits a wall or an enemy
+#[derive(Clone)]
+pub struct Fireball {
+    pub(crate) object: Object,
+    pub(crate) velocity: Vec2,
+    pub(crate) animate: Animate,
+    pub(crate) owner: usize, // the player who threw it, each one has only so many out at once
+    pub(crate) direction: f32, // -1.0 left, 1.0 right
+    pub(crate) spent: bool, // stopped by something, the world takes it out
+}
+impl Updatable for Fireball {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn mut_object(&mut self) -> &mut Object {
+        &mut self.object
+    }
+
+    fn mut_velocity(&mut self) -> &mut Vec2 {
+        &mut self.velocity
+    }
+
+    fn object(&self) -> &Object {
+        &self.object
+    }
+
+    fn velocity(&self) -> &Vec2 {
+        &self.velocity
+    }
+
+    fn set_grounded(&mut self, _: bool) {}
+
+    fn animate(& self) -> & Animate {
+        & self.animate
+    }
+    fn mut_animate(&mut self) -> &mut Animate {
+        &mut self.animate
+    }
+    // never rests on anything, the floor bounces it back up in the collision handler
+    fn ground_surface(&self, _: &[SurroundingObject], _: &PhysicsConfig) -> Option<Surface> {
+        None
+    }
+    fn collision_mask(&self) -> CollisionMask {
+        // the enemies it hits are found by World::resolve_fireball_hits, where they are after they moved
+        CollisionMask::of(&[CollisionLayer::Block])
+    }
+    fn get_collision_handler(&self, other: ObjectType) -> CollisionBehavior {
+        match other {
+            ObjectType::Block(_) | ObjectType::Enemy(_) => CollisionBehavior::Fireball,
+            _ => CollisionBehavior::DoNothing,
+        }
+    }
+    fn handle_world_border(&mut self, world_bounds: WorldBounds) -> Option<GameEvent> {
+        let right = self.object.pos.x + self.object.width as f32;
+        if self.object.pos.x < world_bounds.min_x as f32 || right > world_bounds.max_x as f32 || self.object.pos.y > world_bounds.max_y as f32 {
+            self.spent = true;
+        }
+        None
+    }
+}
+impl Fireball {
+    pub(crate) fn new(x: usize, y: usize, direction: f32, owner: usize) -> Fireball {
+        let mut object = Object::new(x, y, ObjectType::Fireball);
+        // the ball is the middle of the sprite, a tile sized object keeps it out of the cells of the floor it bounces on
+        let ball = MARIO_SPRITE_BLOCK_SIZE as f32 / 2.0;
+        object.hitbox = Some(Rect::new(ball / 2.0, ball / 2.0, ball, ball));
+        let mut fireball = Fireball {
+            object,
+            velocity: Vec2::new(FIREBALL_SPEED * direction, FIREBALL_SPEED / 2.0),
+            animate: Animate::new(2.0),
+            owner,
+            direction,
+            spent: false,
+        };
+        fireball
+            .animate
+            .change_animation_sprites(FIREBALL_FRAMES);
+        fireball
+    }
+    pub(crate) fn update(
+        &mut self,
+        surrounding_objects: &mut Vec<SurroundingObject>,
+        world_bounds: WorldBounds,
+        physics: &PhysicsConfig,
+        game_events: &mut Vec<GameEvent>,
+    ) -> u32 {
+        self.velocity.x = FIREBALL_SPEED * self.direction; // no friction in the air either
+        let collision_pairs = Updatable::update(self, surrounding_objects, world_bounds, physics, game_events);
+        // a wall stops it sideways and an enemy all the way, either is the end of it
+        if self.velocity.x == 0.0 {
+            self.spent = true;
+        }
+        collision_pairs
+    }
+    pub(crate) fn draw(&self, assets: &Assets, camera_x: usize, camera_y: usize) {
+        let params = DrawParams::new(self.object.pos, self.object.width, self.object.height).facing(&self.velocity);
+        self.animate.draw(assets, params, camera_x, camera_y)
+    }
+}
+#[derive(Clone)]
+pub(crate) struct Block {
+    pub(crate) object: Object,
+    pub(crate) texture_id: u8,
+    pub(crate) animate: Animate,
+    pub(crate) cooldown_frames: u32, // a launcher's physics frames until it can fire again
+}
+impl Block {
+    pub(crate) fn new_block(x: usize, y: usize, texture_id: u8) -> Block {
+        let mut block = Block {
+            object: Object::new(x, y, ObjectType::Block(BlockType::Block)),
+            animate: Animate::new(1.0),
+            texture_id,
+            cooldown_frames: 0,
+        };
+        block
+            .animate
+            .change_animation_sprites(TextureFrames::single(TextureId::tile(texture_id)));
+        block
+    }
+    pub(crate) fn new_powerup_block(x: usize, y: usize, texture_id: u8) -> Block {
+        let mut block = Block {
+            object: Object::new(x, y, ObjectType::Block(BlockType::PowerupBlock)),
+            animate: Animate::new(1.0),
+            texture_id,
+            cooldown_frames: 0,
+        };
+        block
+            .animate
+            .change_animation_sprites(TextureFrames::single(TextureId::tile(block.texture_id)));
+        block
+    }
+    // placed with a marker, its sprite isn't one of the tiles
+    pub(crate) fn new_launcher(x: usize, y: usize) -> Block {
+        let mut block = Block {
+            object: Object::new(x, y, ObjectType::Block(BlockType::Launcher)),
+            animate: Animate::new(1.0),
+            texture_id: 0,
+            cooldown_frames: LAUNCHER_COOLDOWN_FRAMES,
+        };
+        block.animate.change_animation_sprites(LAUNCHER_FRAMES.part(0, 1));
+        block
+    }
+    // the puff of smoke, the bullet itself is the world's to add
+    pub(crate) fn fire(&mut self) {
+        self.cooldown_frames = LAUNCHER_COOLDOWN_FRAMES;
+        self.animate.play_animation(PlayAnimationBuilder::new(LAUNCHER_FRAMES.part(1, 3)).build());
+    }
+    pub(crate) fn transform_into_regular_block(&mut self) {
+        self.object.object_type = ObjectType::Block(BlockType::Block);
+        self.
+        animate
+        .change_animation_sprites(TextureFrames::single(TextureId::tile(10)));
+    }
+    pub(crate) fn update(&mut self, physics: &PhysicsConfig) {
+        self.animate.update(physics.frame_time);
+    }
+    pub(crate) fn draw(&self, assets: &Assets, camera_x: usize, camera_y: usize) {
+        let params = DrawParams::new(self.object.pos, self.object.width, self.object.height);
+        self.animate.draw(assets, params, camera_x, camera_y)
+    }
+}
+// static pickup, lives in the grid like a block but only triggers, never blocks movement
+#[derive(Clone)]
+pub(crate) struct Coin {
+    pub(crate) object: Object,
+    pub(crate) animate: Animate,
+}
+impl Coin {
+    pub(crate) fn new(x: usize, y: usize) -> Coin {
+        let mut coin = Coin {
+            object: Object::new(x, y, ObjectType::Coin),
+            animate: Animate::new(0.5),
+        };
+        coin.animate.change_animation_sprites(COIN_FRAMES);
+        coin
+    }
+    pub(crate) fn update(&mut self, physics: &PhysicsConfig) {
+        self.animate.update(physics.frame_time);
+    }
+    pub(crate) fn draw(&self, assets: &Assets, camera_x: usize, camera_y: usize) {
+        let params = DrawParams::new(self.object.pos, self.object.width, self.object.height);
+        self.animate.draw(assets, params, camera_x, camera_y)
+    }
+}
+// everything that moves on its own except the player, a new kind is one more variant here
+#[derive(Clone)]
+pub enum Entity {
+    Goomba(Goomba),
+    Koopa(Koopa),
+    PiranhaPlant(PiranhaPlant),
+    BulletBill(BulletBill),
+    Spiny(Spiny),
+    Lakitu(Lakitu),
+    PowerUp(PowerUp),
+    Fireball(Fireball),
+}
+
+impl Entity {
+    pub(crate) fn updatable(&self) -> &dyn Updatable {
+        match self {
+            Entity::Goomba(goomba) => goomba,
+            Entity::Koopa(koopa) => koopa,
+            Entity::PiranhaPlant(plant) => plant,
+            Entity::BulletBill(bullet) => bullet,
+            Entity::Spiny(spiny) => spiny,
+            Entity::Lakitu(lakitu) => lakitu,
+            Entity::PowerUp(powerup) => powerup,
+            Entity::Fireball(fireball) => fireball,
+        }
+    }
+    pub(crate) fn updatable_mut(&mut self) -> &mut dyn Updatable {
+        match self {
+            Entity::Goomba(goomba) => goomba,
+            Entity::Koopa(koopa) => koopa,
+            Entity::PiranhaPlant(plant) => plant,
+            Entity::BulletBill(bullet) => bullet,
+            Entity::Spiny(spiny) => spiny,
+            Entity::Lakitu(lakitu) => lakitu,
+            Entity::PowerUp(powerup) => powerup,
+            Entity::Fireball(fireball) => fireball,
+        }
+    }
+    pub(crate) fn object(&self) -> &Object {
+        self.updatable().object()
+    }
+    pub(crate) fn is_enemy(&self) -> bool {
+        matches!(self.object().object_type, ObjectType::Enemy(_))
+    }
+    pub(crate) fn as_koopa_mut(&mut self) -> Option<&mut Koopa> {
+        match self {
+            Entity::Koopa(koopa) => Some(koopa),
+            _ => None,
+        }
+    }
+    pub(crate) fn as_fireball(&self) -> Option<&Fireball> {
+        match self {
+            Entity::Fireball(fireball) => Some(fireball),
+            _ => None,
+        }
+    }
+    // each kind's own update, which may adjust itself before the shared Updatable::update
+    pub(crate) fn update(&mut self, surrounding_objects: &mut Vec<SurroundingObject>, world_bounds: WorldBounds, physics: &PhysicsConfig, game_events: &mut Vec<GameEvent>) -> u32 {
+        match self {
+            Entity::Goomba(goomba) => goomba.update(surrounding_objects, world_bounds, physics, game_events),
+            Entity::Koopa(koopa) => koopa.update(surrounding_objects, world_bounds, physics, game_events),
+            Entity::PiranhaPlant(plant) => plant.update(surrounding_objects, world_bounds, physics, game_events),
+            Entity::BulletBill(bullet) => bullet.update(surrounding_objects, world_bounds, physics, game_events),
+            Entity::Spiny(spiny) => spiny.update(surrounding_objects, world_bounds, physics, game_events),
+            Entity::Lakitu(lakitu) => lakitu.update(surrounding_objects, world_bounds, physics, game_events),
+            Entity::PowerUp(powerup) => powerup.update(surrounding_objects, world_bounds, physics, game_events),
+            Entity::Fireball(fireball) => fireball.update(surrounding_objects, world_bounds, physics, game_events),
+        }
+    }
+    pub(crate) fn draw(&self, assets: &Assets, camera_x: usize, camera_y: usize) {
+        match self {
+            Entity::Goomba(goomba) => goomba.draw(assets, camera_x, camera_y),
+            Entity::Koopa(koopa) => koopa.draw(assets, camera_x, camera_y),
+            Entity::PiranhaPlant(plant) => plant.draw(assets, camera_x, camera_y),
+            Entity::BulletBill(bullet) => bullet.draw(assets, camera_x, camera_y),
+            Entity::Spiny(spiny) => spiny.draw(assets, camera_x, camera_y),
+            Entity::Lakitu(lakitu) => lakitu.draw(assets, camera_x, camera_y),
+            Entity::PowerUp(powerup) => powerup.draw(assets, camera_x, camera_y),
+            Entity::Fireball(fireball) => fireball.draw(assets, camera_x, camera_y),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATES: [JumpState; 6] = [
+        JumpState::Grounded,
+        JumpState::Coyote(COYOTE_FRAMES),
+        JumpState::Coyote(1),
+        JumpState::Airborne,
+        JumpState::Buffered(JUMP_BUFFER_FRAMES),
+        JumpState::Buffered(1),
+    ];
+
+    // what every state does on a frame, (grounded, jump pressed) -> (next state, jumps)
+    fn expected(state: JumpState, grounded: bool, jump_pressed: bool) -> (JumpState, bool) {
+        use JumpState::*;
+        match (state, grounded, jump_pressed) {
+            // on the ground a press jumps, and so does a press buffered before landing
+            (_, true, true) => (Airborne, true),
+            (Buffered(_), true, false) => (Airborne, true),
+            (_, true, false) => (Grounded, false),
+            // just off a ledge a press still jumps, the frames run out one per frame
+            (Grounded | Coyote(_), false, true) => (Airborne, true),
+            (Grounded, false, false) => (Coyote(COYOTE_FRAMES), false),
+            (Coyote(1), false, false) => (Airborne, false),
+            (Coyote(frames), false, false) => (Coyote(frames - 1), false),
+            // a press in the air waits for the ground, pressing again starts the wait over
+            (Airborne | Buffered(_), false, true) => (Buffered(JUMP_BUFFER_FRAMES), false),
+            (Buffered(1), false, false) => (Airborne, false),
+            (Buffered(frames), false, false) => (Buffered(frames - 1), false),
+            (Airborne, false, false) => (Airborne, false),
+        }
+    }
+
+    #[test]
+    fn every_jump_state_transition() {
+        for state in STATES {
+            for grounded in [false, true] {
+                for jump_pressed in [false, true] {
+                    assert_eq!(
+                        state.step(grounded, jump_pressed),
+                        expected(state, grounded, jump_pressed),
+                        "{:?} grounded {} pressed {}",
+                        state,
+                        grounded,
+                        jump_pressed
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_press_buffered_during_coyote_time_jumps_once() {
+        // off a ledge, a press jumps, another press before landing is buffered and fires on touchdown only
+        let (state, jumped) = JumpState::Grounded.step(false, false);
+        assert_eq!(state, JumpState::Coyote(COYOTE_FRAMES));
+        assert!(!jumped);
+        let (state, jumped) = state.step(false, true);
+        assert!(jumped);
+        let (state, jumped) = state.step(false, true);
+        assert_eq!(state, JumpState::Buffered(JUMP_BUFFER_FRAMES));
+        assert!(!jumped);
+        let (state, jumped) = state.step(true, false);
+        assert!(jumped);
+        assert_eq!(state.step(true, false), (JumpState::Grounded, false));
+    }
+
+    #[test]
+    fn coyote_time_and_the_buffer_last_their_frames() {
+        let mut state = JumpState::Grounded.step(false, false).0;
+        for _ in 1..COYOTE_FRAMES {
+            state = state.step(false, false).0;
+        }
+        assert_eq!(state, JumpState::Coyote(1));
+        assert_eq!(state.step(false, true), (JumpState::Airborne, true));
+        assert_eq!(state.step(false, false), (JumpState::Airborne, false));
+
+        let mut state = JumpState::Airborne.step(false, true).0;
+        for _ in 1..JUMP_BUFFER_FRAMES {
+            state = state.step(false, false).0;
+        }
+        assert_eq!(state.step(true, false), (JumpState::Airborne, true));
+        // one frame too late, landing doesn't jump
+        assert_eq!(state.step(false, false).0.step(true, false), (JumpState::Grounded, false));
+    }
+
+    #[test]
+    fn a_powerup_animates_one_step_per_physics_step() {
+        let bounds = WorldBounds { min_x: 0, max_x: 1000, max_y: 1000, goal_x: None };
+        let physics = PhysicsConfig::default();
+        let (mut surrounding, mut events) = (Vec::new(), Vec::new());
+        for powerup_type in [PowerupType::Mushroom, PowerupType::FireFlower, PowerupType::Star] {
+            let mut powerup = PowerUp::new(100, 100, powerup_type);
+            let (frames, time_to_change) = (powerup.animate.frames.len().max(1), powerup.animate.time_to_change);
+            let steps_per_frame = (time_to_change / physics.frame_time).round() as usize;
+            for step in 1..=4 * steps_per_frame * frames {
+                powerup.update(&mut surrounding, bounds, &physics, &mut events);
+                // away from where a frame changes, the summed up frame times may land on either side of it
+                if step % steps_per_frame == steps_per_frame / 2 {
+                    let expected = (step / steps_per_frame) % frames;
+                    assert_eq!(powerup.animate.current_frame_index, expected, "{:?} after {} steps", powerup_type, step);
+                    let elapsed = (step % steps_per_frame) as f32 * physics.frame_time;
+                    assert!((powerup.animate.time_elapsed - elapsed).abs() < 1e-4, "{:?} after {} steps", powerup_type, step);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+// a headless game for bots and AI experiments: one physics frame per step, an observation back, nothing drawn or played.
+// the world steps the same as in the game, so a bot's run is a run the game could have had
+use std::path::{Path, PathBuf};
+
+use macroquad::math::Vec2;
+
+use crate::assets::AssetError;
+use crate::audio::AudioManager;
+use crate::config::{MARIO_SPRITE_BLOCK_SIZE, MARIO_WORLD_SIZE};
+use crate::entities::{BlockType, ObjectType, PlayerState};
+use crate::input::InputState;
+use crate::world::{GameState, LevelFiles, ObjectReference, StepDriver, World};
+
+// tiles the observed grid reaches from the player's tile in each direction
+pub const OBSERVATION_RADIUS: usize = 4;
+pub const OBSERVATION_SIZE: usize = OBSERVATION_RADIUS * 2 + 1;
+
+// what is in a tile of the observed grid
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Cell {
+    #[default]
+    Empty,
+    Solid,
+    PowerupBlock, // solid too, bumping it from below lets a powerup out
+    Enemy,
+    Powerup,
+    Coin,
+    Player, // the observed player itself included
+    OutOfLevel, // past the edges, below the bottom is a pit
+}
+
+impl Cell {
+    fn priority(self) -> u8 {
+        match self {
+            Cell::Empty => 0,
+            Cell::Player => 1,
+            Cell::Coin => 2,
+            Cell::Powerup => 3,
+            Cell::Enemy => 4,
+            Cell::Solid | Cell::PowerupBlock | Cell::OutOfLevel => 5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Playing,
+    Won,
+    Lost, // from the moment the player dies, the death jingle isn't waited for
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlayerObservation {
+    pub pos: Vec2, // top left, in pixels
+    pub velocity: Vec2,
+    pub grounded: bool,
+    pub big: bool,
+    pub fire: bool, // big too, run throws fireballs
+    pub star: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Observation {
+    pub frame: u32, // physics frames stepped since the last reset
+    pub player: PlayerObservation,
+    pub grid: [[Cell; OBSERVATION_SIZE]; OBSERVATION_SIZE], // grid[row][column], the player's tile in the middle
+    pub score: u32,
+    pub coins: usize,
+    pub time_left: u32, // in the clock's time units
+    pub outcome: Outcome,
+}
+
+impl Observation {
+    pub fn done(&self) -> bool {
+        self.outcome != Outcome::Playing
+    }
+
+    // dx to the right, dy down, in tiles from the player's
+    pub fn cell(&self, dx: isize, dy: isize) -> Cell {
+        let column = OBSERVATION_RADIUS as isize + dx;
+        let row = OBSERVATION_RADIUS as isize + dy;
+        if !(0..OBSERVATION_SIZE as isize).contains(&column) || !(0..OBSERVATION_SIZE as isize).contains(&row) {
+            return Cell::OutOfLevel;
+        }
+        self.grid[row as usize][column as usize]
+    }
+}
+
+// the bot's input for the one step run_step asks for
+struct BotInput(InputState);
+
+impl StepDriver for BotInput {
+    fn next_input(&mut self) -> InputState {
+        self.0
+    }
+
+    fn stepped(&mut self, _world: &World, _input: &InputState) {}
+}
+
+pub struct Game {
+    world: World,
+    level_path: PathBuf,
+    files: LevelFiles, // read once, every reset loads from them
+    previous_input: InputState,
+    frame: u32,
+}
+
+impl Game {
+    pub fn new(level_path: &Path) -> Result<Game, AssetError> {
+        let files = LevelFiles::read(level_path)?;
+        let mut world = World::new_headless(MARIO_WORLD_SIZE.height, MARIO_WORLD_SIZE.width);
+        // the recording a headless world keeps of its sounds would grow with every episode
+        world.audio = AudioManager::silent();
+        world.load_level_from(level_path, &files)?;
+        Ok(Game {
+            world,
+            level_path: level_path.to_owned(),
+            files,
+            previous_input: InputState::default(),
+            frame: 0,
+        })
+    }
+
+    // the level from the start again, with a fresh clock and score
+    pub fn reset(&mut self) -> Observation {
+        self.world
+            .load_level_from(&self.level_path, &self.files)
+            .expect("the level loaded before, from the same files");
+        self.previous_input = InputState::default();
+        self.frame = 0;
+        self.observe()
+    }
+
+    // one physics frame. the edges (jump_pressed, run_pressed) are taken against the previous step's input,
+    // a bot only says what is held. after the game is done stepping does nothing
+    pub fn step(&mut self, input: InputState) -> Observation {
+        let mut input = input;
+        input.update_edges(&self.previous_input);
+        if self.world.run_step(&mut BotInput(input)) {
+            self.previous_input = input;
+            self.frame += 1;
+        }
+        self.observe()
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn observe(&self) -> Observation {
+        let player = &self.world.players[0];
+        let outcome = match self.world.game_state {
+            GameState::GameWon => Outcome::Won,
+            GameState::Dying(_) | GameState::GameOver => Outcome::Lost,
+            _ => Outcome::Playing,
+        };
+        Observation {
+            frame: self.frame,
+            player: PlayerObservation {
+                pos: player.object.pos,
+                velocity: player.velocity,
+                // the same as a jump sees it, the first frames of a jump still touch the floor but are on the way up
+                grounded: player.is_grounded && player.velocity.y >= 0.0,
+                big: matches!(player.power_state, PlayerState::Big | PlayerState::Fire),
+                fire: matches!(player.power_state, PlayerState::Fire),
+                star: player.star_frames > 0,
+            },
+            grid: self.observed_grid(player.object.pos),
+            score: self.world.score().score(),
+            coins: self.world.collected_coins(),
+            time_left: self.world.time_left(),
+            outcome,
+        }
+    }
+
+    fn observed_grid(&self, player_pos: Vec2) -> [[Cell; OBSERVATION_SIZE]; OBSERVATION_SIZE] {
+        let tile = MARIO_SPRITE_BLOCK_SIZE as f32;
+        let center_x = ((player_pos.x + tile / 2.0) / tile).floor() as isize;
+        let center_y = ((player_pos.y + tile / 2.0) / tile).floor() as isize;
+        let mut grid = [[Cell::Empty; OBSERVATION_SIZE]; OBSERVATION_SIZE];
+        for (row, cells) in grid.iter_mut().enumerate() {
+            let y = center_y + row as isize - OBSERVATION_RADIUS as isize;
+            for (column, cell) in cells.iter_mut().enumerate() {
+                let x = center_x + column as isize - OBSERVATION_RADIUS as isize;
+                *cell = if y < 0 || x < 0 {
+                    Cell::OutOfLevel
+                } else {
+                    match self.world.grid.get(x as usize, y as usize) {
+                        // a tile shared by several shows the one that matters most to a bot
+                        Some(references) => references
+                            .iter()
+                            .map(|reference| self.cell_of(*reference))
+                            .max_by_key(|cell| cell.priority())
+                            .unwrap_or(Cell::Empty),
+                        None => Cell::OutOfLevel,
+                    }
+                };
+            }
+        }
+        grid
+    }
+
+    fn cell_of(&self, reference: ObjectReference) -> Cell {
+        match reference {
+            ObjectReference::Block(index) => match self.world.blocks[index].object.object_type {
+                ObjectType::Block(BlockType::PowerupBlock) => Cell::PowerupBlock,
+                _ => Cell::Solid,
+            },
+            ObjectReference::Entity(id) => match self.world.entities.get(id) {
+                Some(entity) if entity.is_enemy() => Cell::Enemy,
+                Some(entity) if entity.as_fireball().is_some() => Cell::Empty, // the player's own, harmless to them
+                Some(_) => Cell::Powerup,
+                None => Cell::Empty,
+            },
+            ObjectReference::Coin(_) => Cell::Coin,
+            ObjectReference::Player(_) => Cell::Player,
+            ObjectReference::None => Cell::Empty,
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use macroquad::math::Vec2;
+
+use crate::config::MARIO_SPRITE_BLOCK_SIZE;
+use crate::world::ObjectReference;
+
+// the grid cells an object covers, a rectangle so it can be compared and walked without allocating
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) struct GridCells {
+    pub(crate) x: usize,
+    pub(crate) y: usize,
+    pub(crate) wide: usize,
+    pub(crate) high: usize,
+}
+
+impl GridCells {
+    // these and the ring of cells around them, anything overlapping an object in these cells is in there
+    pub(crate) fn with_ring(self) -> GridCells {
+        GridCells {
+            x: self.x.saturating_sub(1),
+            y: self.y.saturating_sub(1),
+            wide: self.wide + 1 + self.x.min(1),
+            high: self.high + 1 + self.y.min(1),
+        }
+    }
+
+    pub(crate) fn iter(self) -> impl Iterator<Item = (usize, usize)> {
+        (self.y..self.y + self.high).flat_map(move |y| (self.x..self.x + self.wide).map(move |x| (x, y)))
+    }
+}
+
+// the broadphase: what covers each tile sized cell of the level. a cell can hold several objects, a Goomba walking past
+// a block or two Goombas crossing each other share one, and leaving only takes out the one that left.
+// the references keep the order they came in, the collisions are resolved in it
+#[derive(Clone, Debug)]
+pub(crate) struct Grid {
+    width: usize,
+    height: usize,
+    cells: Vec<Vec<ObjectReference>>, // row after row, a cell keeps its capacity when emptied so moving doesn't allocate
+}
+
+impl Grid {
+    pub(crate) fn new(width: usize, height: usize) -> Grid {
+        Grid {
+            width,
+            height,
+            cells: vec![Vec::new(); width * height],
+        }
+    }
+
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.width && y < self.height).then_some(y * self.width + x)
+    }
+
+    // the cell a position is anchored at, the nearest one to its top left corner. None outside the grid,
+    // the first and last rows and columns are inside
+    pub(crate) fn grid_index(&self, pos: Vec2) -> Option<(usize, usize)> {
+        let x = (pos.x / MARIO_SPRITE_BLOCK_SIZE as f32).round();
+        let y = (pos.y / MARIO_SPRITE_BLOCK_SIZE as f32).round();
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        let (x, y) = (x as usize, y as usize);
+        self.index(x, y).map(|_| (x, y))
+    }
+
+    // None outside the grid, empty for a free cell
+    pub(crate) fn get(&self, x: usize, y: usize) -> Option<&[ObjectReference]> {
+        self.index(x, y).map(|index| self.cells[index].as_slice())
+    }
+
+    // outside the grid (falling out of the level) nothing is kept
+    pub(crate) fn insert_at(&mut self, x: usize, y: usize, reference: ObjectReference) {
+        if let Some(index) = self.index(x, y) {
+            let cell = &mut self.cells[index];
+            if !cell.contains(&reference) {
+                cell.push(reference);
+            }
+        }
+    }
+
+    pub(crate) fn insert(&mut self, cells: GridCells, reference: ObjectReference) {
+        for (x, y) in cells.iter() {
+            self.insert_at(x, y, reference);
+        }
+    }
+
+    // only this reference, whatever else shares the cells stays
+    pub(crate) fn remove(&mut self, cells: GridCells, reference: ObjectReference) {
+        for (x, y) in cells.iter() {
+            if let Some(index) = self.index(x, y) {
+                self.cells[index].retain(|existing| *existing != reference);
+            }
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        for cell in &mut self.cells {
+            cell.clear();
+        }
+    }
+
+    // every reference with its cell, row by row
+    pub(crate) fn occupied(&self) -> impl Iterator<Item = (usize, usize, ObjectReference)> + '_ {
+        self.cells.iter().enumerate().flat_map(move |(index, cell)| {
+            cell.iter().map(move |reference| (index % self.width, index / self.width, *reference))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILE: f32 = MARIO_SPRITE_BLOCK_SIZE as f32;
+
+    #[test]
+    fn the_first_and_last_rows_and_columns_are_in_the_grid() {
+        let grid = Grid::new(20, 14);
+        for (x, y) in [(0, 0), (19, 0), (0, 13), (19, 13), (19, 5), (7, 13)] {
+            assert_eq!(grid.grid_index(Vec2::new(x as f32 * TILE, y as f32 * TILE)), Some((x, y)));
+            assert!(grid.get(x, y).is_some());
+        }
+        // rounded to the nearest cell, up to half a tile off still counts
+        assert_eq!(grid.grid_index(Vec2::new(-7.0, 13.0 * TILE + 7.0)), Some((0, 13)));
+        assert_eq!(grid.grid_index(Vec2::new(19.0 * TILE + 7.0, -7.0)), Some((19, 0)));
+    }
+
+    #[test]
+    fn past_the_edges_is_outside_the_grid() {
+        let grid = Grid::new(20, 14);
+        let outside = [
+            Vec2::new(20.0 * TILE, 0.0),
+            Vec2::new(0.0, 14.0 * TILE),
+            Vec2::new(-9.0, 0.0),
+            Vec2::new(0.0, -9.0),
+            Vec2::new(19.0 * TILE + 9.0, 0.0),
+        ];
+        for pos in outside {
+            assert_eq!(grid.grid_index(pos), None, "{}", pos);
+        }
+        assert!(grid.get(20, 0).is_none());
+        assert!(grid.get(0, 14).is_none());
+    }
+
+    #[test]
+    fn references_in_the_corner_cells_can_be_added_found_and_removed() {
+        let mut grid = Grid::new(20, 14);
+        let corners = [(0, 0), (19, 0), (0, 13), (19, 13)];
+        for (index, (x, y)) in corners.into_iter().enumerate() {
+            grid.insert_at(x, y, ObjectReference::Block(index));
+        }
+        for (index, (x, y)) in corners.into_iter().enumerate() {
+            assert_eq!(grid.get(x, y).unwrap(), [ObjectReference::Block(index)]);
+        }
+        let occupied: Vec<(usize, usize)> = grid.occupied().map(|(x, y, _)| (x, y)).collect();
+        assert_eq!(occupied, corners);
+        // an object in the last column spills over the edge, only the part inside is kept
+        let cells = GridCells { x: 19, y: 12, wide: 2, high: 2 };
+        grid.insert(cells, ObjectReference::Player(0));
+        assert_eq!(grid.get(19, 12).unwrap(), [ObjectReference::Player(0)]);
+        assert_eq!(grid.get(19, 13).unwrap(), [ObjectReference::Block(3), ObjectReference::Player(0)]);
+        grid.remove(cells, ObjectReference::Player(0));
+        for (index, (x, y)) in corners.into_iter().enumerate() {
+            grid.remove(GridCells { x, y, wide: 1, high: 1 }, ObjectReference::Block(index));
+        }
+        assert_eq!(grid.occupied().count(), 0);
+    }
+
+    #[test]
+    fn the_ring_around_an_edge_cell_stays_in_the_grid_on_the_near_side() {
+        let ring = GridCells { x: 0, y: 0, wide: 1, high: 1 }.with_ring();
+        assert_eq!(ring, GridCells { x: 0, y: 0, wide: 2, high: 2 });
+        let ring = GridCells { x: 19, y: 13, wide: 1, high: 1 }.with_ring();
+        assert_eq!(ring, GridCells { x: 18, y: 12, wide: 3, high: 3 });
+    }
+}
@@ -16,10 +16,25 @@ pub fn convert_white_to_transparent(image: &mut Image) {
     }
 }
 
+// every pixel of a color in the first of a pair becomes the second, alpha is kept
+pub fn swap_colors(image: &mut Image, swaps: &[([u8; 3], [u8; 3])]) {
+    for pixel in image.get_image_data_mut().iter_mut() {
+        if let Some((_, to)) = swaps.iter().find(|(from, _)| pixel[..3] == from[..]) {
+            pixel[..3].copy_from_slice(to);
+        }
+    }
+}
+
 pub fn load_and_convert_texture(data: &[u8], format: ImageFormat) -> Texture2D {
+    load_recolored_texture(data, format, &[])
+}
+
+// the same sprite in another palette
+pub fn load_recolored_texture(data: &[u8], format: ImageFormat, swaps: &[([u8; 3], [u8; 3])]) -> Texture2D {
     let texture = Texture2D::from_file_with_format(data, Some(format));
     let mut texture_data = texture.get_texture_data();
     convert_white_to_transparent(&mut texture_data);
+    swap_colors(&mut texture_data, swaps);
     texture.update(&texture_data);
     texture
 }
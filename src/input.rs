@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, Read, Write};
+
+use log::warn;
+use macroquad::color::Color;
+use macroquad::input::{is_key_down, touches, KeyCode, TouchPhase};
+use macroquad::math::{Rect, Vec2};
+use macroquad::shapes::draw_rectangle;
+use macroquad::text::draw_text;
+use macroquad::window::{screen_height, screen_width};
+use serde::{Deserialize, Serialize};
+
+pub const INPUT_BINDINGS_PATH: &str = "config/input_bindings.json";
+pub const SECOND_PLAYER_BINDINGS_PATH: &str = "config/input_bindings_p2.json"; // player two's in co-op, edited by hand
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Deserialize, Serialize)]
+pub enum Action {
+    Left,
+    Right,
+    Jump,
+    Run,
+    Down,
+}
+
+impl Action {
+    pub const ALL: [Action; 5] = [Action::Left, Action::Right, Action::Jump, Action::Run, Action::Down];
+}
+
+// names used in the bindings file, spelled like the KeyCode variants
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("Space", KeyCode::Space), ("Apostrophe", KeyCode::Apostrophe), ("Comma", KeyCode::Comma),
+    ("Minus", KeyCode::Minus), ("Period", KeyCode::Period), ("Slash", KeyCode::Slash),
+    ("Key0", KeyCode::Key0), ("Key1", KeyCode::Key1), ("Key2", KeyCode::Key2), ("Key3", KeyCode::Key3),
+    ("Key4", KeyCode::Key4), ("Key5", KeyCode::Key5), ("Key6", KeyCode::Key6), ("Key7", KeyCode::Key7),
+    ("Key8", KeyCode::Key8), ("Key9", KeyCode::Key9), ("Semicolon", KeyCode::Semicolon),
+    ("Equal", KeyCode::Equal), ("A", KeyCode::A), ("B", KeyCode::B), ("C", KeyCode::C), ("D", KeyCode::D),
+    ("E", KeyCode::E), ("F", KeyCode::F), ("G", KeyCode::G), ("H", KeyCode::H), ("I", KeyCode::I),
+    ("J", KeyCode::J), ("K", KeyCode::K), ("L", KeyCode::L), ("M", KeyCode::M), ("N", KeyCode::N),
+    ("O", KeyCode::O), ("P", KeyCode::P), ("Q", KeyCode::Q), ("R", KeyCode::R), ("S", KeyCode::S),
+    ("T", KeyCode::T), ("U", KeyCode::U), ("V", KeyCode::V), ("W", KeyCode::W), ("X", KeyCode::X),
+    ("Y", KeyCode::Y), ("Z", KeyCode::Z), ("LeftBracket", KeyCode::LeftBracket),
+    ("Backslash", KeyCode::Backslash), ("RightBracket", KeyCode::RightBracket),
+    ("GraveAccent", KeyCode::GraveAccent), ("Escape", KeyCode::Escape), ("Enter", KeyCode::Enter),
+    ("Tab", KeyCode::Tab), ("Backspace", KeyCode::Backspace), ("Insert", KeyCode::Insert),
+    ("Delete", KeyCode::Delete), ("Right", KeyCode::Right), ("Left", KeyCode::Left), ("Down", KeyCode::Down),
+    ("Up", KeyCode::Up), ("PageUp", KeyCode::PageUp), ("PageDown", KeyCode::PageDown),
+    ("Home", KeyCode::Home), ("End", KeyCode::End), ("CapsLock", KeyCode::CapsLock),
+    ("ScrollLock", KeyCode::ScrollLock), ("NumLock", KeyCode::NumLock),
+    ("PrintScreen", KeyCode::PrintScreen), ("Pause", KeyCode::Pause), ("F1", KeyCode::F1),
+    ("F2", KeyCode::F2), ("F3", KeyCode::F3), ("F4", KeyCode::F4), ("F5", KeyCode::F5), ("F6", KeyCode::F6),
+    ("F7", KeyCode::F7), ("F8", KeyCode::F8), ("F9", KeyCode::F9), ("F10", KeyCode::F10),
+    ("F11", KeyCode::F11), ("F12", KeyCode::F12), ("Kp0", KeyCode::Kp0), ("Kp1", KeyCode::Kp1),
+    ("Kp2", KeyCode::Kp2), ("Kp3", KeyCode::Kp3), ("Kp4", KeyCode::Kp4), ("Kp5", KeyCode::Kp5),
+    ("Kp6", KeyCode::Kp6), ("Kp7", KeyCode::Kp7), ("Kp8", KeyCode::Kp8), ("Kp9", KeyCode::Kp9),
+    ("KpDecimal", KeyCode::KpDecimal), ("KpDivide", KeyCode::KpDivide), ("KpMultiply", KeyCode::KpMultiply),
+    ("KpSubtract", KeyCode::KpSubtract), ("KpAdd", KeyCode::KpAdd), ("KpEnter", KeyCode::KpEnter),
+    ("KpEqual", KeyCode::KpEqual), ("LeftShift", KeyCode::LeftShift), ("LeftControl", KeyCode::LeftControl),
+    ("LeftAlt", KeyCode::LeftAlt), ("LeftSuper", KeyCode::LeftSuper), ("RightShift", KeyCode::RightShift),
+    ("RightControl", KeyCode::RightControl), ("RightAlt", KeyCode::RightAlt),
+    ("RightSuper", KeyCode::RightSuper), ("Menu", KeyCode::Menu),
+];
+
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    KEY_NAMES.iter().find(|(key_name, _)| *key_name == name).map(|(_, key)| *key)
+}
+
+pub fn key_name(key: KeyCode) -> &'static str {
+    KEY_NAMES
+        .iter()
+        .find(|(_, named_key)| *named_key == key)
+        .map_or("Unknown", |(name, _)| *name)
+}
+
+pub struct InputBindings {
+    bindings: HashMap<Action, Vec<KeyCode>>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        let mut bindings = HashMap::new();
+        bindings.insert(Action::Left, vec![KeyCode::Left, KeyCode::A]);
+        bindings.insert(Action::Right, vec![KeyCode::Right, KeyCode::D]);
+        bindings.insert(Action::Jump, vec![KeyCode::Space]);
+        bindings.insert(Action::Run, vec![KeyCode::LeftShift]);
+        bindings.insert(Action::Down, vec![KeyCode::Down, KeyCode::S]);
+        InputBindings { bindings }
+    }
+}
+
+impl InputBindings {
+    // player two's defaults, on the right of the keyboard away from player one's WASD
+    pub fn second_player() -> InputBindings {
+        let mut bindings = HashMap::new();
+        bindings.insert(Action::Left, vec![KeyCode::J]);
+        bindings.insert(Action::Right, vec![KeyCode::L]);
+        bindings.insert(Action::Jump, vec![KeyCode::I]);
+        bindings.insert(Action::Run, vec![KeyCode::U]);
+        bindings.insert(Action::Down, vec![KeyCode::K]);
+        InputBindings { bindings }
+    }
+
+    // actions missing from the file keep their default keys, a missing or broken file means all defaults
+    pub fn load(path: &str) -> InputBindings {
+        InputBindings::load_over(path, InputBindings::default())
+    }
+
+    pub fn load_second_player(path: &str) -> InputBindings {
+        InputBindings::load_over(path, InputBindings::second_player())
+    }
+
+    fn load_over(path: &str, defaults: InputBindings) -> InputBindings {
+        let mut input_bindings = defaults;
+        let mut bindings_string = String::new();
+        if File::open(path).and_then(|mut file| file.read_to_string(&mut bindings_string)).is_err() {
+            return input_bindings;
+        }
+        let configured: HashMap<Action, Vec<String>> = match serde_json::from_str(&bindings_string) {
+            Ok(configured) => configured,
+            Err(error) => {
+                warn!("Ignoring {}, failed to parse key bindings: {}", path, error);
+                return input_bindings;
+            }
+        };
+        for (action, key_names) in configured {
+            let keys = key_names
+                .iter()
+                .filter_map(|name| {
+                    let key = key_from_name(name);
+                    if key.is_none() {
+                        warn!("Unknown key {} bound to {:?} in {}", name, action, path);
+                    }
+                    key
+                })
+                .collect();
+            input_bindings.bindings.insert(action, keys);
+        }
+        input_bindings
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let named: BTreeMap<Action, Vec<&str>> = self
+            .bindings
+            .iter()
+            .map(|(action, keys)| (*action, keys.iter().map(|key| key_name(*key)).collect()))
+            .collect();
+        let json = serde_json::to_string_pretty(&named).map_err(io::Error::other)?;
+        File::create(path)?.write_all(json.as_bytes())
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.bindings.get(&action).map_or(&[], |keys| keys.as_slice())
+    }
+
+    pub fn rebind(&mut self, action: Action, key: KeyCode) {
+        self.bindings.insert(action, vec![key]);
+    }
+
+    // another action already using the key, rebinding still goes through but the menu warns about it
+    pub fn conflict(&self, action: Action, key: KeyCode) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .find(|other| *other != action && self.keys(*other).contains(&key))
+    }
+
+    pub fn is_down(&self, action: Action) -> bool {
+        self.bindings
+            .get(&action)
+            .is_some_and(|keys| keys.iter().any(|key| is_key_down(*key)))
+    }
+}
+
+// what the player wants to do this physics frame, polled once so the simulation never reads the keyboard itself;
+// replays, bots and tests build these by hand
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InputState {
+    pub left: bool,
+    pub right: bool,
+    pub jump: bool,
+    pub jump_pressed: bool, // jump went down this physics frame
+    pub run: bool,
+    pub run_pressed: bool, // run went down this physics frame, throws a fireball with fire
+    pub down: bool,
+}
+
+impl InputState {
+    // edges are taken against the previous physics frame, not the render frame, so none get lost or doubled
+    pub fn from_keyboard(bindings: &InputBindings, previous: &InputState) -> InputState {
+        let mut input = InputState {
+            left: bindings.is_down(Action::Left),
+            right: bindings.is_down(Action::Right),
+            jump: bindings.is_down(Action::Jump),
+            jump_pressed: false,
+            run: bindings.is_down(Action::Run),
+            run_pressed: false,
+            down: bindings.is_down(Action::Down),
+        };
+        input.update_edges(previous);
+        input
+    }
+    pub fn update_edges(&mut self, previous: &InputState) {
+        self.jump_pressed = self.jump && !previous.jump;
+        self.run_pressed = self.run && !previous.run;
+    }
+}
+
+const TOUCH_BUTTON_SIZE: f32 = 72.0;
+const TOUCH_BUTTON_MARGIN: f32 = 24.0;
+const TOUCH_HITBOX_PADDING: f32 = 20.0; // fingers are imprecise, a touch slightly outside the drawn button still counts
+
+// on-screen buttons for phones, hidden until the first touch unless enabled up front
+pub struct TouchControls {
+    enabled: bool,
+}
+
+impl TouchControls {
+    pub fn new(enabled: bool) -> TouchControls {
+        TouchControls { enabled }
+    }
+
+    // screen space, left/right bottom left, run/jump bottom right; recomputed so resizes and rotation just work
+    fn buttons() -> [(Action, &'static str, Rect); 4] {
+        let y = screen_height() - TOUCH_BUTTON_SIZE - TOUCH_BUTTON_MARGIN;
+        let right_edge = screen_width() - TOUCH_BUTTON_MARGIN;
+        [
+            (Action::Left, "<", Rect::new(TOUCH_BUTTON_MARGIN, y, TOUCH_BUTTON_SIZE, TOUCH_BUTTON_SIZE)),
+            (Action::Right, ">", Rect::new(TOUCH_BUTTON_MARGIN * 2.0 + TOUCH_BUTTON_SIZE, y, TOUCH_BUTTON_SIZE, TOUCH_BUTTON_SIZE)),
+            (Action::Run, "B", Rect::new(right_edge - TOUCH_BUTTON_SIZE * 2.0 - TOUCH_BUTTON_MARGIN, y, TOUCH_BUTTON_SIZE, TOUCH_BUTTON_SIZE)),
+            (Action::Jump, "A", Rect::new(right_edge - TOUCH_BUTTON_SIZE, y, TOUCH_BUTTON_SIZE, TOUCH_BUTTON_SIZE)),
+        ]
+    }
+
+    // every active touch presses whatever button it is on, so moving and jumping with two thumbs works
+    pub fn apply(&mut self, input: &mut InputState, previous: &InputState) {
+        let active_touches: Vec<Vec2> = touches()
+            .into_iter()
+            .filter(|touch| !matches!(touch.phase, TouchPhase::Ended | TouchPhase::Cancelled))
+            .map(|touch| touch.position)
+            .collect();
+        if active_touches.is_empty() {
+            return;
+        }
+        self.enabled = true;
+        for (action, _, rect) in Self::buttons() {
+            let hitbox = Rect::new(
+                rect.x - TOUCH_HITBOX_PADDING,
+                rect.y - TOUCH_HITBOX_PADDING,
+                rect.w + TOUCH_HITBOX_PADDING * 2.0,
+                rect.h + TOUCH_HITBOX_PADDING * 2.0,
+            );
+            if !active_touches.iter().any(|position| hitbox.contains(*position)) {
+                continue;
+            }
+            match action {
+                Action::Left => input.left = true,
+                Action::Right => input.right = true,
+                Action::Jump => input.jump = true,
+                Action::Run => input.run = true,
+                Action::Down => input.down = true,
+            }
+        }
+        input.update_edges(previous);
+    }
+
+    pub fn draw(&self) {
+        if !self.enabled {
+            return;
+        }
+        for (_, label, rect) in Self::buttons() {
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, Color::new(1.0, 1.0, 1.0, 0.25));
+            draw_text(label, rect.x + rect.w / 2.0 - 8.0, rect.y + rect.h / 2.0 + 10.0, 32.0, Color::new(1.0, 1.0, 1.0, 0.6));
+        }
+    }
+}
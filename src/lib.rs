@@ -0,0 +1,25 @@
+pub mod allocations;
+pub mod animation;
+pub mod assets;
+pub mod audio;
+#[doc(hidden)] // only for benches/world_update.rs
+pub mod bench;
+pub mod camera;
+pub mod collision;
+pub mod config;
+pub mod entities;
+pub mod game;
+pub mod grid;
+pub mod image_utils;
+pub mod input;
+pub mod logging;
+pub mod menu;
+pub mod perf_overlay;
+pub mod physics;
+pub mod preparation;
+pub mod replay;
+pub mod rewind;
+pub mod score;
+#[cfg(feature = "tas")]
+pub mod tas;
+pub mod world;
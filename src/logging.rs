@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use log::{Level, Log, Metadata, Record};
+
+const KEPT_WARNINGS: usize = 5;
+
+static WARNINGS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+// env_logger for the terminal, or the browser's console, keeping the last warnings and errors around for the performance overlay
+struct Logger {
+    #[cfg(not(target_arch = "wasm32"))]
+    terminal: env_logger::Logger,
+}
+
+impl Logger {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write(&self, record: &Record) {
+        self.terminal.log(record);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn write(&self, record: &Record) {
+        use macroquad::logging;
+        match record.level() {
+            Level::Error => logging::error!("{}", record.args()),
+            Level::Warn => logging::warn!("{}", record.args()),
+            Level::Info => logging::info!("{}", record.args()),
+            Level::Debug | Level::Trace => logging::debug!("{}", record.args()),
+        }
+    }
+}
+
+impl Log for Logger {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.terminal.enabled(metadata)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        self.write(record);
+        if record.level() <= Level::Warn {
+            let mut warnings = WARNINGS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if warnings.len() == KEPT_WARNINGS {
+                warnings.pop_front();
+            }
+            warnings.push_back(format!("{}: {}", record.level(), record.args()));
+        }
+    }
+
+    fn flush(&self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.terminal.flush();
+    }
+}
+
+// RUST_LOG picks what is shown (e.g. RUST_LOG=debug or RUST_LOG=rustario_2d::world=trace), info and up by default.
+// a second call keeps the first logger
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init() {
+    let terminal = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).build();
+    let max_level = terminal.filter();
+    if log::set_boxed_logger(Box::new(Logger { terminal })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+// there is no RUST_LOG in the browser, always info and up
+#[cfg(target_arch = "wasm32")]
+pub fn init() {
+    if log::set_boxed_logger(Box::new(Logger {})).is_ok() {
+        log::set_max_level(log::LevelFilter::Info);
+    }
+}
+
+// oldest first
+pub fn recent_warnings() -> Vec<String> {
+    let warnings = WARNINGS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    warnings.iter().cloned().collect()
+}
@@ -0,0 +1,213 @@
+use macroquad::color::{Color, WHITE, YELLOW};
+use macroquad::input::{get_last_key_pressed, is_key_pressed, KeyCode};
+use macroquad::shapes::draw_rectangle;
+use macroquad::text::draw_text;
+use macroquad::window::{screen_height, screen_width};
+
+use crate::audio::{AudioManager, SfxId, VOLUME_STEP};
+use crate::input::{key_name, Action, InputBindings};
+
+#[derive(PartialEq)]
+enum MenuPage {
+    Main,
+    Controls,
+    Audio,
+}
+
+const MAIN_ENTRIES: [&str; 3] = ["Resume", "Controls", "Audio"];
+// the actions come first on the controls page, then these
+const CONTROLS_EXTRA_ENTRIES: [&str; 2] = ["Reset to defaults", "Back"];
+const AUDIO_ENTRY_COUNT: usize = 3; // music volume, sound volume, back
+
+// Escape opens it, arrows + Enter navigate; the menu keys are fixed so a bad binding can always be undone
+pub struct PauseMenu {
+    open: bool,
+    page: MenuPage,
+    selected: usize,
+    waiting_for_key: Option<Action>,
+    message: Option<String>,
+    bindings_path: String,
+    audio_settings_path: String,
+}
+
+impl PauseMenu {
+    pub fn new(bindings_path: &str, audio_settings_path: &str) -> PauseMenu {
+        PauseMenu {
+            open: false,
+            page: MenuPage::Main,
+            selected: 0,
+            waiting_for_key: None,
+            message: None,
+            bindings_path: bindings_path.to_owned(),
+            audio_settings_path: audio_settings_path.to_owned(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    // every key goes to the binding being changed, hotkeys like mute have to wait
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.waiting_for_key.is_some()
+    }
+
+    fn entry_count(&self) -> usize {
+        match self.page {
+            MenuPage::Main => MAIN_ENTRIES.len(),
+            MenuPage::Controls => Action::ALL.len() + CONTROLS_EXTRA_ENTRIES.len(),
+            MenuPage::Audio => AUDIO_ENTRY_COUNT,
+        }
+    }
+
+    fn show_page(&mut self, page: MenuPage) {
+        self.page = page;
+        self.selected = 0;
+        self.message = None;
+    }
+
+    fn save_bindings(&mut self, bindings: &InputBindings) {
+        if let Err(error) = bindings.save(&self.bindings_path) {
+            self.message = Some(format!("Could not save {}: {}", self.bindings_path, error));
+        }
+    }
+
+    // left/right on a volume entry, in 10% steps
+    fn adjust_volume(&mut self, audio: &mut AudioManager, direction: f32) {
+        let settings = audio.settings();
+        match self.selected {
+            0 => audio.set_music_volume(settings.music_volume + direction * VOLUME_STEP),
+            1 => {
+                audio.set_sfx_volume(settings.sfx_volume + direction * VOLUME_STEP);
+                // the music is already there to hear, effects need a sample
+                audio.play_sfx(SfxId::Jump);
+            }
+            _ => return,
+        }
+        if let Err(error) = audio.settings().save(&self.audio_settings_path) {
+            self.message = Some(format!("Could not save {}: {}", self.audio_settings_path, error));
+        }
+    }
+
+    // once per render frame, also while closed so Escape can open it
+    pub fn update(&mut self, bindings: &mut InputBindings, audio: &mut AudioManager) {
+        if let Some(action) = self.waiting_for_key {
+            if is_key_pressed(KeyCode::Escape) {
+                self.waiting_for_key = None;
+                self.message = None;
+                return;
+            }
+            if let Some(key) = get_last_key_pressed() {
+                self.waiting_for_key = None;
+                self.message = bindings
+                    .conflict(action, key)
+                    .map(|other| format!("{} is also bound to {:?}", key_name(key), other));
+                bindings.rebind(action, key);
+                self.save_bindings(bindings);
+            }
+            return;
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            if !self.open {
+                self.open = true;
+                self.show_page(MenuPage::Main);
+            } else if self.page != MenuPage::Main {
+                self.show_page(MenuPage::Main);
+            } else {
+                self.open = false;
+            }
+            return;
+        }
+        if !self.open {
+            return;
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.selected = (self.selected + 1) % self.entry_count();
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.selected = (self.selected + self.entry_count() - 1) % self.entry_count();
+        }
+        if self.page == MenuPage::Audio {
+            if is_key_pressed(KeyCode::Left) {
+                self.adjust_volume(audio, -1.0);
+            }
+            if is_key_pressed(KeyCode::Right) {
+                self.adjust_volume(audio, 1.0);
+            }
+        }
+        if !is_key_pressed(KeyCode::Enter) {
+            return;
+        }
+        match self.page {
+            MenuPage::Main => match self.selected {
+                0 => self.open = false,
+                1 => self.show_page(MenuPage::Controls),
+                _ => self.show_page(MenuPage::Audio),
+            },
+            MenuPage::Controls => {
+                if let Some(action) = Action::ALL.get(self.selected) {
+                    self.waiting_for_key = Some(*action);
+                    self.message = None;
+                } else if self.selected == Action::ALL.len() {
+                    *bindings = InputBindings::default();
+                    self.message = Some("Restored default controls".to_owned());
+                    self.save_bindings(bindings);
+                } else {
+                    self.show_page(MenuPage::Main);
+                }
+            }
+            MenuPage::Audio => {
+                if self.selected == AUDIO_ENTRY_COUNT - 1 {
+                    self.show_page(MenuPage::Main);
+                }
+            }
+        }
+    }
+
+    pub fn draw(&self, bindings: &InputBindings, audio: &AudioManager) {
+        if !self.open && self.waiting_for_key.is_none() {
+            return;
+        }
+        draw_rectangle(0.0, 0.0, screen_width(), screen_height(), Color::new(0.0, 0.0, 0.0, 0.6));
+        let x = screen_width() / 2.0 - 150.0;
+        let mut y = 80.0;
+        let title = match self.page {
+            MenuPage::Main => "Paused",
+            MenuPage::Controls => "Controls",
+            MenuPage::Audio => "Audio",
+        };
+        draw_text(title, x, y, 40.0, WHITE);
+        y += 50.0;
+
+        let entries: Vec<String> = match self.page {
+            MenuPage::Main => MAIN_ENTRIES.iter().map(|entry| entry.to_string()).collect(),
+            MenuPage::Controls => Action::ALL
+                .iter()
+                .map(|action| {
+                    if self.waiting_for_key == Some(*action) {
+                        return format!("{:?}: press a key (Escape cancels)", action);
+                    }
+                    let keys: Vec<&str> = bindings.keys(*action).iter().map(|key| key_name(*key)).collect();
+                    format!("{:?}: {}", action, keys.join(", "))
+                })
+                .chain(CONTROLS_EXTRA_ENTRIES.iter().map(|entry| entry.to_string()))
+                .collect(),
+            MenuPage::Audio => {
+                let settings = audio.settings();
+                vec![
+                    format!("Music volume: < {:.0}% >", settings.music_volume * 100.0),
+                    format!("Sound volume: < {:.0}% >", settings.sfx_volume * 100.0),
+                    "Back".to_owned(),
+                ]
+            }
+        };
+        for (index, entry) in entries.iter().enumerate() {
+            let color = if index == self.selected { YELLOW } else { WHITE };
+            draw_text(entry, x, y, 28.0, color);
+            y += 32.0;
+        }
+        if let Some(message) = &self.message {
+            draw_text(message, x, y + 20.0, 24.0, YELLOW);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use macroquad::color::{Color, WHITE};
+use macroquad::input::{is_key_pressed, KeyCode};
+use macroquad::miniquad::date;
+use macroquad::shapes::draw_rectangle;
+use macroquad::text::draw_text;
+use macroquad::time::get_fps;
+use macroquad::window::screen_width;
+
+use crate::allocations::allocations;
+use crate::logging::recent_warnings;
+use crate::world::{EntityCounts, FrameStats};
+
+const SMOOTHED_FRAMES: usize = 30;
+
+// std's Instant panics in the browser, miniquad's clock works everywhere and without a window
+#[derive(Clone, Copy)]
+pub struct Stopwatch(f64);
+
+impl Stopwatch {
+    pub fn start() -> Stopwatch {
+        Stopwatch(date::now())
+    }
+
+    // the clock is the wall clock on desktop, it can go backwards
+    pub fn elapsed(self) -> Duration {
+        Duration::from_secs_f64((date::now() - self.0).max(0.0))
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct FrameSample {
+    stats: FrameStats,
+    draw_time: Duration,
+    allocations: u64,
+}
+
+// the FPS, and with F3 what the frames spend their time on, averaged over the last SMOOTHED_FRAMES frames,
+// and the last warnings logged
+pub struct PerfOverlay {
+    visible: bool,
+    samples: VecDeque<FrameSample>,
+    allocations_before: u64,
+}
+
+impl PerfOverlay {
+    pub fn new(visible: bool) -> PerfOverlay {
+        PerfOverlay {
+            visible,
+            samples: VecDeque::with_capacity(SMOOTHED_FRAMES),
+            allocations_before: allocations(),
+        }
+    }
+
+    pub fn poll(&mut self) {
+        if is_key_pressed(KeyCode::F3) {
+            self.visible = !self.visible;
+        }
+    }
+
+    // once per render frame, after drawing the world
+    pub fn record(&mut self, stats: FrameStats, draw_time: Duration) {
+        let allocations_now = allocations();
+        if self.samples.len() == SMOOTHED_FRAMES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(FrameSample {
+            stats,
+            draw_time,
+            allocations: allocations_now - self.allocations_before,
+        });
+        self.allocations_before = allocations_now;
+    }
+
+    fn average(&self, value: impl Fn(&FrameSample) -> f64) -> f64 {
+        self.samples.iter().map(value).sum::<f64>() / self.samples.len().max(1) as f64
+    }
+
+    pub fn draw(&self, counts: EntityCounts) {
+        draw_text(&format!("FPS: {}", get_fps()), 10.0, 10.0, 20.0, WHITE);
+        if !self.visible {
+            return;
+        }
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        // the counter only moves with the CountingAllocator installed as the global allocator
+        let allocations = if allocations() == 0 {
+            "not counted".to_owned()
+        } else {
+            format!("{:.1}", self.average(|sample| sample.allocations as f64))
+        };
+        let mut lines = vec![
+            format!("steps/frame: {:.2}", self.average(|sample| sample.stats.steps as f64)),
+            format!("update: {:.3} ms", self.average(|sample| millis(sample.stats.update_time))),
+            format!("draw: {:.3} ms", self.average(|sample| millis(sample.draw_time))),
+            format!("collision pairs: {:.1}", self.average(|sample| sample.stats.collision_pairs as f64)),
+            format!("allocations/frame: {}", allocations),
+            format!("enemies: {} powerups: {} spawning: {}", counts.enemies, counts.powerups, counts.spawning),
+            format!("blocks: {} coins: {}", counts.blocks, counts.coins),
+        ];
+        lines.extend(recent_warnings());
+        let top = 70.0;
+        draw_rectangle(0.0, top, screen_width(), lines.len() as f32 * 20.0 + 8.0, Color::new(0.0, 0.0, 0.0, 0.5));
+        for (index, line) in lines.iter().enumerate() {
+            draw_text(line, 10.0, top + 18.0 + index as f32 * 20.0, 20.0, WHITE);
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::fs;
+use std::io;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{ACCELERATION, AIR_FRICTION, FAST_FALL_GRAVITY_FACTOR, GRAVITY, GROUND_FRICTION, JUMP_STRENGTH, MAX_FALL_VELOCITY, MAX_RUN_VELOCITY_X, MAX_WALK_VELOCITY_X, PHYSICS_FRAME_TIME, RISING_GRAVITY_FACTOR, RUN_ACCELERATION};
+
+pub const PHYSICS_CONFIG_PATH: &str = "config/physics.toml";
+
+// what the movement feels like, tunable without a recompile. the constants in config are the defaults,
+// a key missing from the file keeps its default
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PhysicsConfig {
+    pub frame_time: f32, // seconds per physics step
+    pub gravity: f32,
+    pub fast_fall_gravity_factor: f32, // holding down while airborne
+    pub rising_gravity_factor: f32, // a jump rising with the key held
+    pub max_fall_speed: f32, // pixels per physics step, nothing falls faster
+    pub jump_strength: f32, // upwards speed added every step jump is held on the way up
+    pub acceleration: f32,
+    pub run_acceleration: f32, // while holding run
+    pub ground_friction: f32,
+    pub air_friction: f32,
+    pub max_speed: f32, // while holding run
+    pub max_walk_speed: f32,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        PhysicsConfig {
+            frame_time: PHYSICS_FRAME_TIME,
+            gravity: GRAVITY as f32,
+            fast_fall_gravity_factor: FAST_FALL_GRAVITY_FACTOR,
+            rising_gravity_factor: RISING_GRAVITY_FACTOR,
+            max_fall_speed: MAX_FALL_VELOCITY,
+            jump_strength: JUMP_STRENGTH,
+            acceleration: ACCELERATION,
+            run_acceleration: RUN_ACCELERATION,
+            ground_friction: GROUND_FRICTION,
+            air_friction: AIR_FRICTION,
+            max_speed: MAX_RUN_VELOCITY_X,
+            max_walk_speed: MAX_WALK_VELOCITY_X,
+        }
+    }
+}
+
+impl PhysicsConfig {
+    // at startup: a missing file means the defaults, a broken one too after a warning
+    pub fn load(path: &str) -> PhysicsConfig {
+        match PhysicsConfig::read(path) {
+            Ok(physics) => physics,
+            Err(error) => {
+                if !matches!(&error, PhysicsError::Io(error) if error.kind() == io::ErrorKind::NotFound) {
+                    warn!("Ignoring {}, {}", path, error);
+                }
+                PhysicsConfig::default()
+            }
+        }
+    }
+
+    // for reloading while playing, the caller keeps the config it has when this fails
+    pub fn read(path: &str) -> Result<PhysicsConfig, PhysicsError> {
+        let physics: PhysicsConfig = toml::from_str(&fs::read_to_string(path).map_err(PhysicsError::Io)?)
+            .map_err(|error| PhysicsError::Parse(error.message().to_owned()))?; // the full error spans lines, too long for the screen
+        physics.validate()?;
+        Ok(physics)
+    }
+
+    // values the game can't run with, the first one found
+    pub fn validate(&self) -> Result<(), PhysicsError> {
+        let values = [
+            ("frame_time", self.frame_time),
+            ("gravity", self.gravity),
+            ("fast_fall_gravity_factor", self.fast_fall_gravity_factor),
+            ("rising_gravity_factor", self.rising_gravity_factor),
+            ("max_fall_speed", self.max_fall_speed),
+            ("jump_strength", self.jump_strength),
+            ("acceleration", self.acceleration),
+            ("run_acceleration", self.run_acceleration),
+            ("ground_friction", self.ground_friction),
+            ("air_friction", self.air_friction),
+            ("max_speed", self.max_speed),
+            ("max_walk_speed", self.max_walk_speed),
+        ];
+        for (name, value) in values {
+            if !value.is_finite() {
+                return Err(PhysicsError::Invalid(format!("{} is {}", name, value)));
+            }
+        }
+        // no time passing, or no moving at all
+        for (name, value) in [("frame_time", self.frame_time), ("max_speed", self.max_speed), ("max_walk_speed", self.max_walk_speed), ("max_fall_speed", self.max_fall_speed)] {
+            if value <= 0.0 {
+                return Err(PhysicsError::Invalid(format!("{} has to be above 0, not {}", name, value)));
+            }
+        }
+        // negative friction speeds things up forever
+        for (name, value) in [("ground_friction", self.ground_friction), ("air_friction", self.air_friction)] {
+            if value < 0.0 {
+                return Err(PhysicsError::Invalid(format!("{} can't be negative, not {}", name, value)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum PhysicsError {
+    Io(io::Error),
+    Parse(String),
+    Invalid(String), // parsed, but not something the game can run with
+}
+
+impl std::fmt::Display for PhysicsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PhysicsError::Io(error) => write!(f, "failed to read physics: {}", error),
+            PhysicsError::Parse(message) => write!(f, "failed to parse physics: {}", message),
+            PhysicsError::Invalid(message) => write!(f, "invalid physics: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for PhysicsError {}
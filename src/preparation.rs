@@ -1,30 +1,346 @@
 use image::{GenericImageView, ImageBuffer, Rgba};
-use serde::{Deserialize, Serialize};
+use log::info;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{create_dir_all, File};
-use std::io::Write;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 
-use crate::mario_config::mario_config::MARIO_SPRITE_BLOCK_SIZE;
+use crate::config::{LEVEL_TIME, MARIO_SPRITE_BLOCK_SIZE};
+
+pub const TILE_TYPES_PATH: &str = "leveldata/tile_types.json";
+pub const TILESHEET_PATH: &str = "sprites/tilesheet.png";
+pub const LEVEL_DATA_PATH: &str = "leveldata/level_data.json";
+// bump when the generated files change shape, so outputs from an older version get regenerated
+const PREPARATION_VERSION: u32 = 5;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum TileType {
+    Solid,
+    PowerupBlock,
+    Decorative, // baked into the background, no collision
+}
+
+// the types level1.png was authored with, ids new to tile_types.json start out from here
+pub fn default_tile_type(sprite_id: u8) -> TileType {
+    match sprite_id {
+        9 => TileType::PowerupBlock,
+        10..=17 | 19..=21 | 25 | 31 => TileType::Solid,
+        _ => TileType::Decorative,
+    }
+}
+
+pub fn load_tile_types(path: impl AsRef<Path>) -> io::Result<BTreeMap<u8, TileType>> {
+    let path = path.as_ref();
+    let mut tile_types_string = String::new();
+    File::open(path)?.read_to_string(&mut tile_types_string)?;
+    serde_json::from_str(&tile_types_string)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), error)))
+}
 
 pub struct Tile {
     pub sprite_id: u8,
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum EntityKind {
+    Goomba,
+    Koopa,
+    PiranhaPlant, // placed on the left tile above a pipe
+    Launcher, // a Bullet Bill launcher, solid like a block
+    Lakitu, // placed near the top, where it waits for the camera
+    Star, // bounces off along the level from where it is placed
+    Coin,
+    PlayerStart,
+    Flagpole,
+}
+
+// a 16x16 tile filled with exactly one of these colors is a spawn marker, not a tile
+const MARKER_COLORS: [([u8; 4], EntityKind); 9] = [
+    ([255, 0, 0, 255], EntityKind::Goomba),
+    ([0, 255, 255, 255], EntityKind::Koopa),
+    ([255, 0, 255, 255], EntityKind::PiranhaPlant),
+    ([255, 128, 0, 255], EntityKind::Launcher),
+    ([128, 0, 255, 255], EntityKind::Lakitu),
+    ([255, 255, 128, 255], EntityKind::Star),
+    ([255, 255, 0, 255], EntityKind::Coin),
+    ([0, 0, 255, 255], EntityKind::PlayerStart),
+    ([0, 255, 0, 255], EntityKind::Flagpole),
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct EntitySpawn {
+    pub kind: EntityKind,
+    pub x: usize, // pixels, top left of the marker tile
+    pub y: usize,
+}
+
+// written by hand into the level file, preparation keeps it when regenerating the tiles.
+// every field is optional, a file without it plays like level 1 always has
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct LevelMeta {
+    pub name: String, // shown in the HUD, nothing when empty
+    pub theme: String, // palette id for themed tilesets, only "overworld" exists so far
+    pub music: String, // see MusicId::from_name
+    pub time_limit: u32, // what the clock starts at, in time units
+    pub backward_scroll: bool, // whether the camera follows the player back left
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goal_x: Option<usize>, // pixels, the player wins once past it. without it the flagpole, or a tile before the right end
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lakitu_leaves_x: Option<usize>, // pixels, a player past it sends the Lakitus away. without it a stretch before the goal
+}
+
+impl Default for LevelMeta {
+    fn default() -> Self {
+        LevelMeta {
+            name: String::new(),
+            theme: "overworld".to_owned(),
+            music: "overworld".to_owned(),
+            time_limit: LEVEL_TIME,
+            backward_scroll: false,
+            goal_x: None,
+            lakitu_leaves_x: None,
+        }
+    }
+}
+
+// a slice of the level's columns with its own camera bounds, e.g. a bonus room drawn next to the main area.
+// a level without rooms is one room as wide as the level
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Room {
+    pub name: String,
+    pub x: usize, // pixels, like width
+    pub width: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum WarpTrigger {
+    Down, // standing on it and pressing down, a pipe from above
+    Right, // walking into it, a pipe from the side
+}
+
+// moves the player into another room once it stands in the area and the trigger is held
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Warp {
+    pub x: usize, // pixels, the area the player has to overlap
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub trigger: WarpTrigger,
+    pub room: usize, // index into rooms
+    pub to_x: usize, // where the player comes out, pixels
+    pub to_y: usize,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct LevelData {
+    #[serde(default)]
+    pub meta: LevelMeta,
     pub height: usize,
+    #[serde(default)] // missing in files from before it was stored, see width()
+    pub width: usize,
+    #[serde(deserialize_with = "deserialize_tiles")]
     pub tiles: Vec<u8>,
+    // (tile index, sprite id) drawn in front of everything, empty cells are left out
+    #[serde(default)]
+    pub foreground: Vec<(usize, u8)>,
+    #[serde(default)]
+    pub entities: Vec<EntitySpawn>,
+    // written by hand like meta
+    #[serde(default)]
+    pub rooms: Vec<Room>,
+    #[serde(default)]
+    pub warps: Vec<Warp>,
+}
+
+// how the tiles are written, loading reads either
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum TileEncoding {
+    #[default]
+    Plain, // one number per tile
+    Rle, // {"rle": "count*sprite_id ..."} over the row major tiles, a fraction of the size
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredTiles {
+    Plain(Vec<u8>),
+    Rle { rle: String },
+}
+
+pub fn encode_rle(tiles: &[u8]) -> String {
+    let mut runs: Vec<(usize, u8)> = Vec::new();
+    for tile in tiles {
+        match runs.last_mut() {
+            Some((count, sprite_id)) if sprite_id == tile => *count += 1,
+            _ => runs.push((1, *tile)),
+        }
+    }
+    let runs: Vec<String> = runs.iter().map(|(count, sprite_id)| format!("{}*{}", count, sprite_id)).collect();
+    runs.join(" ")
+}
+
+pub fn decode_rle(rle: &str) -> Result<Vec<u8>, String> {
+    let mut tiles = Vec::new();
+    for run in rle.split_whitespace() {
+        let parsed = run
+            .split_once('*')
+            .and_then(|(count, sprite_id)| Some((count.parse::<usize>().ok()?, sprite_id.parse::<u8>().ok()?)));
+        let Some((count, sprite_id)) = parsed else {
+            return Err(format!("{:?} is not a count*sprite_id run", run));
+        };
+        tiles.extend(std::iter::repeat_n(sprite_id, count));
+    }
+    Ok(tiles)
+}
+
+fn deserialize_tiles<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    match StoredTiles::deserialize(deserializer)? {
+        StoredTiles::Plain(tiles) => Ok(tiles),
+        StoredTiles::Rle { rle } => decode_rle(&rle).map_err(serde::de::Error::custom),
+    }
+}
+
+// how far two tiles may differ and still share a sprite id, re-exported images often touch a few pixels
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Tolerance {
+    pub pixels: usize, // how many pixels may differ by more than channel_delta
+    pub channel_delta: u8, // pixels whose channels are all within this count as equal
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct PreparationOptions {
+    pub encoding: TileEncoding,
+    pub tolerance: Tolerance, // the default only merges identical tiles
+}
+
+// defaults are where the game looks for them
+pub struct PreparationPaths {
+    pub image: PathBuf,
+    pub foreground_image: Option<PathBuf>, // same size as image, transparent where nothing is in front
+    pub level_data: PathBuf,
+    pub tilesheet: PathBuf,
+    pub tile_types: PathBuf,
+    pub source_hash: PathBuf, // hash of the image the outputs were generated from
+}
+
+impl Default for PreparationPaths {
+    fn default() -> Self {
+        PreparationPaths {
+            image: PathBuf::from("level1.png"),
+            foreground_image: None,
+            level_data: PathBuf::from(LEVEL_DATA_PATH),
+            tilesheet: PathBuf::from(TILESHEET_PATH),
+            tile_types: PathBuf::from(TILE_TYPES_PATH),
+            source_hash: PathBuf::from("leveldata/source_hash"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub name: String, // the image's file stem, e.g. level2
+    pub level_data: PathBuf,
+}
+
+// level*.png in the directory with the number in their name, ordered so level2 comes before level10
+pub fn level_images(directory: &Path) -> io::Result<Vec<(u32, PathBuf)>> {
+    let mut images = Vec::new();
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "png") {
+            continue;
+        }
+        let number = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.strip_prefix("level"))
+            .and_then(|number| number.parse().ok());
+        if let Some(number) = number {
+            images.push((number, path));
+        }
+    }
+    images.sort();
+    Ok(images)
+}
+
+// every level image into level_data_N.json next to paths.level_data, one tilesheet and tile_types.json for all
+// of them so sprite ids mean the same in every level, and a levels.json manifest listing them
+pub fn prepare_directory(directory: &Path, paths: &PreparationPaths, options: PreparationOptions) -> io::Result<Vec<ManifestEntry>> {
+    let output_directory = paths.level_data.parent().unwrap_or(Path::new("")).to_path_buf();
+    let mut manifest = Vec::new();
+    for (number, image) in level_images(directory)? {
+        let level_paths = PreparationPaths {
+            foreground_image: foreground_image_for(&image),
+            image: image.clone(),
+            level_data: output_directory.join(format!("level_data_{}.json", number)),
+            tilesheet: paths.tilesheet.clone(),
+            tile_types: paths.tile_types.clone(),
+            source_hash: output_directory.join(format!("level_data_{}.hash", number)),
+        };
+        prepare(&level_paths, options);
+        manifest.push(ManifestEntry {
+            name: format!("level{}", number),
+            level_data: level_paths.level_data,
+        });
+    }
+    create_parent_dir(&paths.level_data);
+    let manifest_json = serde_json::to_string_pretty(&manifest).map_err(io::Error::other)?;
+    File::create(output_directory.join("levels.json"))?.write_all(manifest_json.as_bytes())?;
+    Ok(manifest)
+}
+
+fn create_parent_dir(path: &Path) {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        create_dir_all(parent).expect("Failed to create directory");
+    }
+}
+
+// returns false when the outputs were already up to date with the image
+// the foreground layer for an image, level1_fg.png next to level1.png
+pub fn foreground_image_for(image: &Path) -> Option<PathBuf> {
+    let stem = image.file_stem()?.to_str()?;
+    let foreground = image.with_file_name(format!("{}_fg.png", stem));
+    foreground.exists().then_some(foreground)
 }
 
-pub fn main() {
-    let img_path = "level1.png";
-    let img = image::open(img_path).expect("Failed to open image");
+pub fn prepare(paths: &PreparationPaths, options: PreparationOptions) -> bool {
+    let source = std::fs::read(&paths.image).expect("Failed to open image");
+    let foreground_source = paths
+        .foreground_image
+        .as_ref()
+        .map(|path| std::fs::read(path).expect("Failed to open foreground image"));
+    let mut hashed_bytes: Vec<u8> = source.iter().chain(foreground_source.iter().flatten()).copied().collect();
+    // other options rewrite the files too
+    hashed_bytes.push(options.encoding as u8);
+    hashed_bytes.extend((options.tolerance.pixels as u64).to_le_bytes());
+    hashed_bytes.push(options.tolerance.channel_delta);
+    let source_hash = format!("{:016x}", source_hash(&hashed_bytes));
+    let outputs_exist = [&paths.tilesheet, &paths.level_data, &paths.tile_types]
+        .iter()
+        .all(|path| path.exists());
+    if outputs_exist && std::fs::read_to_string(&paths.source_hash).is_ok_and(|hash| hash == source_hash) {
+        return false;
+    }
+    let img = image::load_from_memory(&source).expect("Failed to decode image");
 
     let (img_width, img_height) = img.dimensions();
 
-    let mut tiles_map = Vec::<ImageBuffer<Rgba<u8>, Vec<u8>>>::new();
-    let mut level_data = Vec::new();
+    // the existing tilesheet is the id mapping, its tiles keep their ids and new ones are appended
+    let mut tiles_map = TileSet::new(options.tolerance);
+    if let Ok(existing_tilesheet) = image::open(&paths.tilesheet) {
+        for y in (0..existing_tilesheet.height()).step_by(MARIO_SPRITE_BLOCK_SIZE) {
+            let size = MARIO_SPRITE_BLOCK_SIZE as u32;
+            tiles_map.push(existing_tilesheet.view(0, y, size, size).to_image());
+        }
+    }
+    let mut level_data: Vec<Tile> = Vec::new();
+    let mut entities = Vec::new();
 
-    create_dir_all("leveldata").expect("Failed to create directory");
+    for path in [&paths.level_data, &paths.tilesheet, &paths.tile_types, &paths.source_hash] {
+        create_parent_dir(path);
+    }
     
     for y in (0..img_height).step_by(MARIO_SPRITE_BLOCK_SIZE) {
         for x in (0..img_width).step_by(MARIO_SPRITE_BLOCK_SIZE) {
@@ -36,32 +352,44 @@ pub fn main() {
                     MARIO_SPRITE_BLOCK_SIZE.try_into().unwrap(),
                 )
                 .to_image();
-            let mut found = false;
-            let mut sprite_id: u8 = 0;
-            
-            for (i, existing_tile) in tiles_map.iter().enumerate() {
-                assert!(tiles_map.len() < 256);
-                if tiles_equal(&tile, existing_tile) {
-                    found = true;
-                    sprite_id = i as u8;
-                    break;
-                }
+            if let Some(kind) = marker_kind(&tile) {
+                // markers are painted over the sky, which is the top left tile of every level
+                assert!(!level_data.is_empty(), "The top left tile can't be a spawn marker");
+                entities.push(EntitySpawn { kind, x: x as usize, y: y as usize });
+                level_data.push(Tile { sprite_id: level_data[0].sprite_id });
+                continue;
             }
+            let sprite_id = tiles_map.sprite_id_for(&tile);
+            level_data.push(Tile { sprite_id });
+        }
+    }
 
-            if !found {
-                sprite_id = tiles_map.len() as u8;
-                tiles_map.push(tile.clone());
+    let mut foreground = Vec::new();
+    if let Some(foreground_source) = foreground_source {
+        let foreground_img = image::load_from_memory(&foreground_source).expect("Failed to decode foreground image");
+        assert_eq!(foreground_img.dimensions(), (img_width, img_height), "Foreground image must be the size of the level image");
+        let size = MARIO_SPRITE_BLOCK_SIZE as u32;
+        let tiles_per_row = (img_width / size) as usize;
+        for y in (0..img_height).step_by(MARIO_SPRITE_BLOCK_SIZE) {
+            for x in (0..img_width).step_by(MARIO_SPRITE_BLOCK_SIZE) {
+                let tile = foreground_img.view(x, y, size, size).to_image();
+                if tile.pixels().all(|pixel| pixel.0[3] == 0) {
+                    continue;
+                }
+                let index = (y / size) as usize * tiles_per_row + (x / size) as usize;
+                foreground.push((index, tiles_map.sprite_id_for(&tile)));
             }
-
-            level_data.push(Tile { sprite_id });
         }
     }
 
     let tilesheet_width = MARIO_SPRITE_BLOCK_SIZE;
-    let tilesheet_height = MARIO_SPRITE_BLOCK_SIZE * tiles_map.len();
+    if tiles_map.merged > 0 {
+        info!("Merged {} near-duplicate tiles into existing sprite ids", tiles_map.merged);
+    }
+    let tilesheet_height = MARIO_SPRITE_BLOCK_SIZE * tiles_map.tiles.len();
     let mut tilesheet = ImageBuffer::new(tilesheet_width as u32, tilesheet_height as u32);
 
-    for (i, tile) in tiles_map.iter().enumerate() {
+    for (i, tile) in tiles_map.tiles.iter().enumerate() {
         let y_offset = i as u32 * MARIO_SPRITE_BLOCK_SIZE as u32;
 
         for y in 0..MARIO_SPRITE_BLOCK_SIZE {
@@ -73,24 +401,201 @@ pub fn main() {
     }
 
     tilesheet
-        .save("sprites/tilesheet.png")
+        .save(&paths.tilesheet)
         .expect("Failed to save tilesheet");
 
+    // types already in the file were chosen by hand and stay, only ids it doesn't know yet are added
+    let mut tile_types = load_tile_types(&paths.tile_types).unwrap_or_default();
+    for sprite_id in 0..tiles_map.tiles.len() as u8 {
+        tile_types
+            .entry(sprite_id)
+            .or_insert_with(|| default_tile_type(sprite_id));
+    }
+    let tile_types_json = serde_json::to_string_pretty(&tile_types).expect("Failed to serialize tile types");
+    File::create(&paths.tile_types)
+        .and_then(|mut file| file.write_all(tile_types_json.as_bytes()))
+        .expect("Failed to write tile types");
+
+    let (meta, rooms, warps) = match existing_level_data(&paths.level_data) {
+        Some(existing) => (existing.meta, existing.rooms, existing.warps),
+        None => (LevelMeta::default(), Vec::new(), Vec::new()),
+    };
     let level_data_json = LevelData {
+        meta,
         height: img_height as usize,
+        width: img_width as usize,
         tiles: level_data.iter().map(|t| t.sprite_id).collect(),
+        foreground,
+        entities,
+        rooms,
+        warps,
     };
 
-    let json_data =
-        serde_json::to_string_pretty(&level_data_json).expect("Failed to serialize level data");
-    let mut file = File::create("leveldata/level_data.json").expect("Failed to create file");
+    let json_data = match options.encoding {
+        TileEncoding::Plain => serde_json::to_string_pretty(&level_data_json),
+        TileEncoding::Rle => {
+            // through a Value, which sorts the keys, so only the tiles field has to change
+            let mut json_value = serde_json::to_value(&level_data_json).expect("Failed to serialize level data");
+            json_value["tiles"] = serde_json::to_value(StoredTiles::Rle { rle: encode_rle(&level_data_json.tiles) })
+                .expect("Failed to serialize level data");
+            serde_json::to_string_pretty(&json_value)
+        }
+    }
+    .expect("Failed to serialize level data");
+    let mut file = File::create(&paths.level_data).expect("Failed to create file");
     file.write_all(json_data.as_bytes())
         .expect("Failed to write to file");
+    File::create(&paths.source_hash)
+        .and_then(|mut file| file.write_all(source_hash.as_bytes()))
+        .expect("Failed to write source hash");
+    true
+}
+
+// the level file being replaced, its hand written parts are carried over
+fn existing_level_data(level_data: &Path) -> Option<LevelData> {
+    let json = std::fs::read(level_data).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+// FNV-1a over the preparation version and the image bytes
+fn source_hash(source: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in PREPARATION_VERSION.to_le_bytes().iter().chain(source) {
+        hash = (hash ^ *byte as u64).wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+impl LevelData {
+    // in pixels like height; older files without a width get it from the tile count
+    pub fn width(&self) -> usize {
+        if self.width != 0 {
+            return self.width;
+        }
+        let rows = (self.height / MARIO_SPRITE_BLOCK_SIZE).max(1);
+        self.tiles.len() / rows * MARIO_SPRITE_BLOCK_SIZE
+    }
 }
 
-fn tiles_equal(
-    tile1: &ImageBuffer<Rgba<u8>, Vec<u8>>,
-    tile2: &ImageBuffer<Rgba<u8>, Vec<u8>>,
-) -> bool {
-    tile1.pixels().zip(tile2.pixels()).all(|(p1, p2)| p1 == p2)
-}
\ No newline at end of file
+fn marker_kind(tile: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Option<EntityKind> {
+    let first = tile.pixels().next()?;
+    if !tile.pixels().all(|pixel| pixel == first) {
+        return None;
+    }
+    MARKER_COLORS
+        .iter()
+        .find(|(color, _)| first.0 == *color)
+        .map(|(_, kind)| *kind)
+}
+
+// the id of an equal tile already in the tilesheet, or the id it gets appended with
+type TileImage = ImageBuffer<Rgba<u8>, Vec<u8>>;
+
+// the tilesheet being built; identical tiles are found by hash, near duplicates by comparing pixels
+struct TileSet {
+    tiles: Vec<TileImage>,
+    by_hash: HashMap<u64, u8>, // also holds the variants merged into a tile, so each is compared once
+    channel_sums: Vec<[u32; 4]>, // per tile, tiles too far apart in them can't be near duplicates
+    tolerance: Tolerance,
+    merged: usize, // distinct tiles that were given the id of a near duplicate
+}
+
+impl TileSet {
+    fn new(tolerance: Tolerance) -> TileSet {
+        TileSet {
+            tiles: Vec::new(),
+            by_hash: HashMap::new(),
+            channel_sums: Vec::new(),
+            tolerance,
+            merged: 0,
+        }
+    }
+
+    // a new sprite id without looking for duplicates, the existing tilesheet keeps its ids this way
+    fn push(&mut self, tile: TileImage) -> u8 {
+        assert!(self.tiles.len() < 256);
+        let sprite_id = self.tiles.len() as u8;
+        self.by_hash.entry(source_hash(tile.as_raw())).or_insert(sprite_id);
+        self.channel_sums.push(channel_sums(&tile));
+        self.tiles.push(tile);
+        sprite_id
+    }
+
+    fn sprite_id_for(&mut self, tile: &TileImage) -> u8 {
+        let hash = source_hash(tile.as_raw());
+        if let Some(sprite_id) = self.by_hash.get(&hash) {
+            return *sprite_id;
+        }
+        if self.tolerance != Tolerance::default() {
+            let sums = channel_sums(tile);
+            // every pixel may be off by channel_delta and the differing ones by anything
+            let pixel_count = tile.pixels().len() as u32;
+            let allowed = self.tolerance.pixels as u32 * 255 + pixel_count * self.tolerance.channel_delta as u32;
+            let near = (0..self.tiles.len()).find(|index| {
+                let close_sums = sums.iter().zip(self.channel_sums[*index]).all(|(sum, other)| sum.abs_diff(other) <= allowed);
+                close_sums && tiles_similar(tile, &self.tiles[*index], self.tolerance)
+            });
+            if let Some(index) = near {
+                self.merged += 1;
+                self.by_hash.insert(hash, index as u8);
+                return index as u8;
+            }
+        }
+        self.push(tile.clone())
+    }
+}
+
+fn channel_sums(tile: &TileImage) -> [u32; 4] {
+    let mut sums = [0; 4];
+    for pixel in tile.pixels() {
+        for (sum, channel) in sums.iter_mut().zip(pixel.0) {
+            *sum += channel as u32;
+        }
+    }
+    sums
+}
+
+fn tiles_similar(tile1: &TileImage, tile2: &TileImage, tolerance: Tolerance) -> bool {
+    let differing = tile1
+        .pixels()
+        .zip(tile2.pixels())
+        .filter(|(p1, p2)| p1.0.iter().zip(p2.0).any(|(c1, c2)| c1.abs_diff(c2) > tolerance.channel_delta))
+        .count();
+    differing <= tolerance.pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rle_tiles_decode_to_exactly_the_tiles_encoded() {
+        let mut tiles: Vec<u8> = (0..=255).collect();
+        tiles.extend([0; 300]);
+        tiles.extend([10, 10, 9, 10, 255, 255, 0]);
+        tiles.extend((0..1000).map(|index| (index / 7 % 3) as u8));
+        for tiles in [tiles, Vec::new(), vec![7]] {
+            assert_eq!(decode_rle(&encode_rle(&tiles)).unwrap(), tiles);
+        }
+        assert_eq!(encode_rle(&[0, 0, 0, 10, 9, 9]), "3*0 1*10 2*9");
+    }
+
+    #[test]
+    fn level_data_loads_the_same_tiles_from_either_encoding() {
+        let tiles: Vec<u8> = (0..14 * 20).map(|index| if index >= 13 * 20 { 10 } else { (index % 5 == 0) as u8 }).collect();
+        let plain = serde_json::json!({ "height": 14 * MARIO_SPRITE_BLOCK_SIZE, "tiles": tiles });
+        let rle = serde_json::json!({ "height": 14 * MARIO_SPRITE_BLOCK_SIZE, "tiles": { "rle": encode_rle(&tiles) } });
+        for json in [plain, rle] {
+            let level_data: LevelData = serde_json::from_value(json).unwrap();
+            assert_eq!(level_data.tiles, tiles);
+            assert_eq!(level_data.width(), 20 * MARIO_SPRITE_BLOCK_SIZE);
+        }
+    }
+
+    #[test]
+    fn a_broken_run_is_an_error() {
+        for rle in ["3*0 x*1", "3*0 2", "1*256", "*4"] {
+            assert!(decode_rle(rle).is_err(), "{:?}", rle);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+
+use log::warn;
+
+use crate::input::InputState;
+
+const MAGIC: &[u8; 4] = b"RRPL";
+const VERSION: u8 = 1;
+// a checksum record follows every CHECKSUM_INTERVAL input records
+pub const CHECKSUM_INTERVAL: u64 = 60;
+const CHECKSUM_TAG: u8 = 0x80;
+
+// one byte per physics frame, jump_pressed is derived again on playback
+fn encode(input: &InputState) -> u8 {
+    input.left as u8
+        | (input.right as u8) << 1
+        | (input.jump as u8) << 2
+        | (input.run as u8) << 3
+        | (input.down as u8) << 4
+}
+
+fn decode(byte: u8, previous: &InputState) -> InputState {
+    let mut input = InputState {
+        left: byte & 1 != 0,
+        right: byte & 1 << 1 != 0,
+        jump: byte & 1 << 2 != 0,
+        jump_pressed: false,
+        run: byte & 1 << 3 != 0,
+        run_pressed: false,
+        down: byte & 1 << 4 != 0,
+    };
+    input.update_edges(previous);
+    input
+}
+
+// header: magic, version, seed (u64 le), level id length (u16 le) and bytes; then the records
+pub struct Recorder {
+    writer: BufWriter<File>,
+    header_len: u64,
+    frame: u64,
+}
+
+impl Recorder {
+    pub fn create(path: &str, seed: u64, level_id: &str) -> io::Result<Recorder> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&seed.to_le_bytes())?;
+        writer.write_all(&(level_id.len() as u16).to_le_bytes())?;
+        writer.write_all(level_id.as_bytes())?;
+        let header_len = (MAGIC.len() + 1 + 8 + 2 + level_id.len()) as u64;
+        Ok(Recorder { writer, header_len, frame: 0 })
+    }
+
+    // drops everything recorded after frame, recording continues from there (loading a savestate, rewinding)
+    pub fn rewind_to(&mut self, frame: u64) -> io::Result<()> {
+        if frame >= self.frame {
+            return Ok(());
+        }
+        let position = self.header_len + frame + (frame / CHECKSUM_INTERVAL) * 9;
+        self.writer.flush()?;
+        let file = self.writer.get_mut();
+        file.set_len(position)?;
+        file.seek(SeekFrom::Start(position))?;
+        self.frame = frame;
+        Ok(())
+    }
+
+    // checksum is the world state after this frame's step
+    pub fn record(&mut self, input: &InputState, checksum: u64) -> io::Result<()> {
+        self.writer.write_all(&[encode(input)])?;
+        self.frame += 1;
+        if self.frame.is_multiple_of(CHECKSUM_INTERVAL) {
+            self.writer.write_all(&[CHECKSUM_TAG])?;
+            self.writer.write_all(&checksum.to_le_bytes())?;
+            // flushed here so a crash still leaves a replay up to the last checksum
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Replay {
+    #[allow(dead_code)] // nothing is random yet, kept so old replays stay readable once something is
+    pub seed: u64,
+    pub level_id: String,
+    inputs: Vec<InputState>,
+    checksums: Vec<(u64, u64)>, // (frame count after which it was taken, checksum)
+    frame: u64,
+    verified_frame: u64,
+    desynced: bool,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> io::Result<Replay> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, message));
+        if bytes.len() < 15 || &bytes[0..4] != MAGIC {
+            return Err(invalid("not a replay file"));
+        }
+        if bytes[4] != VERSION {
+            return Err(invalid("unsupported replay version"));
+        }
+        let seed = u64::from_le_bytes(bytes[5..13].try_into().expect("slice is 8 bytes"));
+        let level_id_len = u16::from_le_bytes([bytes[13], bytes[14]]) as usize;
+        let records_start = 15 + level_id_len;
+        if bytes.len() < records_start {
+            return Err(invalid("truncated header"));
+        }
+        let level_id = String::from_utf8_lossy(&bytes[15..records_start]).into_owned();
+
+        let mut inputs = Vec::new();
+        let mut checksums = Vec::new();
+        let mut previous = InputState::default();
+        let mut index = records_start;
+        while index < bytes.len() {
+            if bytes[index] == CHECKSUM_TAG {
+                let Some(checksum) = bytes.get(index + 1..index + 9) else {
+                    break; // cut off mid record, play what we have
+                };
+                checksums.push((inputs.len() as u64, u64::from_le_bytes(checksum.try_into().expect("slice is 8 bytes"))));
+                index += 9;
+            } else {
+                let input = decode(bytes[index], &previous);
+                inputs.push(input);
+                previous = input;
+                index += 1;
+            }
+        }
+        Ok(Replay {
+            seed,
+            level_id,
+            inputs,
+            checksums,
+            frame: 0,
+            verified_frame: 0,
+            desynced: false,
+        })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame as usize == self.inputs.len()
+    }
+
+    pub fn desynced(&self) -> bool {
+        self.desynced
+    }
+
+    // None once the recording is over
+    pub fn next_input(&mut self) -> Option<InputState> {
+        let input = self.inputs.get(self.frame as usize).copied();
+        if input.is_some() {
+            self.frame += 1;
+        }
+        input
+    }
+
+    // call after stepping with next_input, reports the first frame where the world went a different way
+    pub fn verify(&mut self, checksum: u64) {
+        // past the end of the recording the frame stops advancing, don't check the last one again
+        if self.desynced || self.verified_frame == self.frame {
+            return;
+        }
+        self.verified_frame = self.frame;
+        let expected = self.checksums.iter().find(|(frame, _)| *frame == self.frame);
+        if let Some((frame, expected)) = expected {
+            if *expected != checksum {
+                self.desynced = true;
+                warn!(
+                    "Replay desynced within frames {}..={} (checksum {:016x}, recorded {:016x})",
+                    frame.saturating_sub(CHECKSUM_INTERVAL) + 1,
+                    frame,
+                    checksum,
+                    expected
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::VecDeque;
+
+use crate::config::{PHYSICS_FRAME_PER_SECOND, REWIND_SECONDS, REWIND_SNAPSHOT_FRAMES};
+use crate::input::InputState;
+
+// a snapshot plus what's needed to continue recording from it
+pub struct SaveSlot<State> {
+    pub state: State,
+    pub frame: u64,
+    pub previous_input: InputState,
+}
+
+// the last REWIND_SECONDS of play, a snapshot every REWIND_SNAPSHOT_FRAMES physics frames.
+// the oldest snapshot makes room for the newest, so it never holds more than capacity() of them
+pub struct Rewind<State> {
+    snapshots: VecDeque<SaveSlot<State>>,
+    frame: u64, // physics frames stepped, goes back with the snapshots
+    rewind_time: f32, // seconds rewound that didn't add up to a snapshot yet
+}
+
+impl<State> Default for Rewind<State> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<State> Rewind<State> {
+    pub fn new() -> Rewind<State> {
+        Rewind {
+            snapshots: VecDeque::with_capacity(Self::capacity()),
+            frame: 0,
+            rewind_time: 0.0,
+        }
+    }
+
+    pub const fn capacity() -> usize {
+        (REWIND_SECONDS * PHYSICS_FRAME_PER_SECOND) as usize / REWIND_SNAPSHOT_FRAMES as usize
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    // after every physics step, save only runs for the frames that keep a snapshot
+    pub fn stepped(&mut self, save: impl FnOnce() -> State, previous_input: InputState) {
+        self.frame += 1;
+        self.rewind_time = 0.0;
+        if !self.frame.is_multiple_of(REWIND_SNAPSHOT_FRAMES as u64) {
+            return;
+        }
+        if self.snapshots.len() == Self::capacity() {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(SaveSlot {
+            state: save(),
+            frame: self.frame,
+            previous_input,
+        });
+    }
+
+    // once per render frame while rewinding, goes back as far as dt with snapshots frame_time apart per physics frame.
+    // None until that reaches the next snapshot, or once they are used up
+    pub fn rewind(&mut self, dt: f32, frame_time: f32) -> Option<SaveSlot<State>> {
+        let snapshot_time = REWIND_SNAPSHOT_FRAMES as f32 * frame_time;
+        self.rewind_time += dt;
+        let mut snapshot = None;
+        while self.rewind_time >= snapshot_time {
+            self.rewind_time -= snapshot_time;
+            match self.snapshots.pop_back() {
+                Some(older) => snapshot = Some(older),
+                None => {
+                    self.rewind_time = 0.0;
+                    break;
+                }
+            }
+        }
+        if let Some(snapshot) = &snapshot {
+            self.frame = snapshot.frame;
+        }
+        snapshot
+    }
+
+    // the snapshots don't fit anymore (a savestate was loaded or the level reloaded), play continues from frame
+    pub fn clear(&mut self, frame: u64) {
+        self.snapshots.clear();
+        self.frame = frame;
+        self.rewind_time = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PHYSICS_FRAME_TIME;
+
+    const SNAPSHOT_TIME: f32 = REWIND_SNAPSHOT_FRAMES as f32 * PHYSICS_FRAME_TIME;
+
+    // stepped for frames, every snapshot is the frame it was taken at
+    fn recorded(frames: u64) -> Rewind<u64> {
+        let mut rewind = Rewind::new();
+        for frame in 1..=frames {
+            rewind.stepped(|| frame, InputState::default());
+        }
+        rewind
+    }
+
+    #[test]
+    fn only_the_last_seconds_are_kept() {
+        let frames = 3 * (Rewind::<u64>::capacity() as u64 * REWIND_SNAPSHOT_FRAMES as u64) + 2;
+        let mut rewind = recorded(frames);
+        let mut rewound = Vec::new();
+        while let Some(snapshot) = rewind.rewind(SNAPSHOT_TIME, PHYSICS_FRAME_TIME) {
+            assert_eq!(snapshot.state, snapshot.frame);
+            rewound.push(snapshot.frame);
+        }
+        // newest first, one snapshot per snapshot's worth of time, back REWIND_SECONDS
+        let newest = frames - frames % REWIND_SNAPSHOT_FRAMES as u64;
+        let expected: Vec<u64> = (0..Rewind::<u64>::capacity() as u64).map(|back| newest - back * REWIND_SNAPSHOT_FRAMES as u64).collect();
+        assert_eq!(rewound, expected);
+        assert_eq!(rewind.frame(), *expected.last().unwrap());
+    }
+
+    #[test]
+    fn rewinding_goes_back_by_the_time_held() {
+        let mut rewind = recorded(400);
+        // less than a snapshot apart, nothing yet, the time adds up
+        assert!(rewind.rewind(SNAPSHOT_TIME * 0.6, PHYSICS_FRAME_TIME).is_none());
+        assert_eq!(rewind.rewind(SNAPSHOT_TIME * 0.6, PHYSICS_FRAME_TIME).unwrap().frame, 400);
+        // a second back at once skips the snapshots in between
+        assert_eq!(rewind.rewind(1.0, PHYSICS_FRAME_TIME).unwrap().frame, 400 - 60);
+        // stepping on from there records on from its frame
+        rewind.stepped(|| 341, InputState::default());
+        assert_eq!(rewind.frame(), 341);
+    }
+}
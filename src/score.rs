@@ -0,0 +1,196 @@
+use macroquad::math::Vec2;
+
+use crate::config::{BRICK_POINTS, COIN_POINTS, FIREBALL_KILL_POINTS, FLAGPOLE_POINTS, MAX_PLAYERS, POWERUP_POINTS, STOMP_CHAIN_POINTS, TIME_BONUS_POINTS};
+use crate::entities::ObjectType;
+use crate::world::{GameEvent, GameEventType};
+
+// points given for something, and where it happened so they can float up from there
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Award {
+    pub points: u32,
+    pub pos: Vec2,
+}
+
+// the score and the coins of the HUD, worked out from the game events as the world handles them.
+// the world only reports what happened, the point values are all in config. in co-op the players share one score
+#[derive(Clone, Default, Debug)]
+pub struct ScoreKeeper {
+    score: u32,
+    coins: usize,
+    stomp_chains: [usize; MAX_PLAYERS], // enemies each player stomped since they last stood on the ground
+    awards: Vec<Award>, // since the world last cleared them, once per render frame
+}
+
+impl ScoreKeeper {
+    pub fn new() -> ScoreKeeper {
+        ScoreKeeper::default()
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn coins(&self) -> usize {
+        self.coins
+    }
+
+    // what the last render frame gave points for, oldest first, for popup text
+    pub fn awards(&self) -> &[Award] {
+        &self.awards
+    }
+
+    pub(crate) fn clear_awards(&mut self) {
+        self.awards.clear();
+    }
+
+    fn award(&mut self, points: u32, pos: Vec2) {
+        if points == 0 {
+            return; // nothing to show for no time left on the clock
+        }
+        self.score = self.score.saturating_add(points);
+        self.awards.push(Award { points, pos });
+    }
+
+    // every event the world handles comes through here, the ones not worth anything are ignored
+    pub(crate) fn handle_game_event(&mut self, game_event: &GameEvent) {
+        match (&game_event.event, game_event.target) {
+            // an enemy falling out of the world kills itself, only the player's kills count. a Koopa going into its shell
+            // is worth the same as a kill
+            (GameEventType::Kill | GameEventType::EnemyStomped, Some(victim)) if game_event.triggered_by.object_type == ObjectType::Player => {
+                // every stomp before landing is worth more, the chain stays at the top value once it gets there
+                let chain = &mut self.stomp_chains[game_event.player.unwrap_or(0)];
+                let points = STOMP_CHAIN_POINTS[(*chain).min(STOMP_CHAIN_POINTS.len() - 1)];
+                *chain += 1;
+                self.award(points, victim.pos);
+            }
+            (GameEventType::Kill, Some(victim)) if game_event.triggered_by.object_type == ObjectType::Fireball => {
+                self.award(FIREBALL_KILL_POINTS, victim.pos);
+            }
+            (GameEventType::CoinCollected, Some(coin)) => {
+                self.coins += 1;
+                self.award(COIN_POINTS, coin.pos);
+            }
+            (GameEventType::PlayerPowerUp, Some(powerup)) => self.award(POWERUP_POINTS, powerup.pos),
+            _ => {}
+        }
+    }
+
+    // kills is how many the shell took out before this one since it was kicked, they go up like a stomp chain
+    pub(crate) fn shell_kill(&mut self, kills: usize, pos: Vec2) {
+        self.award(STOMP_CHAIN_POINTS[kills.min(STOMP_CHAIN_POINTS.len() - 1)], pos);
+    }
+
+    pub(crate) fn landed(&mut self, player: usize) {
+        self.stomp_chains[player] = 0;
+    }
+
+    #[allow(dead_code)] // nothing breaks yet
+    pub(crate) fn brick_broken(&mut self, pos: Vec2) {
+        self.award(BRICK_POINTS, pos);
+    }
+
+    // flag_height goes from 0.0 at the bottom of the level to 1.0 at the top, time_left is in the clock's time units
+    pub(crate) fn level_won(&mut self, flag_height: f32, time_left: u32, pos: Vec2) {
+        let band = (flag_height.clamp(0.0, 1.0) * FLAGPOLE_POINTS.len() as f32) as usize;
+        self.award(FLAGPOLE_POINTS[band.min(FLAGPOLE_POINTS.len() - 1)], pos);
+        self.award(time_left * TIME_BONUS_POINTS, pos);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::{BlockType, EnemyType, Object, PowerupType};
+
+    fn event(event: GameEventType, by: ObjectType, target: ObjectType, player: usize) -> GameEvent {
+        GameEvent {
+            event,
+            triggered_by: Object::new(0, 0, by),
+            target: Some(Object::new(32, 48, target)),
+            entity: None,
+            coin: None,
+            source: None,
+            contact: None,
+            player: Some(player),
+        }
+    }
+
+    fn stomp(player: usize) -> GameEvent {
+        event(GameEventType::Kill, ObjectType::Player, ObjectType::Enemy(EnemyType::Goomba), player)
+    }
+
+    #[test]
+    fn stomps_before_landing_chain_up_and_stay_at_the_top() {
+        let mut score = ScoreKeeper::new();
+        for _ in 0..STOMP_CHAIN_POINTS.len() + 3 {
+            score.handle_game_event(&stomp(0));
+        }
+        let points: Vec<u32> = score.awards().iter().map(|award| award.points).collect();
+        let top = *STOMP_CHAIN_POINTS.last().unwrap();
+        assert_eq!(points, [STOMP_CHAIN_POINTS.as_slice(), &[top; 3]].concat());
+        assert_eq!(score.score(), points.iter().sum::<u32>());
+        // landing starts the chain over
+        score.landed(0);
+        score.clear_awards();
+        score.handle_game_event(&stomp(0));
+        assert_eq!(score.awards(), [Award { points: STOMP_CHAIN_POINTS[0], pos: Vec2::new(32.0, 48.0) }]);
+    }
+
+    #[test]
+    fn each_player_has_their_own_chain() {
+        let mut score = ScoreKeeper::new();
+        score.handle_game_event(&stomp(0));
+        score.handle_game_event(&stomp(0));
+        score.handle_game_event(&stomp(1));
+        score.landed(0);
+        score.handle_game_event(&stomp(1));
+        let points: Vec<u32> = score.awards().iter().map(|award| award.points).collect();
+        assert_eq!(points, [STOMP_CHAIN_POINTS[0], STOMP_CHAIN_POINTS[1], STOMP_CHAIN_POINTS[0], STOMP_CHAIN_POINTS[1]]);
+    }
+
+    #[test]
+    fn coins_powerups_fireballs_and_shells_are_worth_their_points() {
+        let mut score = ScoreKeeper::new();
+        score.handle_game_event(&event(GameEventType::CoinCollected, ObjectType::Player, ObjectType::Coin, 0));
+        score.handle_game_event(&event(GameEventType::PlayerPowerUp, ObjectType::Player, ObjectType::Powerup(PowerupType::Mushroom), 0));
+        score.handle_game_event(&event(GameEventType::Kill, ObjectType::Fireball, ObjectType::Enemy(EnemyType::Goomba), 0));
+        score.shell_kill(0, Vec2::ZERO);
+        score.shell_kill(50, Vec2::ZERO);
+        score.brick_broken(Vec2::ZERO);
+        let points: Vec<u32> = score.awards().iter().map(|award| award.points).collect();
+        assert_eq!(
+            points,
+            [COIN_POINTS, POWERUP_POINTS, FIREBALL_KILL_POINTS, STOMP_CHAIN_POINTS[0], *STOMP_CHAIN_POINTS.last().unwrap(), BRICK_POINTS]
+        );
+        assert_eq!(score.coins(), 1);
+        // a fireball kill doesn't start a stomp chain
+        score.handle_game_event(&stomp(0));
+        assert_eq!(score.awards().last().unwrap().points, STOMP_CHAIN_POINTS[0]);
+    }
+
+    #[test]
+    fn events_worth_nothing_score_nothing() {
+        let mut score = ScoreKeeper::new();
+        let goomba = ObjectType::Enemy(EnemyType::Goomba);
+        // falling out of the world, hits, bumps and the like
+        let mut fell_out = event(GameEventType::Kill, goomba, goomba, 0);
+        fell_out.target = None;
+        score.handle_game_event(&fell_out);
+        score.handle_game_event(&event(GameEventType::Kill, goomba, goomba, 0));
+        score.handle_game_event(&event(GameEventType::PlayerHit, goomba, ObjectType::Player, 0));
+        score.handle_game_event(&event(GameEventType::PlayerHitBlock, ObjectType::Player, ObjectType::Block(BlockType::Block), 0));
+        score.level_won(0.5, 0, Vec2::ZERO);
+        // only the flagpole's points, nothing for no time left
+        assert_eq!(score.awards().len(), 1);
+        assert_eq!(score.score(), FLAGPOLE_POINTS[2]);
+    }
+
+    #[test]
+    fn the_flag_height_picks_the_band_and_the_clock_adds_its_bonus() {
+        for (height, points) in [(0.0, FLAGPOLE_POINTS[0]), (0.19, FLAGPOLE_POINTS[0]), (0.2, FLAGPOLE_POINTS[1]), (0.99, FLAGPOLE_POINTS[4]), (1.0, FLAGPOLE_POINTS[4]), (1.5, FLAGPOLE_POINTS[4]), (-0.5, FLAGPOLE_POINTS[0])] {
+            let mut score = ScoreKeeper::new();
+            score.level_won(height, 123, Vec2::ZERO);
+            assert_eq!(score.score(), points + 123 * TIME_BONUS_POINTS, "at {}", height);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use macroquad::color::{Color, WHITE};
+use macroquad::input::{is_key_pressed, KeyCode};
+use macroquad::shapes::draw_rectangle;
+use macroquad::text::draw_text;
+
+use crate::input::InputState;
+use crate::rewind::SaveSlot;
+
+const SLOT_KEYS: [KeyCode; 10] = [
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+pub enum TasRequest {
+    Save,
+    Load,
+}
+
+// 0-9 pick a slot, F5 saves, F9 loads, P pauses, Period advances one physics frame while paused
+pub struct Tas<State> {
+    slots: Vec<Option<SaveSlot<State>>>,
+    selected_slot: usize,
+    paused: bool,
+    advance_requested: bool,
+    frame: u64, // physics frames stepped, rewinds with loads
+}
+
+impl<State: Clone> Default for Tas<State> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<State: Clone> Tas<State> {
+    pub fn new() -> Tas<State> {
+        Tas {
+            slots: (0..SLOT_KEYS.len()).map(|_| None).collect(),
+            selected_slot: 0,
+            paused: false,
+            advance_requested: false,
+            frame: 0,
+        }
+    }
+
+    // once per render frame
+    pub fn poll(&mut self) -> Option<TasRequest> {
+        if let Some(slot) = SLOT_KEYS.iter().position(|key| is_key_pressed(*key)) {
+            self.selected_slot = slot;
+        }
+        if is_key_pressed(KeyCode::P) {
+            self.paused = !self.paused;
+        }
+        if self.paused && is_key_pressed(KeyCode::Period) {
+            self.advance_requested = true;
+        }
+        if is_key_pressed(KeyCode::F5) {
+            return Some(TasRequest::Save);
+        }
+        if is_key_pressed(KeyCode::F9) {
+            return Some(TasRequest::Load);
+        }
+        None
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // whether one physics frame should run while paused, asking uses the request up
+    pub fn take_advance(&mut self) -> bool {
+        std::mem::take(&mut self.advance_requested)
+    }
+
+    pub fn stepped(&mut self) {
+        self.frame += 1;
+    }
+
+    // time was rewound outside the savestates
+    pub fn set_frame(&mut self, frame: u64) {
+        self.frame = frame;
+    }
+
+    pub fn save(&mut self, state: State, previous_input: InputState) {
+        self.slots[self.selected_slot] = Some(SaveSlot {
+            state,
+            frame: self.frame,
+            previous_input,
+        });
+    }
+
+    // the slot stays, so the same point can be loaded again and again while re-recording
+    pub fn load(&mut self) -> Option<SaveSlot<State>> {
+        let slot = self.slots[self.selected_slot].as_ref()?;
+        self.frame = slot.frame;
+        Some(SaveSlot {
+            state: slot.state.clone(),
+            frame: slot.frame,
+            previous_input: slot.previous_input,
+        })
+    }
+
+    pub fn draw(&self) {
+        let status = format!(
+            "TAS slot {}{} frame {}{}",
+            self.selected_slot,
+            if self.slots[self.selected_slot].is_some() { "*" } else { "" },
+            self.frame,
+            if self.paused { " paused" } else { "" }
+        );
+        draw_rectangle(0.0, 40.0, 260.0, 24.0, Color::new(0.0, 0.0, 0.0, 0.5));
+        draw_text(&status, 10.0, 58.0, 20.0, WHITE);
+    }
+}
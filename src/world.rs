@@ -0,0 +1,3747 @@
+use std::borrow::BorrowMut;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+
+use log::{debug, error, info, trace, warn};
+use macroquad::prelude::*;
+use slotmap::{new_key_type, SlotMap};
+
+use crate::animation::PlayAnimationBuilder;
+use crate::assets::{AssetError, Assets, TextureId, LUIGI_FRAMES, MARIO_FRAMES};
+use crate::audio::{AudioManager, AudioSettings, MusicId, SfxId};
+use crate::camera::Camera;
+use crate::collision::{overlap_area, Contact, RayHit, SurroundingObject};
+use crate::config::{DYING_TIME, HIT_INVINCIBLE_FRAMES, KICK_GRACE_FRAMES, LAKITU_LEAVES_BEFORE_GOAL, LAKITU_THROW_FRAMES, HURRY_UP_TIME, LEVEL_TIME, MARIO_SPRITE_BLOCK_SIZE, MAX_FIREBALLS, MAX_SPINIES, MAX_PHYSICS_STEPS_PER_FRAME, MAX_PLAYERS, SCALE_IMAGE_FACTOR, TIME_UNIT_FRAMES};
+use crate::grid::{Grid, GridCells};
+use crate::entities::{Block, BlockType, BulletBill, Coin, EnemyType, Entity, Fireball, Goomba, Koopa, KoopaState, Lakitu, Object, ObjectType, PiranhaPlant, PlantState, Player, PlayerState, PowerUp, PowerupType, SpawningObject, Spiny, Updatable};
+use crate::input::InputState;
+use crate::perf_overlay::Stopwatch;
+use crate::physics::PhysicsConfig;
+use crate::preparation::{default_tile_type, EntityKind, EntitySpawn, LevelData, LevelMeta, Room, TileType, Warp, WarpTrigger, TILESHEET_PATH, TILE_TYPES_PATH};
+use crate::score::ScoreKeeper;
+#[cfg(feature = "embed-level")]
+use crate::preparation::LEVEL_DATA_PATH;
+
+new_key_type! {
+    pub struct EntityId;
+    pub struct CoinId;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct WorldBounds {
+    pub(crate) min_x: usize,
+    pub(crate) max_x: usize,
+    pub(crate) max_y: usize,
+    pub(crate) goal_x: Option<usize>, // only the player's bounds have it, None while it is in a room without the goal
+}
+// what the last render frame's physics did, for the performance overlay
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FrameStats {
+    pub steps: u32,
+    pub update_time: Duration, // spent in World::update over all the steps
+    pub collision_pairs: u32, // objects resolved against each other over all the steps
+}
+
+// what the world holds right now
+#[derive(Clone, Copy, Default, Debug)]
+pub struct EntityCounts {
+    pub enemies: usize,
+    pub powerups: usize,
+    pub blocks: usize,
+    pub coins: usize,
+    pub spawning: usize, // powerups still rising out of their block
+}
+
+// one entity's update worked out on a copy, applied once every entity's is done. kept between frames for the buffers
+pub(crate) struct EntityUpdate {
+    id: EntityId,
+    proposed: Option<Entity>,
+    surrounding_objects: Vec<SurroundingObject>,
+    events: Vec<GameEvent>,
+    collision_pairs: u32,
+}
+
+impl EntityUpdate {
+    // room for the ring of cells around a one cell entity, so the buffers don't each grow a few times first
+    fn new(id: EntityId) -> EntityUpdate {
+        EntityUpdate {
+            id,
+            proposed: None,
+            surrounding_objects: Vec::with_capacity(16),
+            events: Vec::with_capacity(4),
+            collision_pairs: 0,
+        }
+    }
+}
+// the shipped level files, compiled in with the embed-level feature so the game runs without them on disk
+#[cfg(feature = "embed-level")]
+pub(crate) const EMBEDDED_FILES: [(&str, &[u8]); 3] = [
+    (LEVEL_DATA_PATH, include_bytes!("../leveldata/level_data.json")),
+    (TILE_TYPES_PATH, include_bytes!("../leveldata/tile_types.json")),
+    (TILESHEET_PATH, include_bytes!("../sprites/tilesheet.png")),
+];
+// the embedded copy if there is one, otherwise the file on disk
+pub(crate) fn read_level_file(path: &Path) -> Result<Vec<u8>, AssetError> {
+    #[cfg(feature = "embed-level")]
+    if let Some((_, bytes)) = EMBEDDED_FILES.iter().find(|(embedded_path, _)| Path::new(embedded_path) == path) {
+        return Ok(bytes.to_vec());
+    }
+    std::fs::read(path).map_err(|error| AssetError::io(path, error))
+}
+// the same without blocking: the file on desktop, fetched relative to the page in the browser
+pub(crate) async fn fetch_level_file(path: &Path) -> Result<Vec<u8>, AssetError> {
+    #[cfg(feature = "embed-level")]
+    if let Some((_, bytes)) = EMBEDDED_FILES.iter().find(|(embedded_path, _)| Path::new(embedded_path) == path) {
+        return Ok(bytes.to_vec());
+    }
+    load_file(&path.to_string_lossy()).await.map_err(|error| AssetError::from_macroquad(path, error))
+}
+// what a level is loaded from, read or fetched up front so loading it doesn't need the filesystem
+pub struct LevelFiles {
+    pub(crate) level_data: Vec<u8>,
+    pub(crate) tile_types: Vec<u8>,
+}
+impl LevelFiles {
+    // blocks, there is no filesystem in the browser
+    pub fn read(level_path: &Path) -> Result<LevelFiles, AssetError> {
+        Ok(LevelFiles {
+            level_data: read_level_file(level_path)?,
+            tile_types: read_level_file(Path::new(TILE_TYPES_PATH))?,
+        })
+    }
+    pub async fn fetch(level_path: &Path) -> Result<LevelFiles, AssetError> {
+        Ok(LevelFiles {
+            level_data: fetch_level_file(level_path).await?,
+            tile_types: fetch_level_file(Path::new(TILE_TYPES_PATH)).await?,
+        })
+    }
+}
+pub(crate) fn tile_object_type(tile_type: TileType) -> Option<ObjectType> {
+    match tile_type {
+        TileType::Solid => Some(ObjectType::Block(BlockType::Block)),
+        TileType::PowerupBlock => Some(ObjectType::Block(BlockType::PowerupBlock)),
+        TileType::Decorative => None,
+    }
+}
+#[derive(Debug, Clone)]
+pub(crate) enum GameEventType {
+    GameWon,
+    GameOver,
+    Kill,
+    EnemyStomped, // an enemy that survives a stomp, a Koopa goes into its shell
+    ShellKicked,
+    PlayerHit,
+    PlayerPowerUp,
+    PlayerHitBlock,
+    PlayerHitPowerupBlock,
+    EnemyCollEnemy,
+    CoinCollected,
+}
+impl GameEventType {
+    // processing order within a frame: deaths first so nothing reacts to an enemy that is already gone,
+    // then pickups, then block hits and what they spawn, the game ending last
+    pub(crate) fn order(&self) -> u8 {
+        match self {
+            GameEventType::Kill | GameEventType::EnemyStomped | GameEventType::ShellKicked | GameEventType::PlayerHit => 0,
+            GameEventType::PlayerPowerUp | GameEventType::CoinCollected => 1,
+            GameEventType::PlayerHitBlock | GameEventType::PlayerHitPowerupBlock | GameEventType::EnemyCollEnemy => 2,
+            GameEventType::GameOver | GameEventType::GameWon => 3,
+        }
+    }
+}
+#[derive(Debug, Clone)]
+pub(crate) struct GameEvent {
+    pub(crate) event: GameEventType,
+    pub(crate) triggered_by: Object,
+    pub(crate) target: Option<Object>,
+    // the entity it is about, the one on the other side of a collision or else the one whose update raised it
+    pub(crate) entity: Option<EntityId>,
+    pub(crate) coin: Option<CoinId>, // the coin a player touched, for CoinCollected
+    pub(crate) source: Option<EntityId>, // the entity whose update raised it, the world fills it in afterwards
+    pub(crate) contact: Option<Contact>, // how triggered_by touched target, for collision events
+    pub(crate) player: Option<usize>, // which player's update raised it, the world fills it in afterwards
+}
+pub(crate) const STEP_TIME_SLACK: f32 = 0.0001; // seconds
+
+// World::transition_to is the only way in or out of a state, it runs the hooks
+#[derive(PartialEq, Clone, Debug)]
+pub enum GameState {
+    Playing,
+    Dying(f32), // seconds until game over, the level stays up while the death jingle plays
+    GameOver,
+    GameWon,
+    Menu(Box<GameState>), // the pause menu is open, closing it goes back to the state inside
+    #[cfg_attr(not(feature = "tas"), allow(dead_code))]
+    Paused(Box<GameState>), // stopped without a menu, frame advance steps the state inside
+}
+impl GameState {
+    // the state a menu or pause sits on top of
+    pub(crate) fn overlaid(&self) -> Option<&GameState> {
+        match self {
+            GameState::Menu(state) | GameState::Paused(state) => Some(state),
+            _ => None,
+        }
+    }
+    // what is shown, under any number of menus and pauses
+    pub(crate) fn underlying(&self) -> &GameState {
+        self.overlaid().map_or(self, GameState::underlying)
+    }
+    // nothing moves and the music ducks
+    pub(crate) fn stands_still(&self) -> bool {
+        matches!(self, GameState::Menu(_) | GameState::Paused(_))
+    }
+    // the level is decided, the music and warps stop with it
+    pub(crate) fn has_ended(&self) -> bool {
+        matches!(self, GameState::Dying(_) | GameState::GameOver | GameState::GameWon)
+    }
+}
+// where the physics steps of a render frame get their input from, and who hears about each step afterwards
+pub trait StepDriver {
+    fn next_input(&mut self) -> InputState;
+    // player two's, only asked for in co-op
+    fn next_second_input(&mut self) -> InputState {
+        InputState::default()
+    }
+    fn stepped(&mut self, world: &World, input: &InputState);
+}
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ObjectReference {
+    Block(usize),
+    Entity(EntityId), // stable key into self.entities, stays valid (or dangles detectably) after removals
+    Player(usize), // index into self.players
+    Coin(CoinId),
+    None,
+}
+impl ObjectReference {
+    pub(crate) fn entity_id(&self) -> Option<EntityId> {
+        match self {
+            ObjectReference::Entity(id) => Some(*id),
+            _ => None,
+        }
+    }
+    pub(crate) fn coin_id(&self) -> Option<CoinId> {
+        match self {
+            ObjectReference::Coin(id) => Some(*id),
+            _ => None,
+        }
+    }
+}
+
+// why an object couldn't be added to the world, positions in pixels
+#[derive(Debug)]
+pub enum WorldError {
+    OutOfBounds { x: usize, y: usize },
+    CellOccupied { x: usize, y: usize, existing: ObjectReference },
+    WrongType(ObjectType), // a block through add_object or anything else through add_block
+}
+
+impl std::fmt::Display for WorldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WorldError::OutOfBounds { x, y } => write!(f, "object at {},{} outside the level", x, y),
+            WorldError::CellOccupied { x, y, existing } => write!(f, "object at {},{}, the cell already holds {:?}", x, y, existing),
+            WorldError::WrongType(object_type) => write!(f, "{:?} added the wrong way", object_type),
+        }
+    }
+}
+
+// FNV-1a, stable across builds and platforms unlike DefaultHasher
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+// for objects the game adds itself, where failing is a bug rather than a bad level file
+#[track_caller]
+pub(crate) fn debug_expect_added(result: Result<(), WorldError>) {
+    if let Err(error) = result {
+        debug_assert!(false, "{}", error);
+        error!("Failed to add {}", error);
+    }
+}
+
+
+// everything a physics step reads and changes, cheap enough to keep a few seconds of for rewinding
+pub struct WorldState {
+    pub(crate) grid: Vec<(usize, usize, ObjectReference)>, // the occupied cells, most of the grid is empty
+    pub(crate) players: Vec<Player>,
+    pub(crate) entities: SlotMap<EntityId, Entity>,
+    pub(crate) coins: SlotMap<CoinId, Coin>,
+    pub(crate) blocks: Vec<Block>,
+    pub(crate) score: ScoreKeeper,
+    pub(crate) spawning_objects: Vec<SpawningObject>,
+    pub(crate) previously_bumped_blocks: Vec<usize>,
+    pub(crate) camera: Camera,
+    pub(crate) game_state: GameState,
+    pub(crate) time_left_frames: u32,
+    pub(crate) current_room: usize,
+}
+impl Clone for WorldState {
+    fn clone(&self) -> Self {
+        WorldState {
+            grid: self.grid.clone(),
+            players: self.players.clone(),
+            entities: self.entities.clone(),
+            coins: self.coins.clone(),
+            blocks: self.blocks.clone(),
+            score: self.score.clone(),
+            spawning_objects: self.spawning_objects.iter().map(SpawningObject::clone_spawning).collect(),
+            previously_bumped_blocks: self.previously_bumped_blocks.clone(),
+            camera: self.camera.clone(),
+            game_state: self.game_state.clone(),
+            time_left_frames: self.time_left_frames,
+            current_room: self.current_room,
+        }
+    }
+}
+pub struct World {
+    pub(crate) height: usize,
+    pub(crate) width: usize,
+    pub(crate) grid: Grid,
+    pub(crate) player_cells: [Option<GridCells>; MAX_PLAYERS], // where each player is in the grid, it leaves exactly those cells
+    pub(crate) players: Vec<Player>, // player one first, player two after it in co-op
+    pub(crate) entities: SlotMap<EntityId, Entity>, // everything that moves on its own except the players
+    pub(crate) coins: SlotMap<CoinId, Coin>,
+    pub(crate) blocks: Vec<Block>,
+    pub(crate) score: ScoreKeeper,
+    pub(crate) spawning_objects: Vec<SpawningObject>,
+    pub(crate) camera: Camera,
+    pub(crate) game_state: GameState,
+    pub(crate) elapsed_time: f32, // render time not yet used up by physics steps
+    pub(crate) time_left_frames: u32, // counts down every physics frame, so it stands still while paused
+    pub(crate) goal_x: usize, // the player wins once past it: the level's goal_x, its flagpole or a tile before the right end
+    pub(crate) meta: LevelMeta,
+    pub(crate) rooms: Vec<Room>, // never empty, a level without rooms is one room
+    pub(crate) current_room: usize, // only it is shown and updated
+    pub(crate) warps: Vec<Warp>,
+    pub(crate) music: MusicId, // the level's track, star power and the hurry up play over it
+    pub(crate) tile_types: HashMap<u8, ObjectType>, // sprite ids that collide, decorative ones are missing
+    pub(crate) level_texture: Option<Texture2D>,
+    pub(crate) foreground_texture: Option<Texture2D>, // drawn over the player and enemies, None if the level has no foreground
+
+    pub audio: AudioManager,
+    // blocks the player's head touched this frame and the frame before, bumps only react on first contact
+    pub(crate) bumped_blocks: Vec<usize>,
+    pub(crate) previously_bumped_blocks: Vec<usize>,
+    pub(crate) events: Vec<GameEvent>, // raised during a frame's updates, handled together once everything has moved
+    pub(crate) surrounding_objects: Vec<SurroundingObject>, // reused for every update's neighbours instead of allocating
+    pub(crate) entity_ids: Vec<EntityId>, // same, for the ids a frame walks over while the entities change
+    pub(crate) neighbour_ids: Vec<EntityId>, // same, for the entities the grid has around one of them
+    pub(crate) entity_updates: Vec<EntityUpdate>, // same, one per entity in the current room, in id order
+    pub(crate) frame_stats: FrameStats,
+    pub(crate) has_player: bool, // without one (editors, tools) the player is left out of the grid, the updates and drawing
+    pub(crate) second_player: bool, // co-op, a start marker spawns both players
+    pub(crate) gravity_scale: f32, // multiplies the gravity for everything added to the world
+    pub(crate) physics: PhysicsConfig,
+    pub(crate) headless: bool, // nothing is baked or drawn, there is no window to do it with
+    pub(crate) assets: Rc<Assets>,
+}
+
+// how a World is set up, the defaults are the game's
+pub struct WorldBuilder {
+    pub(crate) height: usize,
+    pub(crate) width: usize,
+    pub(crate) camera_width: usize,
+    pub(crate) camera_height: usize,
+    pub(crate) spawn: (usize, usize),
+    pub(crate) has_player: bool,
+    pub(crate) second_player: bool,
+    pub(crate) gravity_scale: f32,
+    pub(crate) physics: PhysicsConfig,
+    pub(crate) headless: bool,
+    pub(crate) assets: Rc<Assets>,
+    pub(crate) audio_settings: AudioSettings,
+}
+
+impl WorldBuilder {
+    pub fn new(height: usize, width: usize) -> WorldBuilder {
+        WorldBuilder {
+            height,
+            width,
+            camera_width: 600,
+            camera_height: height,
+            spawn: (48, 176),
+            has_player: true,
+            second_player: false,
+            gravity_scale: 1.0,
+            physics: PhysicsConfig::default(),
+            headless: false,
+            assets: Rc::new(Assets::empty()),
+            audio_settings: AudioSettings::default(),
+        }
+    }
+    // what the world draws and plays, without them it is invisible and silent
+    pub fn assets(mut self, assets: Rc<Assets>) -> WorldBuilder {
+        self.assets = assets;
+        self
+    }
+    pub fn audio_settings(mut self, audio_settings: AudioSettings) -> WorldBuilder {
+        self.audio_settings = audio_settings;
+        self
+    }
+    // in level pixels, the window shows it scaled by SCALE_IMAGE_FACTOR
+    pub fn camera_viewport(mut self, width: usize, height: usize) -> WorldBuilder {
+        self.camera_width = width;
+        self.camera_height = height;
+        self
+    }
+    // where the player stands until a level's start marker moves it
+    pub fn spawn(mut self, x: usize, y: usize) -> WorldBuilder {
+        self.spawn = (x, y);
+        self
+    }
+    pub fn without_player(mut self) -> WorldBuilder {
+        self.has_player = false;
+        self
+    }
+    // local co-op, Luigi starts a tile to the right of Mario and is driven by StepDriver::next_second_input
+    pub fn second_player(mut self) -> WorldBuilder {
+        self.second_player = true;
+        self
+    }
+    pub fn gravity_scale(mut self, gravity_scale: f32) -> WorldBuilder {
+        self.gravity_scale = gravity_scale;
+        self
+    }
+    // running speed, walking stays capped at the physics' max_walk_speed. after physics, it overrides the one in there
+    pub fn player_max_speed(mut self, player_max_speed: f32) -> WorldBuilder {
+        self.physics.max_speed = player_max_speed;
+        self
+    }
+    pub fn physics(mut self, physics: PhysicsConfig) -> WorldBuilder {
+        self.physics = physics;
+        self
+    }
+    // no window, textures or sounds; the audio only records which effects would have played
+    pub fn headless(mut self) -> WorldBuilder {
+        self.headless = true;
+        self.assets = Rc::new(Assets::empty());
+        self
+    }
+    pub fn build(self) -> World {
+        let (height, width) = (self.height, self.width);
+        let grid = Grid::new(width / MARIO_SPRITE_BLOCK_SIZE, height / MARIO_SPRITE_BLOCK_SIZE);
+        let mut world = World {
+            height,
+            width,
+            grid,
+            player_cells: Default::default(),
+            players: Vec::new(),
+            entities: SlotMap::with_key(),
+            coins: SlotMap::with_key(),
+            score: ScoreKeeper::new(),
+            blocks : Vec::new(),
+            spawning_objects: Vec::new(),
+            camera: Camera::new(self.camera_width, self.camera_height, width),
+            game_state: GameState::Playing,
+            elapsed_time: 0.0,
+            time_left_frames: LEVEL_TIME * TIME_UNIT_FRAMES,
+            goal_x: width.saturating_sub(MARIO_SPRITE_BLOCK_SIZE),
+            meta: LevelMeta::default(),
+            rooms: vec![Room { name: "main".to_owned(), x: 0, width }],
+            current_room: 0,
+            warps: Vec::new(),
+            music: MusicId::Overworld,
+            tile_types: (0..=u8::MAX)
+                .filter_map(|sprite_id| Some((sprite_id, tile_object_type(default_tile_type(sprite_id))?)))
+                .collect(),
+            level_texture: None,
+            foreground_texture: None,
+
+
+            audio: if self.headless {
+                AudioManager::recording()
+            } else {
+                AudioManager::new(self.assets.clone(), self.audio_settings)
+            },
+            bumped_blocks: Vec::new(),
+            previously_bumped_blocks: Vec::new(),
+            events: Vec::new(),
+            surrounding_objects: Vec::new(),
+            entity_ids: Vec::new(),
+            neighbour_ids: Vec::new(),
+            entity_updates: Vec::new(),
+            frame_stats: FrameStats::default(),
+            has_player: self.has_player,
+            second_player: self.second_player,
+            gravity_scale: self.gravity_scale,
+            physics: self.physics,
+            headless: self.headless,
+            assets: self.assets,
+        };
+        world.spawn_players(self.spawn.0, self.spawn.1);
+        world
+    }
+}
+
+impl World {
+    // fill it with load_level, or load_tiles and spawn_entities, and drive it with step or frame
+    pub fn new_headless(height: usize, width: usize) -> World {
+        WorldBuilder::new(height, width).headless().build()
+    }
+    // sizes the grid, camera and goal for a level, before anything is added to it
+    pub(crate) fn resize(&mut self, height: usize, width: usize) {
+        self.height = height;
+        self.width = width;
+        self.grid = Grid::new(width / MARIO_SPRITE_BLOCK_SIZE, height / MARIO_SPRITE_BLOCK_SIZE);
+        self.player_cells = Default::default();
+        self.camera = Camera::new(self.camera.width, self.camera.height, width);
+        self.goal_x = width.saturating_sub(MARIO_SPRITE_BLOCK_SIZE);
+        self.rooms = vec![Room { name: "main".to_owned(), x: 0, width }];
+        self.current_room = 0;
+        self.warps = Vec::new();
+    }
+    // registers the collision blocks of a level, tiles are row major sprite ids like in LevelData.
+    // tiles that can't be added are skipped, the errors say which
+    pub fn load_tiles(&mut self, tiles: &[u8]) -> Vec<WorldError> {
+        let tiles_per_row = self.width / MARIO_SPRITE_BLOCK_SIZE;
+        let mut errors = Vec::new();
+        for (index, tile) in tiles.iter().enumerate() {
+            if let Some(object_type) = self.tile_types.get(tile) {
+                let x = (index % tiles_per_row) * MARIO_SPRITE_BLOCK_SIZE;
+                let y = (index / tiles_per_row) * MARIO_SPRITE_BLOCK_SIZE;
+                errors.extend(self.add_block(Object::new(x, y, *object_type), *tile).err());
+            }
+        }
+        errors
+    }
+
+    // errors name the file and what is wrong with it, the caller decides whether that ends the game.
+    // headless it only builds the collision data, the level isn't baked into a texture
+    pub fn load_level(&mut self, level_path: &Path) -> Result<(), AssetError> {
+        self.load_level_from(level_path, &LevelFiles::read(level_path)?)
+    }
+    // level_path is only used in the errors
+    pub fn load_level_from(&mut self, level_path: &Path, files: &LevelFiles) -> Result<(), AssetError> {
+        let invalid = |error: &dyn std::fmt::Display| AssetError::decode(level_path, error);
+        // the tiles that were loaded at startup, a tilesheet prepared since can't be drawn until a restart
+        let tilesheet_tiles = if self.headless {
+            let tilesheet_path = Path::new(TILESHEET_PATH);
+            let tilesheet_bytes = read_level_file(tilesheet_path)?;
+            let (_, tilesheet_height) = image::ImageReader::new(io::Cursor::new(&tilesheet_bytes))
+                .with_guessed_format()
+                .map_err(|error| AssetError::io(tilesheet_path, error))?
+                .into_dimensions()
+                .map_err(|error| AssetError::decode(tilesheet_path, error))?;
+            tilesheet_height as usize / MARIO_SPRITE_BLOCK_SIZE
+        } else {
+            self.assets.tilesheet.clone()?
+        };
+        let level_data: LevelData = serde_json::from_slice(&files.level_data).map_err(|error| invalid(&error))?;
+        let foreground_tiles = level_data.foreground.iter().map(|(_, tile)| tile);
+        if let Some(tile) = level_data.tiles.iter().chain(foreground_tiles).find(|tile| **tile as usize >= tilesheet_tiles) {
+            return Err(invalid(&format!("tile id {} is not in the tilesheet", tile)));
+        }
+        let (width, height) = (level_data.width(), level_data.height);
+        let expected_tiles = (width / MARIO_SPRITE_BLOCK_SIZE) * (height / MARIO_SPRITE_BLOCK_SIZE);
+        if level_data.tiles.len() != expected_tiles {
+            return Err(invalid(&format!("{} tiles don't fill a {}x{} level", level_data.tiles.len(), width, height)));
+        }
+        if let Some(goal_x) = level_data.meta.goal_x.filter(|goal_x| *goal_x == 0 || *goal_x > width) {
+            return Err(invalid(&format!("goal at {} isn't inside the {} pixel wide level", goal_x, width)));
+        }
+        if let Some(room) = level_data.rooms.iter().find(|room| room.width == 0 || room.x + room.width > width) {
+            return Err(invalid(&format!("room {} doesn't fit in the {} pixel wide level", room.name, width)));
+        }
+        let room_count = level_data.rooms.len().max(1);
+        if let Some(warp) = level_data.warps.iter().find(|warp| warp.room >= room_count) {
+            return Err(invalid(&format!("warp at {},{} leads to room {}, the level has {}", warp.x, warp.y, warp.room, room_count)));
+        }
+        let tile_types_path = Path::new(TILE_TYPES_PATH);
+        let tile_types: HashMap<u8, TileType> = serde_json::from_slice(&files.tile_types)
+            .map_err(|error| AssetError::decode(tile_types_path, error))?;
+        let mut untyped: Vec<u8> = level_data.tiles.iter().filter(|tile| !tile_types.contains_key(tile)).copied().collect();
+        if !untyped.is_empty() {
+            untyped.sort();
+            untyped.dedup();
+            let untyped: Vec<String> = untyped.iter().map(|tile| tile.to_string()).collect();
+            return Err(invalid(&format!("tile ids {} have no type in {}", untyped.join(", "), TILE_TYPES_PATH)));
+        }
+        self.tile_types = tile_types
+            .into_iter()
+            .filter_map(|(sprite_id, tile_type)| Some((sprite_id, tile_object_type(tile_type)?)))
+            .collect();
+        self.clear_level();
+        self.resize(height, width);
+        self.camera.backward_scroll = level_data.meta.backward_scroll;
+        self.time_left_frames = level_data.meta.time_limit * TIME_UNIT_FRAMES;
+        self.music = MusicId::from_name(&level_data.meta.music).unwrap_or_else(|| {
+            warn!("{}: unknown music {:?}, playing the overworld theme", level_path.display(), level_data.meta.music);
+            MusicId::Overworld
+        });
+        self.meta = level_data.meta.clone();
+        if !level_data.rooms.is_empty() {
+            self.rooms = level_data.rooms.clone();
+        }
+        self.warps = level_data.warps.clone();
+
+        let mut errors = self.load_tiles(&level_data.tiles);
+        errors.extend(self.spawn_entities(&level_data.entities));
+        // over the flagpole, a level can end somewhere else than where its pole is drawn
+        if let Some(goal_x) = level_data.meta.goal_x {
+            self.goal_x = goal_x;
+        }
+        for error in errors {
+            warn!("{}: skipped {}", level_path.display(), error);
+        }
+        let player_x = self.players[0].object.pos.x as usize;
+        self.current_room = self.rooms.iter().position(|room| (room.x..room.x + room.width).contains(&player_x)).unwrap_or(0);
+        self.camera.enter_room(&self.rooms[self.current_room], player_x);
+        info!("Loaded {}: {} by {} pixels, {:?}", level_path.display(), width, height, self.entity_counts());
+
+        self.level_texture = None;
+        self.foreground_texture = None;
+        if self.headless {
+            return Ok(());
+        }
+        self.level_texture = Some(self.bake_level(&level_data.tiles)); // to draw in one call, while keeping compressed json instead of loading a .png
+        if !level_data.foreground.is_empty() {
+            self.foreground_texture = Some(self.bake_foreground(&level_data.foreground));
+        }
+        Ok(())
+    }
+    // the decorative tiles and the sky behind blocks, blocks draw themselves
+    pub(crate) fn bake_level(&self, tiles: &[u8]) -> Texture2D {
+        let sky_texture = self.assets.texture(TextureId::tile(0)).expect("Tilesheet has no tiles");
+        let mut render_target_camera =
+            Camera2D::from_display_rect(Rect::new(0., 0., self.width as f32, self.height as f32));
+
+        let level_render_target = render_target(self.width as u32, self.height as u32);
+
+        render_target_camera.render_target = Some(level_render_target);
+
+
+        {
+            set_camera(&render_target_camera);
+            for (index, tile) in tiles.iter().enumerate() {
+                let x = (index as u32 % (self.width / MARIO_SPRITE_BLOCK_SIZE as usize) as u32)
+                    * MARIO_SPRITE_BLOCK_SIZE as u32;
+                let y = (index as u32 / (self.width / MARIO_SPRITE_BLOCK_SIZE as usize) as u32)
+                    * MARIO_SPRITE_BLOCK_SIZE as u32;
+
+
+                if let None = self.tile_types.get(tile) { // only draw non Blocks
+                    let tile_texture = self.assets.texture(TextureId::tile(*tile)).expect("Couldn't find sprite id in the tilesheet");
+                    draw_texture_ex( 
+                        &tile_texture,
+                        x as f32,
+                        y as f32,
+                        WHITE,
+                        DrawTextureParams::default()
+                    );
+                }
+                else {
+                    draw_texture_ex( // draw background behind any Block
+                        sky_texture,
+                        x as f32,
+                        y as f32,
+                        WHITE,
+                        DrawTextureParams::default()
+                    );
+                }
+            }
+        }
+        draw_text("It's time to save Peach", self.width as f32- 210.0 , self.height as f32 / 2.0 - 25.0, 20.0, WHITE);
+        draw_text("Go! ->", self.width as f32- 55.0 , self.height as f32 / 2.0, 20.0, WHITE); 
+
+        set_default_camera();
+
+        render_target_camera.render_target.unwrap().texture
+    }
+    // hot reload: the players keep their positions and power states if their spots are still free in the new layout.
+    // a broken file leaves the running level alone, load_level checks everything before it changes the world
+    pub fn reload_level(&mut self, level_path: &Path) -> Result<(), AssetError> {
+        self.reload_level_from(level_path, &LevelFiles::read(level_path)?)
+    }
+    pub fn reload_level_from(&mut self, level_path: &Path, files: &LevelFiles) -> Result<(), AssetError> {
+        let players = self.players.clone();
+        self.load_level_from(level_path, files)?;
+        // one that is already out of a co-op game has no spot to keep
+        let spots_free = players.iter().all(|player| {
+            matches!(player.power_state, PlayerState::Dead) || ((player.object.pos.x as usize) < self.width && self.is_free(&player.object))
+        });
+        if players.len() == self.players.len() && spots_free {
+            self.players = players;
+            let player_x = self.players[0].object.pos.x as usize;
+            self.current_room = self.rooms.iter().position(|room| (room.x..room.x + room.width).contains(&player_x)).unwrap_or(0);
+            self.camera.enter_room(&self.rooms[self.current_room], player_x);
+        }
+        Ok(())
+    }
+    // everything the previous level put into the world, the player is replaced by its start marker if there is one
+    pub(crate) fn clear_level(&mut self) {
+        self.entities.clear();
+        self.coins.clear();
+        self.blocks.clear();
+        self.spawning_objects.clear();
+        self.bumped_blocks.clear();
+        self.previously_bumped_blocks.clear();
+        self.score = ScoreKeeper::new();
+        self.transition_to(GameState::Playing);
+    }
+    pub(crate) fn bake_foreground(&self, foreground: &[(usize, u8)]) -> Texture2D {
+        let mut render_target_camera =
+            Camera2D::from_display_rect(Rect::new(0., 0., self.width as f32, self.height as f32));
+        render_target_camera.render_target = Some(render_target(self.width as u32, self.height as u32));
+        set_camera(&render_target_camera);
+        clear_background(Color::new(0.0, 0.0, 0.0, 0.0));
+        let tiles_per_row = self.width / MARIO_SPRITE_BLOCK_SIZE;
+        for (index, tile) in foreground {
+            let x = (index % tiles_per_row) * MARIO_SPRITE_BLOCK_SIZE;
+            let y = (index / tiles_per_row) * MARIO_SPRITE_BLOCK_SIZE;
+            let tile_texture = self.assets.texture(TextureId::tile(*tile)).expect("Couldn't find sprite id in the tilesheet");
+            draw_texture_ex(tile_texture, x as f32, y as f32, WHITE, DrawTextureParams::default());
+        }
+        set_default_camera();
+        render_target_camera.render_target.unwrap().texture
+    }
+
+    // the spawn markers preparation found in the level image, ones that can't be added are skipped
+    pub fn spawn_entities(&mut self, entities: &[EntitySpawn]) -> Vec<WorldError> {
+        let mut errors = Vec::new();
+        for entity in entities {
+            let result = match entity.kind {
+                EntityKind::Goomba => self.add_object(Object::new(entity.x, entity.y, ObjectType::Enemy(EnemyType::Goomba))),
+                EntityKind::Koopa => self.add_object(Object::new(entity.x, entity.y, ObjectType::Enemy(EnemyType::Koopa(KoopaState::Walking)))),
+                EntityKind::PiranhaPlant => self.add_object(Object::new(entity.x, entity.y, ObjectType::Enemy(EnemyType::PiranhaPlant(PlantState::Hidden)))),
+                EntityKind::Coin => self.add_object(Object::new(entity.x, entity.y, ObjectType::Coin)),
+                EntityKind::Star => self.add_object(Object::new(entity.x, entity.y, ObjectType::Powerup(PowerupType::Star))),
+                EntityKind::Lakitu => self.add_object(Object::new(entity.x, entity.y, ObjectType::Enemy(EnemyType::Lakitu))),
+                // its sprite isn't one of the tiles, the marker stands in for it
+                EntityKind::Launcher => self.add_block(Object::new(entity.x, entity.y, ObjectType::Block(BlockType::Launcher)), 0),
+                EntityKind::PlayerStart => {
+                    self.spawn_players(entity.x, entity.y);
+                    Ok(())
+                }
+                EntityKind::Flagpole => {
+                    self.goal_x = entity.x;
+                    Ok(())
+                }
+            };
+            errors.extend(result.err());
+        }
+        errors
+    }
+    // player one at the start, in co-op player two a tile to the right of them unless that is inside a block
+    pub(crate) fn spawn_players(&mut self, x: usize, y: usize) {
+        self.players = vec![Player::new(x, y, self.physics.max_speed, MARIO_FRAMES)];
+        if self.second_player {
+            let mut second = Player::new(x + MARIO_SPRITE_BLOCK_SIZE, y, self.physics.max_speed, LUIGI_FRAMES);
+            if !self.is_free(&second.object) {
+                second.object.pos.x = x as f32;
+            }
+            self.players.push(second);
+        }
+        for player in &mut self.players {
+            player.object.gravity_scale *= self.gravity_scale;
+        }
+    }
+    pub(crate) fn spawn_powerup(&mut self, object: Object) {
+        match object.object_type {
+            ObjectType::Powerup(powerup_type) => {
+                let powerup = PowerUp::new(object.pos.x as usize, object.pos.y as usize, powerup_type);
+                self.spawning_objects.push(SpawningObject::new(powerup));
+            }
+            _ => panic!("Can only spawn powerups with animation"),
+        }
+    }
+    // the grid cell an object would be added to, if it is inside the level and free
+    pub(crate) fn free_cell(&self, object: &Object) -> Result<(usize, usize), WorldError> {
+        let Some((x, y)) = self.grid.grid_index(object.pos) else {
+            return Err(WorldError::OutOfBounds { x: object.pos.x as usize, y: object.pos.y as usize });
+        };
+        let cell = self.grid.get(x, y).unwrap_or_default();
+        if let Some(existing) = cell.first() {
+            return Err(WorldError::CellOccupied { x: object.pos.x as usize, y: object.pos.y as usize, existing: *existing });
+        }
+        Ok((x, y))
+    }
+    pub(crate) fn add_object(&mut self, object: Object) -> Result<(), WorldError> {
+        // fireballs are only ever thrown
+        if let ObjectType::Block(_) | ObjectType::Fireball = object.object_type {
+            return Err(WorldError::WrongType(object.object_type));
+        }
+        let (x, y) = self.free_cell(&object)?;
+        let pos = object.pos;
+        let reference = match object.object_type {
+            ObjectType::Enemy(EnemyType::Goomba) => {
+                let mut goomba = Goomba::new(pos.x as usize, pos.y as usize, 2);
+                goomba.object.gravity_scale = object.gravity_scale * self.gravity_scale;
+                ObjectReference::Entity(self.entities.insert(Entity::Goomba(goomba)))
+            }
+            ObjectType::Enemy(EnemyType::Koopa(state)) => {
+                let mut koopa = Koopa::new(pos.x as usize, pos.y as usize);
+                match state {
+                    KoopaState::Walking => {}
+                    KoopaState::Shell => koopa.hide_in_shell(),
+                    KoopaState::Sliding => {
+                        koopa.hide_in_shell();
+                        koopa.kick(1.0);
+                    }
+                }
+                koopa.object.gravity_scale = object.gravity_scale * self.gravity_scale;
+                ObjectReference::Entity(self.entities.insert(Entity::Koopa(koopa)))
+            }
+            ObjectType::Enemy(EnemyType::PiranhaPlant(_)) => {
+                // placed on the pipe's left tile, it comes out of the middle of the pipe below. it always starts down in it
+                let plant = PiranhaPlant::new(pos.x as usize + MARIO_SPRITE_BLOCK_SIZE / 2, pos.y as usize + MARIO_SPRITE_BLOCK_SIZE);
+                ObjectReference::Entity(self.entities.insert(Entity::PiranhaPlant(plant)))
+            }
+            ObjectType::Enemy(EnemyType::BulletBill) => {
+                // placed on its own it flies left, the way the player comes from
+                let bullet = BulletBill::new(pos.x as usize, pos.y as usize, -1.0);
+                ObjectReference::Entity(self.entities.insert(Entity::BulletBill(bullet)))
+            }
+            ObjectType::Enemy(EnemyType::Spiny) => {
+                let mut spiny = Spiny::new(pos.x as usize, pos.y as usize, 1.0);
+                spiny.object.gravity_scale = object.gravity_scale * self.gravity_scale;
+                ObjectReference::Entity(self.entities.insert(Entity::Spiny(spiny)))
+            }
+            ObjectType::Enemy(EnemyType::Lakitu) => {
+                ObjectReference::Entity(self.entities.insert(Entity::Lakitu(Lakitu::new(pos.x as usize, pos.y as usize))))
+            }
+            ObjectType::Powerup(powerup_type) => {
+                let mut powerup = PowerUp::new(pos.x as usize, pos.y as usize, powerup_type);
+                powerup.object.gravity_scale = object.gravity_scale * self.gravity_scale;
+                ObjectReference::Entity(self.entities.insert(Entity::PowerUp(powerup)))
+            }
+            ObjectType::Coin => ObjectReference::Coin(self.coins.insert(Coin::new(pos.x as usize, pos.y as usize))),
+            ObjectType::Player => ObjectReference::Player(0),
+            ObjectType::Block(_) | ObjectType::Fireball => unreachable!("rejected above"),
+        };
+        // an entity is registered where it ended up, a plant is in the pipe below the cell it was placed in
+        match reference {
+            ObjectReference::Entity(id) => self.grid.insert(Self::grid_cells(self.entities[id].object()), reference),
+            _ => self.grid.insert_at(x, y, reference),
+        }
+        debug!("Added {:?} at {}, {} as {:?}", object.object_type, pos.x, pos.y, reference);
+        Ok(())
+    }
+    pub(crate) fn add_block(&mut self, object: Object, texture_id: u8) -> Result<(), WorldError> {
+        let ObjectType::Block(block_type) = object.object_type else {
+            return Err(WorldError::WrongType(object.object_type));
+        };
+        let (x, y) = self.free_cell(&object)?;
+        let pos = object.pos;
+        match block_type {
+            BlockType::Block => {
+                self.blocks.push(Block::new_block(pos.x as usize, pos.y as usize, texture_id))
+            }
+            BlockType::PowerupBlock => {
+                self.blocks.push(Block::new_powerup_block(pos.x as usize, pos.y as usize, texture_id))
+            }
+            BlockType::Launcher => self.blocks.push(Block::new_launcher(pos.x as usize, pos.y as usize)),
+        }
+        self.grid.insert_at(x, y, ObjectReference::Block(self.blocks.len() - 1));
+        Ok(())
+    }
+
+    pub(crate) fn handle_input(&mut self, index: usize, input: &InputState) {
+        let (player, physics) = (&mut self.players[index], &self.physics);
+        player.running = input.run;
+        let acceleration = if input.run { physics.run_acceleration } else { physics.acceleration };
+        if input.right {
+            player
+                .add_horizontal_velocity(acceleration * physics.frame_time, physics);
+        }
+        if input.left {
+            player
+                .add_horizontal_velocity(-acceleration * physics.frame_time, physics);
+        }
+        player.jump(input, physics, &mut self.audio);
+        if input.run_pressed && matches!(player.power_state, PlayerState::Fire) {
+            self.throw_fireball(index);
+        }
+    }
+    // from in front of the player the way it faces, unless too many of theirs are still out
+    pub(crate) fn throw_fireball(&mut self, index: usize) {
+        let thrown = self.entities.values().filter_map(Entity::as_fireball).filter(|fireball| fireball.owner == index).count();
+        if thrown >= MAX_FIREBALLS {
+            return;
+        }
+        let player = &self.players[index];
+        let direction = player.facing();
+        let x = player.object.pos.x + direction * (MARIO_SPRITE_BLOCK_SIZE / 2) as f32;
+        let mut fireball = Fireball::new(x.max(0.0) as usize, player.object.pos.y as usize, direction, index);
+        fireball.object.gravity_scale *= self.gravity_scale;
+        let cells = Self::grid_cells(&fireball.object);
+        let id = self.entities.insert(Entity::Fireball(fireball));
+        self.grid.insert(cells, ObjectReference::Entity(id));
+    }
+    // spent ones and the ones the camera lost sight of
+    pub(crate) fn remove_fireballs(&mut self) {
+        let (left, right) = (self.camera.x as f32, (self.camera.x + self.camera.width) as f32);
+        let mut gone = std::mem::take(&mut self.entity_ids);
+        gone.clear();
+        gone.extend(self.entities.iter().filter_map(|(id, entity)| {
+            let fireball = entity.as_fireball()?;
+            let hitbox = fireball.object.hitbox();
+            (fireball.spent || hitbox.right() < left || hitbox.left() > right).then_some(id)
+        }));
+        for &id in &gone {
+            if let Some(fireball) = self.entities.remove(id) {
+                self.grid.remove(Self::grid_cells(fireball.object()), ObjectReference::Entity(id));
+            }
+        }
+        self.entity_ids = gone;
+    }
+    // bullets a screen past the camera on either side, they would fly on through the whole level
+    pub(crate) fn remove_stray_bullets(&mut self) {
+        let width = self.camera.width as f32;
+        let (left, right) = (self.camera.x as f32 - width, (self.camera.x + self.camera.width) as f32 + width);
+        let mut gone = std::mem::take(&mut self.entity_ids);
+        gone.clear();
+        gone.extend(self.entities.iter().filter_map(|(id, entity)| {
+            let Entity::BulletBill(bullet) = entity else {
+                return None;
+            };
+            let hitbox = bullet.object.hitbox();
+            (hitbox.right() < left || hitbox.left() > right).then_some(id)
+        }));
+        for &id in &gone {
+            if let Some(bullet) = self.entities.remove(id) {
+                self.grid.remove(Self::grid_cells(bullet.object()), ObjectReference::Entity(id));
+            }
+        }
+        self.entity_ids = gone;
+    }
+    // one physics frame driven by the given input, the game loop and headless callers both go through here
+    pub fn step(&mut self, input: InputState) {
+        self.step_players([input, InputState::default()]);
+    }
+    // the same for co-op, one input per player; player two's is ignored without one
+    pub fn step_players(&mut self, inputs: [InputState; MAX_PLAYERS]) {
+        self.update(&inputs);
+    }
+    pub fn save_state(&self) -> WorldState {
+        let grid = self.grid.occupied().collect();
+        WorldState {
+            grid,
+            players: self.players.clone(),
+            entities: self.entities.clone(),
+            coins: self.coins.clone(),
+            blocks: self.blocks.clone(),
+            score: self.score.clone(),
+            spawning_objects: self.spawning_objects.iter().map(SpawningObject::clone_spawning).collect(),
+            previously_bumped_blocks: self.previously_bumped_blocks.clone(),
+            camera: self.camera.clone(),
+            game_state: self.game_state.clone(),
+            time_left_frames: self.time_left_frames,
+            current_room: self.current_room,
+        }
+    }
+    // a state saved in the same level, the level itself (tiles, rooms, textures) isn't part of it
+    pub fn load_state(&mut self, state: WorldState) {
+        self.grid.clear();
+        self.player_cells = Default::default();
+        // the players are put back from their restored positions, below
+        for (x, y, reference) in state.grid.into_iter().filter(|(_, _, reference)| !matches!(reference, ObjectReference::Player(_))) {
+            self.grid.insert_at(x, y, reference);
+        }
+        self.players = state.players;
+        self.entities = state.entities;
+        self.coins = state.coins;
+        self.blocks = state.blocks;
+        self.score = state.score;
+        self.spawning_objects = state.spawning_objects;
+        self.previously_bumped_blocks = state.previously_bumped_blocks;
+        self.camera = state.camera;
+        self.transition_to(state.game_state);
+        self.time_left_frames = state.time_left_frames;
+        self.current_room = state.current_room;
+        self.register_players();
+    }
+    // player one's
+    pub fn player_position(&self) -> Vec2 {
+        self.players[0].object.pos
+    }
+    pub fn player_velocity(&self) -> Vec2 {
+        self.players[0].velocity
+    }
+    pub fn player_state(&self) -> PlayerState {
+        self.players[0].power_state
+    }
+    pub fn game_state(&self) -> &GameState {
+        &self.game_state
+    }
+    // every enemy with its top left, in no particular order
+    pub fn enemies(&self) -> impl Iterator<Item = (EnemyType, Vec2)> + '_ {
+        self.entities.values().filter_map(|entity| match entity.object().object_type {
+            ObjectType::Enemy(enemy_type) => Some((enemy_type, entity.object().pos)),
+            _ => None,
+        })
+    }
+    pub fn collected_coins(&self) -> usize {
+        self.score.coins()
+    }
+    pub fn score(&self) -> &ScoreKeeper {
+        &self.score
+    }
+    pub fn physics(&self) -> &PhysicsConfig {
+        &self.physics
+    }
+    // for tuning while playing, takes effect from the next physics step
+    pub fn set_physics(&mut self, physics: PhysicsConfig) {
+        self.physics = physics;
+        for player in &mut self.players {
+            player.max_speed = physics.max_speed;
+        }
+    }
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+    pub fn entity_counts(&self) -> EntityCounts {
+        let enemies = self.entities.values().filter(|entity| entity.is_enemy()).count();
+        EntityCounts {
+            enemies,
+            powerups: self.entities.len() - enemies,
+            blocks: self.blocks.len(),
+            coins: self.coins.len(),
+            spawning: self.spawning_objects.len(),
+        }
+    }
+    // what's going on right now, for bug reports
+    pub fn log_summary(&self) {
+        info!("{:?}, {:?}, room {}, {} seconds left, score {}", self.game_state, self.entity_counts(), self.current_room, self.time_left(), self.score.score());
+        for (index, player) in self.players.iter().enumerate() {
+            info!(
+                "Player {} {:?} at {:.2}, {:.2} moving {:.2}, {:.2}, grounded {}, {:?}, star frames {}",
+                index + 1, player.power_state, player.object.pos.x, player.object.pos.y, player.velocity.x, player.velocity.y,
+                player.is_grounded, player.jump_state, player.star_frames
+            );
+        }
+        info!("Camera at {}, {} showing {} by {}, scrolls between {} and {}", self.camera.x, self.camera.y, self.camera.width, self.camera.height, self.camera.min_x, self.camera.max_x);
+    }
+    // what the clock shows
+    pub(crate) fn time_left(&self) -> u32 {
+        self.time_left_frames.div_ceil(TIME_UNIT_FRAMES)
+    }
+    pub(crate) fn hurry_up(&self) -> bool {
+        self.time_left() <= HURRY_UP_TIME
+    }
+    pub(crate) fn tick_timer(&mut self) {
+        let was_hurrying = self.hurry_up();
+        self.time_left_frames = self.time_left_frames.saturating_sub(1);
+        if !was_hurrying && self.hurry_up() {
+            self.audio.play_sfx(SfxId::Hurry);
+        }
+        if self.time_left_frames == 0 {
+            for player in &mut self.players {
+                player.power_state = PlayerState::Dead;
+                player.play_death(&self.physics);
+            }
+            self.game_over();
+        }
+    }
+    // a player who died in co-op sits out the rest of the level while the other plays on, a single player never does
+    pub(crate) fn is_out(&self, index: usize) -> bool {
+        self.players.len() > 1 && matches!(self.players[index].power_state, PlayerState::Dead)
+    }
+    pub(crate) fn player_died(&mut self, index: usize) {
+        // fallen into a pit there is nothing left to see
+        if self.players[index].object.pos.y < self.height as f32 {
+            self.players[index].play_death(&self.physics);
+        }
+        if self.players.len() > 1 {
+            self.players[index].power_state = PlayerState::Dead;
+            if (0..self.players.len()).any(|other| !self.is_out(other)) {
+                info!("Player {} is out", index + 1);
+                self.audio.play_sfx(SfxId::Death);
+                return;
+            }
+        }
+        self.game_over();
+    }
+    pub(crate) fn in_current_room(&self, object: &Object) -> bool {
+        let room = &self.rooms[self.current_room];
+        (room.x as f32..(room.x + room.width) as f32).contains(&object.pos.x)
+    }
+    pub(crate) fn room_bounds(&self) -> WorldBounds {
+        let room = &self.rooms[self.current_room];
+        WorldBounds { min_x: room.x, max_x: room.x + room.width, max_y: self.height, goal_x: None }
+    }
+    // the first warp a player overlaps with its trigger held moves it into that warp's room, in co-op the other comes along
+    pub(crate) fn take_warp(&mut self, inputs: &[InputState; MAX_PLAYERS]) {
+        let triggered_warp = |player: &Player, input: &InputState| {
+            let hitbox = player.object.hitbox();
+            self.warps.iter().find(|warp| {
+                let triggered = match warp.trigger {
+                    WarpTrigger::Down => input.down && player.is_grounded,
+                    WarpTrigger::Right => input.right,
+                };
+                triggered && hitbox.overlaps(&Rect::new(warp.x as f32, warp.y as f32, warp.width as f32, warp.height as f32))
+            })
+        };
+        let warp = (0..self.players.len())
+            .filter(|index| !self.is_out(*index))
+            .find_map(|index| triggered_warp(&self.players[index], &inputs[index]));
+        let Some(warp) = warp.cloned() else {
+            return;
+        };
+        for player in &mut self.players {
+            player.object.pos = Vec2::new(warp.to_x as f32, warp.to_y as f32);
+            player.velocity = Vec2::ZERO;
+        }
+        self.current_room = warp.room;
+        self.camera.enter_room(&self.rooms[warp.room], warp.to_x);
+        self.audio.play_sfx(SfxId::Pipe);
+    }
+    // fingerprint of player one's state, replays compare it to find where a playback diverged
+    pub fn checksum(&self) -> u64 {
+        let player = &self.players[0];
+        let mut hash = FNV_OFFSET_BASIS;
+        for value in [player.object.pos.x, player.object.pos.y, player.velocity.x, player.velocity.y] {
+            hash = fnv1a(hash, &value.to_bits().to_le_bytes());
+        }
+        hash
+    }
+    // fingerprint of everything that moves or counts: the players, every entity, the coins, the clock and the state.
+    // checksum stays player one only, so recorded replays keep verifying
+    pub fn state_hash(&self) -> u64 {
+        let mut hash = self.checksum();
+        // player two, without one a single player run hashes as it always did
+        for player in &self.players[1..] {
+            for value in [player.object.pos.x, player.object.pos.y, player.velocity.x, player.velocity.y] {
+                hash = fnv1a(hash, &value.to_bits().to_le_bytes());
+            }
+            hash = fnv1a(hash, format!("{:?}", player.power_state).as_bytes());
+        }
+        for entity in self.entities.values() {
+            let entity = entity.updatable();
+            for value in [entity.object().pos.x, entity.object().pos.y, entity.velocity().x, entity.velocity().y] {
+                hash = fnv1a(hash, &value.to_bits().to_le_bytes());
+            }
+        }
+        for count in [self.entities.len(), self.coins.len(), self.score.coins(), self.score.score() as usize, self.time_left_frames as usize, self.current_room] {
+            hash = fnv1a(hash, &(count as u64).to_le_bytes());
+        }
+        fnv1a(hash, format!("{:?} {:?}", self.game_state, self.players[0].power_state).as_bytes())
+    }
+    // all grid cells covered by the object, anchored at its rounded position
+    pub(crate) fn grid_cells(object: &Object) -> GridCells {
+        GridCells {
+            x: (object.pos.x / MARIO_SPRITE_BLOCK_SIZE as f32).round() as usize,
+            y: (object.pos.y / MARIO_SPRITE_BLOCK_SIZE as f32).round() as usize,
+            wide: object.width.div_ceil(MARIO_SPRITE_BLOCK_SIZE).max(1),
+            high: object.height.div_ceil(MARIO_SPRITE_BLOCK_SIZE).max(1),
+        }
+    }
+    // inside the grid and not overlapping a block
+    pub(crate) fn is_free(&self, object: &Object) -> bool {
+        Self::grid_cells(object).iter().all(|(x, y)| {
+            self.grid
+                .get(x, y)
+                .is_some_and(|cell| !cell.iter().any(|reference| matches!(reference, ObjectReference::Block(_))))
+        })
+    }
+    pub(crate) fn get_surrounding_objects(
+        grid: &Grid,
+        entities: &SlotMap<EntityId, Entity>,
+        coins: &SlotMap<CoinId, Coin>,
+        blocks: &[Block],
+        object: &Object,
+        own_reference: &ObjectReference,
+        surrounding_objects: &mut Vec<SurroundingObject>,
+    ) {
+        surrounding_objects.clear();
+        // ring of cells around every cell the object covers, so a Big player sees what is next to its head too
+        let cells = Self::grid_cells(object);
+        let (min_x, min_y) = (cells.x as isize, cells.y as isize);
+        let (max_x, max_y) = (min_x + cells.wide as isize - 1, min_y + cells.high as isize - 1);
+
+        for new_y in min_y - 1..=max_y + 1 {
+            for new_x in min_x - 1..=max_x + 1 {
+                let relative_direction = (
+                    if new_y < min_y { -1 } else if new_y > max_y { 1 } else { 0 },
+                    if new_x < min_x { -1 } else if new_x > max_x { 1 } else { 0 },
+                );
+                if relative_direction == (0, 0) {
+                    continue;
+                }
+                if new_y < 0 || new_x < 0 {
+                    continue;
+                }
+                let Some(cell) = grid.get(new_x as usize, new_y as usize) else {
+                    continue;
+                };
+                for &reference in cell {
+                    if reference == *own_reference || surrounding_objects.iter().any(|seen| seen.reference == reference) {
+                        continue; // multi-cell objects are only reported once
+                    }
+                    let surrounding_object = match reference {
+                        ObjectReference::Block(index) => blocks.get(index).map(|block| block.object),
+                        ObjectReference::Entity(id) => entities.get(id).map(|entity| *entity.object()),
+                        ObjectReference::Coin(id) => coins.get(id).map(|coin| coin.object),
+                        ObjectReference::Player(_) => None,
+                        ObjectReference::None => None,
+                    };
+                    if let Some(surrounding_object) = surrounding_object {
+                        surrounding_objects.push(SurroundingObject::new(surrounding_object, reference, relative_direction));
+                    }
+                }
+            }
+        }
+    }
+    // first solid block along the ray, walking the grid cell by cell (DDA); a ray starting inside a block hits at distance 0.
+    // one going exactly through the corner between four cells crosses over diagonally, it only grazes the two beside it
+    pub(crate) fn raycast(&self, origin: Vec2, dir: Vec2, max_dist: f32) -> Option<RayHit> {
+        let dir = dir.normalize_or_zero();
+        if dir == Vec2::ZERO {
+            return None;
+        }
+        let block_size = MARIO_SPRITE_BLOCK_SIZE as f32;
+        let mut cell_x = (origin.x / block_size).floor() as isize;
+        let mut cell_y = (origin.y / block_size).floor() as isize;
+        let step_x = dir.x.signum() as isize;
+        let step_y = dir.y.signum() as isize;
+        let t_delta_x = if dir.x != 0.0 { block_size / dir.x.abs() } else { f32::INFINITY };
+        let t_delta_y = if dir.y != 0.0 { block_size / dir.y.abs() } else { f32::INFINITY };
+        let mut t_max_x = if dir.x > 0.0 {
+            ((cell_x + 1) as f32 * block_size - origin.x) / dir.x
+        } else if dir.x < 0.0 {
+            (origin.x - cell_x as f32 * block_size) / -dir.x
+        } else {
+            f32::INFINITY
+        };
+        let mut t_max_y = if dir.y > 0.0 {
+            ((cell_y + 1) as f32 * block_size - origin.y) / dir.y
+        } else if dir.y < 0.0 {
+            (origin.y - cell_y as f32 * block_size) / -dir.y
+        } else {
+            f32::INFINITY
+        };
+
+        let mut distance = 0.0;
+        while distance <= max_dist {
+            if cell_y >= 0 && cell_x >= 0 {
+                let block = self
+                    .grid
+                    .get(cell_x as usize, cell_y as usize)
+                    .and_then(|cell| cell.iter().find(|reference| matches!(reference, ObjectReference::Block(_))));
+                if let Some(reference) = block {
+                    return Some(RayHit { reference: *reference, distance });
+                }
+            }
+            distance = t_max_x.min(t_max_y);
+            if t_max_x == distance {
+                t_max_x += t_delta_x;
+                cell_x += step_x;
+            }
+            if t_max_y == distance {
+                t_max_y += t_delta_y;
+                cell_y += step_y;
+            }
+        }
+        None
+    }
+    // every distinct reference in the grid cells overlapped by the rect
+    pub(crate) fn query_region(&self, rect: Rect) -> Vec<ObjectReference> {
+        let block_size = MARIO_SPRITE_BLOCK_SIZE as f32;
+        let min_x = (rect.left() / block_size).floor().max(0.0) as usize;
+        let min_y = (rect.top() / block_size).floor().max(0.0) as usize;
+        let max_x = (rect.right() / block_size).ceil().max(0.0) as usize;
+        let max_y = (rect.bottom() / block_size).ceil().max(0.0) as usize;
+        let mut references = Vec::new();
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                for reference in self.grid.get(x, y).unwrap_or_default() {
+                    if !references.contains(reference) {
+                        references.push(*reference);
+                    }
+                }
+            }
+        }
+        references
+    }
+    pub(crate) fn object_of(&self, reference: ObjectReference) -> Option<Object> {
+        match reference {
+            ObjectReference::Block(index) => self.blocks.get(index).map(|block| block.object),
+            ObjectReference::Entity(id) => self.entities.get(id).map(|entity| *entity.object()),
+            ObjectReference::Coin(id) => self.coins.get(id).map(|coin| coin.object),
+            ObjectReference::Player(index) => self.players.get(index).map(|player| player.object),
+            ObjectReference::None => None,
+        }
+    }
+    // the object's own reference in a cell, not one of the others sharing it
+    fn reference_in_cell(&self, x: usize, y: usize, object: &Object) -> Option<ObjectReference> {
+        self.grid.get(x, y)?.iter().copied().find(|reference| self.object_of(*reference).as_ref() == Some(object))
+    }
+    // a walking enemy that ran into a player turns its back on them and takes a step, the others keep their course.
+    // which side the player is on counts, not how fast they were going, standing still included
+    fn walk_away_from(&mut self, id: EntityId, player_x: f32) {
+        let Some(enemy) = self.entities.get_mut(id).map(Entity::updatable_mut) else {
+            return;
+        };
+        let walking = matches!(
+            enemy.object().object_type,
+            ObjectType::Enemy(EnemyType::Goomba | EnemyType::Koopa(KoopaState::Walking) | EnemyType::Spiny)
+        );
+        let offset = enemy.object().hitbox().center().x - player_x;
+        if !walking || offset == 0.0 {
+            return; // right on top of each other there is no side to walk off to
+        }
+        let old_cells = Self::grid_cells(enemy.object());
+        enemy.mut_velocity().x = enemy.velocity().x.abs() * offset.signum();
+        let step = enemy.velocity().x;
+        enemy.mut_object().pos.x += step;
+        // moved like update_entities moves it, only its own references change cells
+        let new_cells = Self::grid_cells(enemy.object());
+        self.grid.remove(old_cells, ObjectReference::Entity(id));
+        self.grid.insert(new_cells, ObjectReference::Entity(id));
+    }
+    pub(crate) fn get_the_objects_reference(&self, object: &Object) -> Option<ObjectReference> {
+        let (x, y) = self.grid.grid_index(object.pos)?;
+        self.reference_in_cell(x, y, object)
+    }
+    pub(crate) fn transition_to(&mut self, state: GameState) {
+        let previous = std::mem::replace(&mut self.game_state, state);
+        // putting a menu or pause on top of a state or taking it off again neither leaves nor re-enters the state underneath
+        if self.game_state.overlaid() != Some(&previous) {
+            self.on_exit(&previous);
+        }
+        if previous.overlaid() != Some(&self.game_state) {
+            self.on_enter();
+        }
+    }
+    pub(crate) fn on_enter(&mut self) {
+        match self.game_state {
+            GameState::Dying(_) => {
+                self.audio.stop_music();
+                self.audio.play_sfx(SfxId::Death);
+            }
+            // no catching up on the time spent in the menu
+            GameState::Menu(_) | GameState::Paused(_) => self.elapsed_time = 0.0,
+            GameState::Playing | GameState::GameOver | GameState::GameWon => {}
+        }
+    }
+    pub(crate) fn on_exit(&mut self, state: &GameState) {
+        match state {
+            GameState::Menu(_) | GameState::Paused(_) => self.elapsed_time = 0.0,
+            GameState::Playing | GameState::Dying(_) | GameState::GameOver | GameState::GameWon => {}
+        }
+    }
+    pub fn set_menu_open(&mut self, open: bool) {
+        match (&self.game_state, open) {
+            (GameState::Menu(state), false) => self.transition_to((**state).clone()),
+            (GameState::Menu(_), true) | (_, false) => {}
+            (state, true) => self.transition_to(GameState::Menu(Box::new(state.clone()))),
+        }
+    }
+    // the menu goes on top of a pause, a pause toggled while the menu is open waits until it closes
+    #[cfg(feature = "tas")]
+    pub fn set_paused(&mut self, paused: bool) {
+        match (&self.game_state, paused) {
+            (GameState::Paused(state), false) => self.transition_to((**state).clone()),
+            (GameState::Paused(_) | GameState::Menu(_), _) | (_, false) => {}
+            (state, true) => self.transition_to(GameState::Paused(Box::new(state.clone()))),
+        }
+    }
+    // once per render frame: runs as many physics steps as the elapsed time is worth, the remainder carries over
+    pub fn frame(&mut self, dt: f32, driver: &mut impl StepDriver) {
+        self.audio.set_ducked(self.game_state.stands_still());
+        self.score.clear_awards();
+        self.frame_stats = FrameStats::default();
+        self.elapsed_time += dt;
+        let mut steps = 0;
+        // summed up frame times land a hair short of whole steps, without the slack 10 seconds at 144 fps would be 599 steps
+        while self.elapsed_time >= self.physics.frame_time - STEP_TIME_SLACK {
+            if steps == MAX_PHYSICS_STEPS_PER_FRAME {
+                self.elapsed_time %= self.physics.frame_time; // the backlog is dropped
+                break;
+            }
+            if !self.run_step(driver) {
+                self.elapsed_time = 0.0;
+                break;
+            }
+            self.elapsed_time -= self.physics.frame_time;
+            steps += 1;
+        }
+    }
+    // frame advance while paused, one step of the state under the pause
+    #[cfg(feature = "tas")]
+    pub fn advance(&mut self, driver: &mut impl StepDriver) {
+        let GameState::Paused(state) = &self.game_state else {
+            return;
+        };
+        // swapped without hooks, the pause stays on around the step
+        self.game_state = (**state).clone();
+        self.run_step(driver);
+        let state = std::mem::replace(&mut self.game_state, GameState::Playing);
+        self.game_state = GameState::Paused(Box::new(state));
+    }
+    // Dying goes on to game over once its time is up, one physics frame per call
+    pub(crate) fn count_down_state(&mut self) {
+        if let GameState::Dying(time_left) = &mut self.game_state {
+            *time_left -= self.physics.frame_time;
+            // the summed up frame times land a hair over zero on the step that should end it, like in frame
+            if *time_left <= STEP_TIME_SLACK {
+                self.transition_to(GameState::GameOver);
+            }
+        }
+    }
+    // one physics frame of the current state, timed states count down by it; false if the state doesn't use the time.
+    // headless playback calls it directly, frame by frame
+    pub fn run_step(&mut self, driver: &mut impl StepDriver) -> bool {
+        match &mut self.game_state {
+            // everything else stands still, only the dead player's hop plays
+            GameState::Dying(_) => {
+                for player in &mut self.players {
+                    player.animate.update(self.physics.frame_time);
+                }
+                self.count_down_state();
+                true
+            }
+            GameState::Playing => {
+                let input = driver.next_input();
+                let step_inputs = [input, self.second_input(driver)];
+                let stopwatch = Stopwatch::start();
+                self.step_players(step_inputs);
+                self.frame_stats.update_time += stopwatch.elapsed();
+                self.frame_stats.steps += 1;
+                driver.stepped(self, &input);
+                true
+            }
+            GameState::GameOver | GameState::GameWon | GameState::Menu(_) | GameState::Paused(_) => false,
+        }
+    }
+    // only read in co-op, so a driver without a second player never has to provide one
+    pub(crate) fn second_input(&self, driver: &mut impl StepDriver) -> InputState {
+        if self.players.len() > 1 {
+            driver.next_second_input()
+        } else {
+            InputState::default()
+        }
+    }
+    pub(crate) fn game_over(&mut self) {
+        if matches!(self.game_state, GameState::Dying(_) | GameState::GameOver) {
+            return; // dying and falling out in the same frame
+        }
+        self.transition_to(GameState::Dying(DYING_TIME));
+    }
+    pub(crate) fn process_events(&mut self) {
+        let mut events = std::mem::take(&mut self.events);
+        events.sort_by_key(|game_event| game_event.event.order()); // stable, same kind keeps the order it was raised in
+        for game_event in events.drain(..) {
+            self.handle_game_event(game_event);
+        }
+        self.events = events; // handed back empty, the next frame reuses its capacity
+    }
+    pub(crate) fn handle_game_event(&mut self, game_event: GameEvent) {
+        trace!("{:?} by {:?} on {:?}", game_event.event, game_event.triggered_by.object_type, game_event.target.map(|target| target.object_type));
+        self.score.handle_game_event(&game_event);
+        match game_event.event {
+            GameEventType::GameWon => {
+                if !matches!(self.game_state, GameState::Dying(_) | GameState::GameOver | GameState::GameWon) {
+                    // the flagpole and the clock are worth points too, the keeper can't tell from the event
+                    let flag_height = 1.0 - game_event.triggered_by.pos.y / self.height as f32;
+                    self.score.level_won(flag_height, self.time_left(), game_event.triggered_by.pos);
+                    self.transition_to(GameState::GameWon);
+                }
+            }
+            GameEventType::GameOver => {
+                self.player_died(game_event.player.unwrap_or(0));
+            }
+            GameEventType::Kill => {
+                // a stomp, a fireball or a shell targets the enemy, an enemy falling out of the world triggers its own kill without a target
+                let killed_by_player = matches!(game_event.triggered_by.object_type, ObjectType::Player | ObjectType::Fireball);
+                let killed_by_shell = game_event.triggered_by.object_type == ObjectType::Enemy(EnemyType::Koopa(KoopaState::Sliding));
+                let victim = game_event.target.unwrap_or(game_event.triggered_by);
+                if killed_by_shell {
+                    // the shell's kills are a chain like stomps, the keeper can't tell from the event how long it is
+                    let shell = game_event.source.and_then(|id| self.entities.get_mut(id)).and_then(Entity::as_koopa_mut);
+                    if let Some(shell) = shell {
+                        self.score.shell_kill(shell.kills, victim.pos);
+                        shell.kills += 1;
+                    }
+                }
+                // by its key, another enemy of the same kind in the same spot stays
+                if let Some(id) = game_event.entity.filter(|&id| self.entities.get(id).is_some_and(Entity::is_enemy)) {
+                    let enemy = self.entities.remove(id).expect("the enemy was just looked up");
+                    self.grid.remove(Self::grid_cells(enemy.object()), ObjectReference::Entity(id));
+                }
+                debug!("Removed killed {:?} at {}, {}", victim.object_type, victim.pos.x, victim.pos.y);
+                if killed_by_player || killed_by_shell {
+                    self.audio.play_sfx(SfxId::Stomp);
+                }
+            }
+            GameEventType::EnemyStomped => {
+                let (Some(target), Some(id)) = (game_event.target, game_event.entity) else {
+                    return;
+                };
+                // two players landing on it in the same frame, the second finds a shell and nothing to stomp
+                let koopa = self.entities.get_mut(id).and_then(Entity::as_koopa_mut).filter(|koopa| koopa.object.object_type == target.object_type);
+                if let Some(koopa) = koopa {
+                    koopa.hide_in_shell();
+                    self.audio.play_sfx(SfxId::Stomp);
+                }
+            }
+            GameEventType::ShellKicked => {
+                let (Some(target), Some(id)) = (game_event.target, game_event.entity) else {
+                    return;
+                };
+                // away from the player, whichever side they came from
+                let direction = (target.hitbox().center().x - game_event.triggered_by.hitbox().center().x).signum();
+                // kicked by two players in the same frame, it is already sliding for the second
+                let shell = self.entities.get_mut(id).and_then(Entity::as_koopa_mut).filter(|koopa| koopa.object.object_type == target.object_type);
+                if let Some(shell) = shell {
+                    shell.kick(direction);
+                    self.players[game_event.player.unwrap_or(0)].kick_grace_frames = KICK_GRACE_FRAMES;
+                    self.audio.play_sfx(SfxId::Stomp);
+                }
+            }
+            GameEventType::PlayerHit => { // handled here because it can lead to game over, so we will handle powerup state in general here
+                let index = game_event.player.unwrap_or(0);
+                if self.players[index].hit_invincible_frames > 0 {
+                    return; // a second enemy touched in the same frame as the first
+                }
+                if self.players[index].star_frames > 0 {
+                    self.handle_game_event(GameEvent {
+                        event: GameEventType::Kill,
+                        triggered_by: self.players[index].object,
+                        target: Some(game_event.triggered_by),
+                        entity: game_event.entity,
+                        coin: None,
+                        source: None,
+                        contact: None,
+                        player: game_event.player,
+                    });
+                    return;
+                }
+                self.players[index].power_down();
+                if let Some(id) = game_event.entity {
+                    self.walk_away_from(id, self.players[index].object.hitbox().center().x);
+                }
+                match self.players[index].power_state {
+                    PlayerState::Dead => {
+                        self.player_died(index);
+                    }
+                    _ => {
+                        // the game plays on, the player blinks and walks through enemies for a while
+                        self.players[index].hit_invincible_frames = HIT_INVINCIBLE_FRAMES;
+                        self.audio.play_sfx(SfxId::PowerDown);
+                    }
+                }
+            } 
+            GameEventType::PlayerPowerUp => {
+                let powerup_type = match game_event.target.map(|target| target.object_type) {
+                    Some(ObjectType::Powerup(powerup_type)) => powerup_type,
+                    _ => PowerupType::Mushroom,
+                };
+                // by its key, one still rising out of its block has none and is already gone from the spawning list
+                if let Some(id) = game_event.entity {
+                    // two players touching it in the same frame, the second finds nothing to collect
+                    let Some(powerup) = self.entities.remove(id) else {
+                        return;
+                    };
+                    self.grid.remove(Self::grid_cells(powerup.object()), ObjectReference::Entity(id));
+                    debug!("Removed collected powerup at {}, {}", powerup.object().pos.x, powerup.object().pos.y);
+                }
+                self.players[game_event.player.unwrap_or(0)].power_up(powerup_type);
+                self.audio.play_sfx(SfxId::Powerup);
+            }
+            GameEventType::EnemyCollEnemy => {
+                // goombas only raise this against powerups, they already turned around in their own update
+            }
+            GameEventType::CoinCollected => {
+                // by its key, like a powerup
+                if let Some(id) = game_event.coin {
+                    if let Some(coin) = self.coins.remove(id) {
+                        self.grid.remove(Self::grid_cells(&coin.object), ObjectReference::Coin(id));
+                        debug!("Removed collected coin at {}, {}", coin.object.pos.x, coin.object.pos.y);
+                    }
+                }
+                self.audio.play_sfx(SfxId::Coin);
+            }
+            GameEventType::PlayerHitPowerupBlock => {
+                if let Some(target) = game_event.target {
+                    if target.object_type == ObjectType::Block(BlockType::PowerupBlock) {
+                        if let Some(ObjectReference::Block(index)) = self.get_the_objects_reference(&target) {
+                            let block = &mut self.blocks[index];
+                            block.transform_into_regular_block();
+                            // it is a regular block from now on, keep the contact next frame from bumping it again
+                            self.bumped_blocks.push(index);
+                        }
+                        self.audio.play_sfx(SfxId::Bump);
+                        // a player who is big already gets the flower
+                        let powerup_type = match self.players[game_event.player.unwrap_or(0)].power_state {
+                            PlayerState::Big | PlayerState::Fire => PowerupType::FireFlower,
+                            _ => PowerupType::Mushroom,
+                        };
+                        self.spawn_powerup(Object::new(
+                            target.pos.x as usize,
+                            target.pos.y as usize - MARIO_SPRITE_BLOCK_SIZE,
+                            ObjectType::Powerup(powerup_type),
+                        ));
+                    }
+                }
+            }
+            GameEventType::PlayerHitBlock => {
+                if let Some(target) = game_event.target {
+                    if target.object_type == ObjectType::Block(BlockType::Block) {
+                        if let Some(ObjectReference::Block(index)) = self.get_the_objects_reference(&target) {
+                            let block = self.blocks[index].borrow_mut();
+                            let head_bump = game_event
+                                .contact
+                                .is_some_and(|contact| contact.is_head_bump(&game_event.triggered_by, &block.object));
+                            if !head_bump {
+                                return;
+                            }
+                            // the head stays in contact for a few frames, each of them raises this event
+                            self.bumped_blocks.push(index);
+                            if self.previously_bumped_blocks.contains(&index) {
+                                return;
+                            }
+                            self.audio.play_sfx(SfxId::Bump);
+                            let animation = PlayAnimationBuilder::new(block.animate.frames).pos_offset_frames(
+                                vec![Vec2::new(0.0, -2.0), Vec2::new(0.0, -4.0), Vec2::new(0.0, -6.0), Vec2::new(0.0, -8.0), Vec2::new(0.0, -6.0), Vec2::new(0.0, -4.0), Vec2::new(0.0, -2.0)]).build();
+                            block.animate.scale_animation_speed(2.0);
+                            block.animate.play_animation(animation);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    // runs after every enemy has moved, so both sides of a pair see the current positions
+    pub(crate) fn resolve_enemy_pairs(&mut self) {
+        let mut enemy_ids = std::mem::take(&mut self.entity_ids);
+        let mut neighbour_ids = std::mem::take(&mut self.neighbour_ids);
+        enemy_ids.clear();
+        // like the updates, only in the room the players are in
+        enemy_ids.extend(self.entities.iter().filter(|(_, entity)| entity.is_enemy() && self.in_current_room(entity.object())).map(|(id, _)| id));
+        for &first in &enemy_ids {
+            // the grid is the broadphase, a pair is found from the one with the lower id, in id order like the entities
+            neighbour_ids.clear();
+            let cells = Self::grid_cells(self.entities[first].object()).with_ring();
+            neighbour_ids.extend(cells.iter().filter_map(|(x, y)| self.grid.get(x, y)).flatten().filter_map(ObjectReference::entity_id));
+            neighbour_ids.retain(|&second| {
+                second > first && self.entities.get(second).is_some_and(|entity| entity.is_enemy() && self.in_current_room(entity.object()))
+            });
+            neighbour_ids.sort();
+            neighbour_ids.dedup();
+            for &second in &neighbour_ids {
+                let Some([enemy1, enemy2]) = self.entities.get_disjoint_mut([first, second]) else {
+                    continue;
+                };
+                let (enemy1, enemy2) = (enemy1.updatable_mut(), enemy2.updatable_mut());
+                if overlap_area(enemy1.object(), enemy2.object()) <= 0.0 {
+                    continue;
+                }
+                self.frame_stats.collision_pairs += 1;
+                let sliding = |enemy: &dyn Updatable| enemy.object().object_type == ObjectType::Enemy(EnemyType::Koopa(KoopaState::Sliding));
+                if sliding(enemy1) || sliding(enemy2) {
+                    // a sliding shell isn't pushed, it kills the other one and goes on. two shells take each other out
+                    for (shell, other, other_id) in [(&*enemy1, &*enemy2, second), (&*enemy2, &*enemy1, first)] {
+                        if !sliding(shell) {
+                            continue;
+                        }
+                        let other = Self::touching(shell.object(), *other.object(), ObjectReference::Entity(other_id));
+                        let response = shell.get_collision_handler(other.object.object_type).resolve(shell.object(), shell.velocity(), &other);
+                        if let Some(collision_type) = &response.collision_type {
+                            self.events.extend(shell.create_game_event(collision_type, &other, response.contact));
+                        }
+                    }
+                    continue;
+                }
+                // enemies walk past a plant like in the original, and nothing moves it out of its pipe. bullets and Lakitus fly on past
+                let fixed_course = |enemy: &dyn Updatable| {
+                    matches!(
+                        enemy.object().object_type,
+                        ObjectType::Enemy(EnemyType::PiranhaPlant(_) | EnemyType::BulletBill | EnemyType::Lakitu)
+                    )
+                };
+                if fixed_course(enemy1) || fixed_course(enemy2) {
+                    continue;
+                }
+                let (hitbox1, hitbox2) = (enemy1.object().hitbox(), enemy2.object().hitbox());
+                let x_overlap = hitbox1.right().min(hitbox2.right()) - hitbox1.left().max(hitbox2.left());
+                let push_direction = (hitbox1.center().x - hitbox2.center().x).signum();
+                let old_cells = (Self::grid_cells(enemy1.object()), Self::grid_cells(enemy2.object()));
+
+                // split the overlap and send both walking away from each other
+                enemy1.mut_object().pos.x += x_overlap / 2.0 * push_direction;
+                enemy2.mut_object().pos.x -= x_overlap / 2.0 * push_direction;
+                enemy1.mut_velocity().x = enemy1.velocity().x.abs() * push_direction;
+                enemy2.mut_velocity().x = -enemy2.velocity().x.abs() * push_direction;
+
+                let new_cells = (Self::grid_cells(enemy1.object()), Self::grid_cells(enemy2.object()));
+                self.grid.remove(old_cells.0, ObjectReference::Entity(first));
+                self.grid.remove(old_cells.1, ObjectReference::Entity(second));
+                self.grid.insert(new_cells.0, ObjectReference::Entity(first));
+                self.grid.insert(new_cells.1, ObjectReference::Entity(second));
+            }
+        }
+        self.entity_ids = enemy_ids;
+        self.neighbour_ids = neighbour_ids;
+    }
+    // an object found overlapping outside of the entity updates, as a diagonal neighbour: the push goes along the real
+    // center to center delta
+    pub(crate) fn touching(object: &Object, other: Object, reference: ObjectReference) -> SurroundingObject {
+        let delta = other.hitbox().center() - object.hitbox().center();
+        let relative_direction = (if delta.y < 0.0 { -1 } else { 1 }, if delta.x < 0.0 { -1 } else { 1 });
+        SurroundingObject::new(other, reference, relative_direction)
+    }
+    // like the enemy pairs, after every enemy has moved: a fireball's update only sees them where they were,
+    // a kill for that position would miss the enemy
+    pub(crate) fn resolve_fireball_hits(&mut self) {
+        let mut fireball_ids = std::mem::take(&mut self.entity_ids);
+        fireball_ids.clear();
+        fireball_ids.extend(self.entities.iter().filter(|(_, entity)| entity.as_fireball().is_some() && self.in_current_room(entity.object())).map(|(id, _)| id));
+        for &fireball_id in &fireball_ids {
+            let Some(Entity::Fireball(fireball)) = self.entities.get(fireball_id) else {
+                continue;
+            };
+            if fireball.spent {
+                continue;
+            }
+            // the first one it touches, it takes only one enemy with it
+            let hit = self
+                .entities
+                .iter()
+                .find(|(_, enemy)| enemy.is_enemy() && self.in_current_room(enemy.object()) && overlap_area(&fireball.object, enemy.object()) > 0.0);
+            let Some((enemy_id, enemy)) = hit else {
+                continue;
+            };
+            self.frame_stats.collision_pairs += 1;
+            let other = Self::touching(&fireball.object, *enemy.object(), ObjectReference::Entity(enemy_id));
+            let response = fireball.get_collision_handler(other.object.object_type).resolve(&fireball.object, &fireball.velocity, &other);
+            if let Some(collision_type) = &response.collision_type {
+                self.events.extend(fireball.create_game_event(collision_type, &other, response.contact));
+            }
+            if let Some(Entity::Fireball(fireball)) = self.entities.get_mut(fireball_id) {
+                fireball.update_position_and_velocity(&response);
+                fireball.spent = true;
+            }
+        }
+        self.entity_ids = fireball_ids;
+    }
+    pub(crate) fn update_spawning_objects(&mut self ) {
+        let mut completed_spawns = Vec::new();
+        for (index, spawning_object) in self.spawning_objects.iter_mut().enumerate() {
+            if spawning_object.update() {
+                completed_spawns.push(index);
+            }
+        }
+    
+        for index in completed_spawns.iter().rev() {
+            let object = *self.spawning_objects[*index].object.object();
+            if let ObjectType::Powerup(_) = object.object_type {
+                // a player standing where it came out collects it right away
+                let collector = (0..self.players.len())
+                    .filter(|index| self.has_player && !self.is_out(*index))
+                    .find(|index| overlap_area(&self.players[*index].object, &object) > 0.0);
+                if let Some(player) = collector {
+                    self.events.push(GameEvent {
+                        event: GameEventType::PlayerPowerUp,
+                        triggered_by: self.players[player].object,
+                        target: Some(object),
+                        entity: None,
+                        coin: None,
+                        source: None,
+                        contact: None,
+                        player: Some(player),
+                    });
+                } else if let Some(free) = self.free_spawn_spot(&object) {
+                    debug_expect_added(self.add_object(free));
+                } else {
+                    continue; // boxed in, it stays on top of the block and tries again next frame
+                }
+            }
+            self.spawning_objects.swap_remove(*index);
+        }
+    }
+    // its own cell, or one to the side if something (an enemy walking by) is in it
+    pub(crate) fn free_spawn_spot(&self, object: &Object) -> Option<Object> {
+        let tile = MARIO_SPRITE_BLOCK_SIZE as f32;
+        [0.0, tile, -tile].into_iter().find_map(|dx| {
+            let mut moved = *object;
+            moved.pos.x += dx;
+            (self.free_cell(&moved).is_ok() && self.is_free(&moved)).then_some(moved)
+        })
+    }
+    // the plants only see the players through the world, a player on or next to a pipe keeps its plant down
+    pub(crate) fn watch_pipes(&mut self) {
+        let players = if self.has_player { self.players.as_slice() } else { &[] };
+        for entity in self.entities.values_mut() {
+            if let Entity::PiranhaPlant(plant) = entity {
+                plant.player_near = players
+                    .iter()
+                    .any(|player| !matches!(player.power_state, PlayerState::Dead) && plant.kept_down_by(&player.object));
+            }
+        }
+    }
+    // launchers within about a screen of the camera fire at the nearest player's side once their cooldown is up.
+    // a bullet that would start inside a block is a shot missed, the launcher waits out another cooldown
+    pub(crate) fn fire_launchers(&mut self) {
+        let width = self.camera.width as f32;
+        let (left, right) = (self.camera.x as f32 - width, (self.camera.x + self.camera.width) as f32 + width);
+        for index in 0..self.blocks.len() {
+            // the other rooms stand still, their launchers too
+            if !self.in_current_room(&self.blocks[index].object) {
+                continue;
+            }
+            let block = &mut self.blocks[index];
+            if block.object.object_type != ObjectType::Block(BlockType::Launcher) {
+                continue;
+            }
+            block.cooldown_frames = block.cooldown_frames.saturating_sub(1);
+            let x = block.object.pos.x;
+            if block.cooldown_frames > 0 || !(left..=right).contains(&x) {
+                continue;
+            }
+            let target = (0..self.players.len())
+                .filter(|&player| self.has_player && !self.is_out(player))
+                .map(|player| self.players[player].object.pos.x)
+                .min_by(|a, b| (a - x).abs().total_cmp(&(b - x).abs()));
+            let Some(target) = target else {
+                continue;
+            };
+            let direction = if target < x { -1.0 } else { 1.0 };
+            let bullet_x = x + direction * MARIO_SPRITE_BLOCK_SIZE as f32;
+            let y = self.blocks[index].object.pos.y;
+            self.blocks[index].fire();
+            let bullet = BulletBill::new(bullet_x.max(0.0) as usize, y as usize, direction);
+            if !self.is_free(&bullet.object) {
+                continue;
+            }
+            let cells = Self::grid_cells(&bullet.object);
+            let id = self.entities.insert(Entity::BulletBill(bullet));
+            self.grid.insert(cells, ObjectReference::Entity(id));
+            self.audio.play_sfx(SfxId::Bump);
+        }
+    }
+    // Lakitus wait until the camera comes within a screen of them, from then on they keep over the middle of the view and
+    // throw a Spiny egg towards the nearest player every few seconds. a player under a ceiling is out of sight, the egg
+    // waits until they come out. a player past the level's lakitu_leaves_x sends them away
+    pub(crate) fn guide_lakitus(&mut self) {
+        let mut players = [None; MAX_PLAYERS];
+        for (index, player) in players.iter_mut().enumerate().take(self.players.len()) {
+            if self.has_player && !self.is_out(index) {
+                *player = Some(self.players[index].object.hitbox().center());
+            }
+        }
+        let leaves_x = self.meta.lakitu_leaves_x.unwrap_or(self.goal_x.saturating_sub(LAKITU_LEAVES_BEFORE_GOAL)) as f32;
+        let mut gone = std::mem::take(&mut self.entity_ids);
+        gone.clear();
+        if players.iter().flatten().any(|player| player.x > leaves_x) {
+            gone.extend(self.entities.iter().filter(|(_, entity)| matches!(entity, Entity::Lakitu(_))).map(|(id, _)| id));
+        }
+        for &id in &gone {
+            if let Some(lakitu) = self.entities.remove(id) {
+                self.grid.remove(Self::grid_cells(lakitu.object()), ObjectReference::Entity(id));
+                debug!("Lakitu at {} left", lakitu.object().pos.x);
+            }
+        }
+        // the ones in the room the players are in, the others wait there
+        let mut lakitus = gone;
+        lakitus.clear();
+        lakitus.extend(self.entities.iter().filter(|(_, entity)| matches!(entity, Entity::Lakitu(_)) && self.in_current_room(entity.object())).map(|(id, _)| id));
+        let mut spinies = self.entities.values().filter(|entity| matches!(entity, Entity::Spiny(_))).count();
+        let (camera_x, width) = (self.camera.x as f32, self.camera.width as f32);
+        let mut eggs = Vec::new();
+        for &id in &lakitus {
+            let Some(lakitu) = self.entities.get(id) else {
+                continue;
+            };
+            let eye = lakitu.object().hitbox().center();
+            let target = players.iter().flatten().min_by(|a, b| (a.x - eye.x).abs().total_cmp(&(b.x - eye.x).abs())).copied();
+            let blocked = target.and_then(|target| self.raycast(eye, target - eye, eye.distance(target)));
+            let Some(Entity::Lakitu(lakitu)) = self.entities.get_mut(id) else {
+                continue;
+            };
+            if lakitu.target_x.is_none() && lakitu.object.pos.x > camera_x + 2.0 * width {
+                continue;
+            }
+            lakitu.target_x = Some(camera_x + (width - lakitu.object.width as f32) / 2.0);
+            let was_waiting = lakitu.throw_frames > 0;
+            lakitu.throw_frames = lakitu.throw_frames.saturating_sub(1);
+            if lakitu.throw_frames > 0 {
+                continue;
+            }
+            let x = lakitu.object.pos.x;
+            if let Some(hit) = blocked {
+                if was_waiting {
+                    debug!("Lakitu at {} can't see the player past {:?} {} pixels away, it holds its egg", x, hit.reference, hit.distance);
+                }
+                continue;
+            }
+            lakitu.throw_frames = LAKITU_THROW_FRAMES;
+            if spinies >= MAX_SPINIES {
+                continue;
+            }
+            if let Some(target) = target {
+                let direction = if target.x < eye.x { -1.0 } else { 1.0 };
+                eggs.push(Spiny::new_egg(x as usize, lakitu.object.pos.y as usize, direction));
+                spinies += 1;
+            }
+        }
+        self.entity_ids = lakitus;
+        for mut egg in eggs {
+            if !self.is_free(&egg.object) {
+                continue;
+            }
+            egg.object.gravity_scale *= self.gravity_scale;
+            let cells = Self::grid_cells(&egg.object);
+            let id = self.entities.insert(Entity::Spiny(egg));
+            self.grid.insert(cells, ObjectReference::Entity(id));
+        }
+    }
+    // every entity sees the others where they were at the start of the frame, so the updates don't depend on each other
+    // and can run on all cores with the parallel feature. the results are applied one after the other in id order,
+    // the same with the feature as without
+    pub(crate) fn update_entities(&mut self) {
+        let mut updates = std::mem::take(&mut self.entity_updates);
+        let mut active = 0;
+        for (id, entity) in &self.entities {
+            if !self.in_current_room(entity.object()) {
+                continue;
+            }
+            if active == updates.len() {
+                updates.push(EntityUpdate::new(id));
+            }
+            updates[active].id = id;
+            active += 1;
+        }
+        let room_bounds = self.room_bounds();
+        let (grid, entities, coins, blocks, physics) = (&self.grid, &self.entities, &self.coins, &self.blocks, &self.physics);
+        let propose = |update: &mut EntityUpdate| {
+            let entity = &entities[update.id];
+            // the grid is the broadphase: neighbours are handed out as Object copies
+            Self::get_surrounding_objects(
+                grid,
+                entities,
+                coins,
+                blocks,
+                entity.object(),
+                &ObjectReference::Entity(update.id),
+                &mut update.surrounding_objects,
+            );
+            let mut proposed = entity.clone();
+            update.events.clear();
+            update.collision_pairs = proposed.update(&mut update.surrounding_objects, room_bounds, physics, &mut update.events);
+            update.proposed = Some(proposed);
+        };
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            updates[..active].par_iter_mut().for_each(propose);
+        }
+        #[cfg(not(feature = "parallel"))]
+        updates[..active].iter_mut().for_each(propose);
+
+        // only an entity's own references move, what else is in the cells it leaves or enters stays
+        for update in &updates[..active] {
+            self.grid.remove(Self::grid_cells(self.entities[update.id].object()), ObjectReference::Entity(update.id));
+        }
+        for update in &mut updates[..active] {
+            let Some(proposed) = update.proposed.take() else {
+                continue;
+            };
+            self.grid.insert(Self::grid_cells(proposed.object()), ObjectReference::Entity(update.id));
+            self.entities[update.id] = proposed;
+            for game_event in &mut update.events {
+                game_event.entity.get_or_insert(update.id);
+                game_event.source = Some(update.id);
+            }
+            self.events.append(&mut update.events);
+            self.frame_stats.collision_pairs += update.collision_pairs;
+        }
+        self.entity_updates = updates;
+    }
+
+    pub(crate) fn update(&mut self, inputs: &[InputState; MAX_PLAYERS]) {
+        if self.game_state != GameState::Playing {
+            return; // the other states only count down their timers, in frame
+        }
+        if self.has_player {
+            self.tick_timer();
+            if self.game_state != GameState::Playing {
+                return; // the time ran out
+            }
+            for (index, input) in inputs.iter().enumerate().take(self.players.len()) {
+                if !self.is_out(index) {
+                    self.handle_input(index, input);
+                }
+            }
+        }
+        self.update_spawning_objects();
+        self.watch_pipes();
+        self.fire_launchers();
+        self.guide_lakitus();
+        self.update_entities();
+        self.resolve_enemy_pairs();
+        self.resolve_fireball_hits();
+        self.remove_fireballs();
+        self.remove_stray_bullets();
+        for i in 0..self.blocks.len() {
+            let block = &mut self.blocks[i];
+            block.update(&self.physics);
+        }
+        for coin in self.coins.values_mut() {
+            coin.update(&self.physics);
+        }
+        if self.has_player {
+            for (index, input) in inputs.iter().enumerate().take(self.players.len()) {
+                if self.is_out(index) {
+                    // in co-op the other plays on through the hop
+                    self.players[index].animate.update(self.physics.frame_time);
+                    continue;
+                }
+                self.update_player(index, input);
+                if self.players[index].is_grounded {
+                    self.score.landed(index);
+                }
+            }
+        }
+        self.previously_bumped_blocks = std::mem::take(&mut self.bumped_blocks);
+        self.process_events();
+        // the game may have ended above, the grid and camera are still brought up to date for the frames after
+        if !self.game_state.has_ended() {
+            let star_power = (0..self.players.len()).any(|index| !self.is_out(index) && self.players[index].star_frames > 0);
+            let music = if star_power {
+                MusicId::Star
+            } else if self.hurry_up() {
+                self.music.hurried()
+            } else {
+                self.music
+            };
+            self.audio.play_music(music);
+            if self.has_player {
+                self.take_warp(inputs);
+            }
+        }
+        if !self.has_player {
+            return;
+        }
+        if self.players_in_game() > 1 {
+            self.carry_stuck_players();
+        }
+        self.register_players();
+
+        let target = self.camera_target();
+        // the camera stays in the level, a player falling into a pit drops out of the view
+        self.camera.update(
+            target.x as usize,
+            target.y.min(self.height as f32) as usize,
+        );
+    }
+    // moves every player's reference to the cells it covers now, once all that moves a player this frame
+    // (its update, the events, warps) is done. cells outside the level (falling out of it) are left out,
+    // and so is a player out of the game
+    pub(crate) fn register_players(&mut self) {
+        for index in 0..self.players.len() {
+            if let Some(cells) = self.player_cells[index].take() {
+                self.grid.remove(cells, ObjectReference::Player(index));
+            }
+            if self.has_player && !self.is_out(index) {
+                let cells = Self::grid_cells(&self.players[index].object);
+                self.grid.insert(cells, ObjectReference::Player(index));
+                self.player_cells[index] = Some(cells);
+            }
+        }
+    }
+    pub(crate) fn players_in_game(&self) -> usize {
+        (0..self.players.len()).filter(|index| !self.is_out(*index)).count()
+    }
+    // the middle of the players still in the game, a single player's own position
+    pub(crate) fn camera_target(&self) -> Vec2 {
+        let (sum, count) = (0..self.players.len())
+            .filter(|index| !self.is_out(*index))
+            .fold((Vec2::ZERO, 0.0), |(sum, count), index| (sum + self.players[index].object.pos, count + 1.0));
+        if count == 0.0 {
+            return self.players[0].object.pos;
+        }
+        sum / count
+    }
+    // in co-op the camera's left edge pushes a player left behind forward. one pushed into a wall that way would hold
+    // the camera back for good, it is carried over to the player furthest ahead instead
+    pub(crate) fn carry_stuck_players(&mut self) {
+        let in_game: Vec<usize> = (0..self.players.len()).filter(|index| !self.is_out(*index)).collect();
+        let Some(&leader) = in_game.iter().max_by(|a, b| self.players[**a].object.pos.x.total_cmp(&self.players[**b].object.pos.x)) else {
+            return;
+        };
+        for index in in_game {
+            let object = self.players[index].object;
+            if index == leader || object.pos.x > self.camera.x as f32 + 1.0 {
+                continue;
+            }
+            let stuck = self.query_region(object.hitbox()).into_iter().any(|reference| {
+                matches!(reference, ObjectReference::Block(block) if overlap_area(&object, &self.blocks[block].object) > 0.0)
+            });
+            if stuck {
+                info!("Carried player {} over to player {}", index + 1, leader + 1);
+                self.players[index].object.pos = self.players[leader].object.pos;
+                self.players[index].velocity = Vec2::ZERO;
+            }
+        }
+    }
+    // the player stays in the grid where it was, update moves it once the events are handled
+    pub(crate) fn update_player(&mut self, index: usize, input: &InputState) {
+        Self::get_surrounding_objects(
+            &self.grid,
+            &self.entities,
+            &self.coins,
+            &self.blocks,
+            &self.players[index].object,
+            &ObjectReference::Player(index),
+            &mut self.surrounding_objects,
+        );
+
+        let room = &self.rooms[self.current_room];
+        // the goal is at the right end of the level when there is no flagpole, the room's right edge still counts
+        let goal_in_room = self.goal_x > room.x && self.goal_x <= room.x + room.width;
+        let mut player_bounds = WorldBounds {
+            min_x: self.camera.x,
+            goal_x: goal_in_room.then_some(self.goal_x),
+            ..self.room_bounds()
+        };
+        // in co-op the one in front can't run out of the view, the camera waits for the other
+        if self.players_in_game() > 1 {
+            player_bounds.max_x = player_bounds.max_x.min(self.camera.x + self.camera.width);
+        }
+        let first_event = self.events.len();
+        self.frame_stats.collision_pairs += self.players[index]
+            .update(input, &mut self.surrounding_objects, player_bounds, &self.physics, &mut self.events);
+        for game_event in &mut self.events[first_event..] {
+            game_event.player = Some(index);
+        }
+    }
+
+    // a level sized render target, only the part the camera sees
+    pub(crate) fn draw_baked_layer(&self, texture: &Texture2D) {
+        draw_texture_ex(
+            texture,
+            0.0,
+            0.0,
+            WHITE,
+            DrawTextureParams {
+                source: Some(Rect::new(
+                    self.camera.x as f32,
+                    self.camera.y as f32,
+                    self.camera.width as f32,
+                    self.camera.height as f32,
+                )),
+                dest_size: Some(Vec2::new(
+                    (self.camera.width * SCALE_IMAGE_FACTOR) as f32,
+                    (self.camera.height * SCALE_IMAGE_FACTOR) as f32,
+                )),
+                flip_y: true,
+                ..Default::default()
+            },
+        );
+    }
+    pub fn draw(&self) {
+        match self.game_state.underlying() {
+            GameState::GameOver => {
+                draw_text(
+                    "Game Over",
+                    200.0 * SCALE_IMAGE_FACTOR as f32,
+                    150.0 * SCALE_IMAGE_FACTOR as f32,
+                    40.0,
+                    RED,
+                );
+            }
+            GameState::GameWon => {
+                draw_text(
+                    "You Won!",
+                    200.0 * SCALE_IMAGE_FACTOR as f32,
+                    150.0 * SCALE_IMAGE_FACTOR as f32,
+                    40.0,
+                    GREEN,
+                );
+            }
+            _ => {
+                if let Some(level_texture) = &self.level_texture {
+                    self.draw_baked_layer(level_texture);
+                }
+                for spawning_obj in &self.spawning_objects {
+                    spawning_obj.draw(&self.assets, self.camera.x, self.camera.y);
+                }
+                for block in &self.blocks {
+                    block.draw(&self.assets, self.camera.x, self.camera.y);
+                }
+                for coin in self.coins.values() {
+                    coin.draw(&self.assets, self.camera.x, self.camera.y);
+                }
+                for entity in self.entities.values() {
+                    entity.draw(&self.assets, self.camera.x, self.camera.y);
+                }
+                if self.has_player {
+                    for (index, player) in self.players.iter().enumerate() {
+                        // one out in co-op is still seen falling until the hop is over
+                        if !self.is_out(index) || player.animate.animation.is_some() {
+                            player.draw(&self.assets, self.camera.x, self.camera.y);
+                        }
+                    }
+                }
+                if let Some(foreground_texture) = &self.foreground_texture {
+                    self.draw_baked_layer(foreground_texture);
+                }
+                draw_text(&format!("Coins: {}", self.score.coins()), 10.0, 30.0, 20.0, WHITE);
+                draw_text(&format!("Score: {:06}", self.score.score()), 10.0, 50.0, 20.0, WHITE);
+                if !self.meta.name.is_empty() {
+                    let name_width = measure_text(&self.meta.name, None, 20, 1.0).width;
+                    draw_text(&self.meta.name, (screen_width() - name_width) / 2.0, 30.0, 20.0, WHITE);
+                }
+                draw_text(&format!("Time: {}", self.time_left()), screen_width() - 100.0, 30.0, 20.0, WHITE);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{PHYSICS_FRAME_TIME, PLAYER_HITBOX_WIDTH};
+
+    const TILE: usize = MARIO_SPRITE_BLOCK_SIZE;
+
+    // a floor along the bottom row and a wall at each end on the row above it
+    fn walled_floor(columns: usize, rows: usize) -> World {
+        let mut world = World::new_headless(rows * TILE, columns * TILE);
+        let mut tiles = vec![0; columns * rows];
+        tiles[columns * (rows - 1)..].fill(10);
+        tiles[columns * (rows - 2)] = 10;
+        tiles[columns * (rows - 1) - 1] = 10;
+        assert!(world.load_tiles(&tiles).is_empty());
+        world
+    }
+
+    // straight into the world, add_object would refuse a second enemy in the same cell
+    fn insert_goomba(world: &mut World, x: usize, y: usize) -> EntityId {
+        let goomba = Goomba::new(x, y, 2);
+        let cells = World::grid_cells(&goomba.object);
+        let id = world.entities.insert(Entity::Goomba(goomba));
+        world.grid.insert(cells, ObjectReference::Entity(id));
+        id
+    }
+
+    fn kill(world: &mut World, id: EntityId) {
+        let victim = *world.entities[id].object();
+        world.handle_game_event(GameEvent {
+            event: GameEventType::Kill,
+            triggered_by: world.players[0].object,
+            target: Some(victim),
+            entity: Some(id),
+            coin: None,
+            source: None,
+            contact: None,
+            player: Some(0),
+        });
+    }
+
+    // an empty 10 by 10 tile world with solid blocks in the given cells
+    fn blocks_at(cells: &[(usize, usize)]) -> World {
+        let mut world = World::new_headless(10 * TILE, 10 * TILE);
+        let mut tiles = vec![0; 100];
+        for &(x, y) in cells {
+            tiles[y * 10 + x] = 10;
+        }
+        assert!(world.load_tiles(&tiles).is_empty());
+        world
+    }
+
+    fn hit_cell(world: &World, hit: &RayHit) -> (usize, usize) {
+        let ObjectReference::Block(index) = hit.reference else {
+            panic!("{:?} is not a block", hit.reference);
+        };
+        let pos = world.blocks[index].object.pos;
+        (pos.x as usize / TILE, pos.y as usize / TILE)
+    }
+
+    fn spinies(world: &World) -> Vec<&Spiny> {
+        world
+            .entities
+            .values()
+            .filter_map(|entity| match entity {
+                Entity::Spiny(spiny) => Some(spiny),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn lakitu(world: &World) -> &Lakitu {
+        world
+            .entities
+            .values()
+            .find_map(|entity| match entity {
+                Entity::Lakitu(lakitu) => Some(lakitu),
+                _ => None,
+            })
+            .expect("a Lakitu")
+    }
+
+    fn referenced_entities(world: &World) -> Vec<EntityId> {
+        world.grid.occupied().filter_map(|(_, _, reference)| reference.entity_id()).collect()
+    }
+
+    #[test]
+    fn a_goomba_that_hits_a_player_standing_still_walks_away_from_them() {
+        let mut world = walled_floor(20, 6);
+        world.spawn_players(9 * TILE, 4 * TILE);
+        world.players[0].power_up(PowerupType::Mushroom);
+        let goomba = insert_goomba(&mut world, 4 * TILE, 4 * TILE);
+        assert!(world.entities[goomba].updatable().velocity().x > 0.0);
+        for _ in 0..120 {
+            world.step(InputState::default());
+            if world.players[0].power_state == PlayerState::Small {
+                break;
+            }
+        }
+        assert_eq!(world.players[0].power_state, PlayerState::Small);
+        assert_eq!(world.players[0].velocity.x, 0.0);
+        let goomba_object = *world.entities[goomba].object();
+        assert!(world.entities[goomba].updatable().velocity().x < 0.0);
+        // its references moved along with it
+        for (x, y) in World::grid_cells(&goomba_object).iter() {
+            assert!(world.grid.get(x, y).unwrap_or_default().contains(&ObjectReference::Entity(goomba)));
+        }
+        assert_eq!(referenced_entities(&world), World::grid_cells(&goomba_object).iter().map(|_| goomba).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn a_goomba_leaving_a_blocks_cell_leaves_the_block_there() {
+        let mut world = walled_floor(12, 6);
+        world.players[0].object.pos = Vec2::new(5.0 * TILE as f32, 0.0); // out of the way
+        let wall = (0, 4);
+        let wall_cell = world.grid.get(wall.0, wall.1).unwrap_or_default().to_vec();
+        // half in the wall's cell, walking off to the right
+        let goomba = insert_goomba(&mut world, 6, 4 * TILE);
+        assert!(world.grid.get(wall.0, wall.1).unwrap_or_default().contains(&ObjectReference::Entity(goomba)));
+        let mut came_back = false;
+        for _ in 0..600 {
+            world.step(InputState::default());
+            let goomba = &world.entities[goomba];
+            came_back |= goomba.updatable().velocity().x < 0.0;
+            if came_back {
+                // the wall still turns it around
+                assert!(goomba.object().pos.x >= TILE as f32 - 1.0, "{}", goomba.object().pos.x);
+            }
+        }
+        assert!(came_back);
+        assert_eq!(world.grid.get(wall.0, wall.1).unwrap_or_default(), wall_cell);
+    }
+
+    #[test]
+    fn overlapping_enemies_found_through_the_grid_are_pushed_apart() {
+        let mut world = walled_floor(12, 6);
+        let (left, right) = (insert_goomba(&mut world, 5 * TILE, 4 * TILE), insert_goomba(&mut world, 5 * TILE + 6, 4 * TILE));
+        world.resolve_enemy_pairs();
+        let (left, right) = (&world.entities[left], &world.entities[right]);
+        assert!(overlap_area(left.object(), right.object()) <= 0.0);
+        assert!(left.updatable().velocity().x < 0.0);
+        assert!(right.updatable().velocity().x > 0.0);
+        assert_eq!(world.frame_stats.collision_pairs, 1);
+    }
+
+    #[test]
+    fn launchers_and_enemies_in_another_room_stand_still() {
+        let mut world = walled_floor(40, 6);
+        world.rooms = vec![Room { name: "main".to_owned(), x: 0, width: 20 * TILE }, Room { name: "bonus".to_owned(), x: 20 * TILE, width: 20 * TILE }];
+        world.spawn_players(2 * TILE, 4 * TILE);
+        world.add_block(Object::new(30 * TILE, 4 * TILE, ObjectType::Block(BlockType::Launcher)), 0).unwrap();
+        let (first, second) = (insert_goomba(&mut world, 25 * TILE, 4 * TILE), insert_goomba(&mut world, 25 * TILE + 4, 4 * TILE));
+        for _ in 0..300 {
+            world.step(InputState::default());
+        }
+        assert_eq!(world.entities.len(), 2);
+        assert_eq!(world.entities[first].object().pos.x, (25 * TILE) as f32);
+        // overlapping, but not pushed apart either
+        assert_eq!(world.entities[second].object().pos.x, (25 * TILE + 4) as f32);
+    }
+
+    #[test]
+    fn walkers_stop_at_the_left_edge_of_a_room_that_does_not_start_at_zero() {
+        let walkers: [fn(usize, usize) -> Entity; 4] = [
+            |x, y| Entity::Goomba(Goomba::new(x, y, 2)),
+            |x, y| Entity::Koopa(Koopa::new(x, y)),
+            |x, y| Entity::Spiny(Spiny::new(x, y, -1.0)),
+            |x, y| Entity::PowerUp(PowerUp::new(x, y, PowerupType::Mushroom)),
+        ];
+        for walker in walkers {
+            let mut world = walled_floor(40, 6);
+            world.rooms = vec![Room { name: "main".to_owned(), x: 0, width: 20 * TILE }, Room { name: "bonus".to_owned(), x: 20 * TILE, width: 20 * TILE }];
+            world.current_room = 1;
+            world.spawn_players(30 * TILE, 4 * TILE);
+            // nothing solid at the room's edge, only the bounds keep it in
+            let mut entity = walker(21 * TILE, 4 * TILE);
+            entity.updatable_mut().mut_velocity().x = -1.0;
+            let cells = World::grid_cells(entity.object());
+            let id = world.entities.insert(entity);
+            world.grid.insert(cells, ObjectReference::Entity(id));
+            for _ in 0..120 {
+                world.step(InputState::default());
+                let x = world.entities[id].object().pos.x;
+                assert!(x >= (20 * TILE) as f32, "{:?} walked out of the room to {}", world.entities[id].object().object_type, x);
+            }
+        }
+    }
+
+    #[test]
+    fn a_kill_leaves_an_enemy_of_the_same_kind_in_the_same_spot() {
+        let mut world = walled_floor(10, 5);
+        let first = insert_goomba(&mut world, 4 * TILE, 3 * TILE);
+        let second = insert_goomba(&mut world, 4 * TILE, 3 * TILE);
+        assert_eq!(world.entities[first].object(), world.entities[second].object());
+        kill(&mut world, first);
+        assert!(!world.entities.contains_key(first));
+        assert!(world.entities.contains_key(second));
+        let referenced = referenced_entities(&world);
+        assert!(!referenced.contains(&first));
+        assert!(referenced.contains(&second));
+    }
+
+    #[test]
+    fn collecting_leaves_a_coin_or_powerup_of_the_same_kind_in_the_same_spot() {
+        let mut world = walled_floor(10, 5);
+        let coins = [(); 2].map(|_| world.coins.insert(Coin::new(4 * TILE, 3 * TILE)));
+        for &id in &coins {
+            world.grid.insert(World::grid_cells(&world.coins[id].object), ObjectReference::Coin(id));
+        }
+        let powerups = [(); 2].map(|_| {
+            let powerup = PowerUp::new(6 * TILE, 3 * TILE, PowerupType::Mushroom);
+            let cells = World::grid_cells(&powerup.object);
+            let id = world.entities.insert(Entity::PowerUp(powerup));
+            world.grid.insert(cells, ObjectReference::Entity(id));
+            id
+        });
+        for (event, entity, coin, target) in [
+            (GameEventType::CoinCollected, None, Some(coins[0]), world.coins[coins[0]].object),
+            (GameEventType::PlayerPowerUp, Some(powerups[0]), None, *world.entities[powerups[0]].object()),
+        ] {
+            world.handle_game_event(GameEvent {
+                event,
+                triggered_by: world.players[0].object,
+                target: Some(target),
+                entity,
+                coin,
+                source: None,
+                contact: None,
+                player: Some(0),
+            });
+        }
+        assert!(!world.coins.contains_key(coins[0]) && world.coins.contains_key(coins[1]));
+        assert!(!world.entities.contains_key(powerups[0]) && world.entities.contains_key(powerups[1]));
+        let referenced = referenced_entities(&world);
+        assert!(!referenced.contains(&powerups[0]) && referenced.contains(&powerups[1]));
+        let referenced: Vec<_> = world.grid.occupied().filter_map(|(_, _, reference)| reference.coin_id()).collect();
+        assert!(!referenced.contains(&coins[0]) && referenced.contains(&coins[1]));
+    }
+
+    #[test]
+    fn a_stomp_or_a_kick_leaves_a_koopa_in_the_same_spot_alone() {
+        let mut world = walled_floor(10, 5);
+        let koopas = [(); 2].map(|_| {
+            let koopa = Koopa::new(4 * TILE, 3 * TILE);
+            let cells = World::grid_cells(&koopa.object);
+            let id = world.entities.insert(Entity::Koopa(koopa));
+            world.grid.insert(cells, ObjectReference::Entity(id));
+            id
+        });
+        let state = |world: &World| koopas.map(|id| world.entities[id].object().object_type);
+        let walking = ObjectType::Enemy(EnemyType::Koopa(KoopaState::Walking));
+        for (event, after) in [(GameEventType::EnemyStomped, KoopaState::Shell), (GameEventType::ShellKicked, KoopaState::Sliding)] {
+            world.handle_game_event(GameEvent {
+                event,
+                triggered_by: world.players[0].object,
+                target: Some(*world.entities[koopas[1]].object()),
+                entity: Some(koopas[1]),
+                coin: None,
+                source: None,
+                contact: None,
+                player: Some(0),
+            });
+            // the first one would be found first by its looks
+            assert_eq!(state(&world), [walking, ObjectType::Enemy(EnemyType::Koopa(after))]);
+        }
+    }
+
+    #[test]
+    fn a_kill_counts_towards_the_shell_that_made_it() {
+        let mut world = walled_floor(10, 5);
+        let shells = [(); 2].map(|_| {
+            let mut koopa = Koopa::new(4 * TILE, 3 * TILE);
+            koopa.hide_in_shell();
+            koopa.kick(1.0);
+            world.entities.insert(Entity::Koopa(koopa))
+        });
+        let goomba = insert_goomba(&mut world, 6 * TILE, 3 * TILE);
+        world.handle_game_event(GameEvent {
+            event: GameEventType::Kill,
+            triggered_by: *world.entities[shells[1]].object(),
+            target: Some(*world.entities[goomba].object()),
+            entity: Some(goomba),
+            coin: None,
+            source: Some(shells[1]),
+            contact: None,
+            player: None,
+        });
+        // the first one looks the same and would be found first
+        let kills = shells.map(|id| world.entities[id].as_koopa_mut().unwrap().kills);
+        assert_eq!(kills, [0, 1]);
+    }
+
+    #[test]
+    fn the_others_keep_colliding_after_the_first_of_three_is_killed() {
+        let mut world = walled_floor(30, 5);
+        world.players[0].object.pos = Vec2::new(TILE as f32, 0.0); // out of the way
+        let goombas = [4, 12, 20].map(|column| insert_goomba(&mut world, column * TILE, 3 * TILE));
+        kill(&mut world, goombas[0]);
+        let mut turned = [false; 2]; // they start out walking right
+        for _ in 0..600 {
+            world.step(InputState::default());
+            // walking into each other or a wall turns them around, none walks through anything
+            let [second, third] = [goombas[1], goombas[2]].map(|id| world.entities[id].object().pos.x);
+            assert!(second < third, "{} walked through {}", second, third);
+            assert!(second >= TILE as f32 && third <= (28 * TILE) as f32);
+            for (index, id) in [goombas[1], goombas[2]].into_iter().enumerate() {
+                turned[index] |= world.entities[id].updatable().velocity().x < 0.0;
+            }
+        }
+        assert_eq!(turned, [true, true]);
+        let mut referenced = referenced_entities(&world);
+        referenced.sort();
+        referenced.dedup();
+        assert_eq!(referenced.len(), 2);
+        assert!(!referenced.contains(&goombas[0]));
+    }
+
+    #[test]
+    fn a_ray_stops_at_the_first_block_in_its_way() {
+        let world = blocks_at(&[(5, 2), (7, 2), (1, 2)]);
+        let hit = world.raycast(Vec2::new(40.0, 40.0), Vec2::new(1.0, 0.0), 1000.0).unwrap();
+        assert_eq!(hit_cell(&world, &hit), (5, 2));
+        assert_eq!(hit.distance, 40.0);
+        let hit = world.raycast(Vec2::new(40.0, 40.0), Vec2::new(-3.0, 0.0), 1000.0).unwrap();
+        assert_eq!(hit_cell(&world, &hit), (1, 2));
+        assert_eq!(hit.distance, 8.0);
+    }
+
+    #[test]
+    fn a_ray_with_nothing_in_its_way_misses() {
+        let world = blocks_at(&[(5, 2)]);
+        assert!(world.raycast(Vec2::new(40.0, 40.0), Vec2::new(0.0, -1.0), 1000.0).is_none());
+        assert!(world.raycast(Vec2::new(40.0, 40.0), Vec2::new(0.0, 1.0), 1000.0).is_none());
+        assert!(world.raycast(Vec2::new(40.0, 40.0), Vec2::ZERO, 1000.0).is_none());
+    }
+
+    #[test]
+    fn a_diagonal_ray_hits_the_block_on_its_diagonal() {
+        let world = blocks_at(&[(3, 3)]);
+        let hit = world.raycast(Vec2::new(8.0, 8.0), Vec2::new(1.0, 1.0), 1000.0).unwrap();
+        assert_eq!(hit_cell(&world, &hit), (3, 3));
+        assert!((hit.distance - 40.0 * 2f32.sqrt()).abs() < 1e-3);
+        // flatter it passes over the block
+        assert!(world.raycast(Vec2::new(8.0, 8.0), Vec2::new(1.0, 0.5), 1000.0).is_none());
+    }
+
+    #[test]
+    fn a_ray_through_a_corner_grazes_the_blocks_beside_it() {
+        // blocks right of and below the corner at (32, 32), the ray crosses it from the top left
+        let world = blocks_at(&[(2, 1), (1, 2)]);
+        assert!(world.raycast(Vec2::new(24.0, 24.0), Vec2::new(1.0, 1.0), 1000.0).is_none());
+        // a little off the corner it runs into one of them
+        let hit = world.raycast(Vec2::new(24.0, 23.0), Vec2::new(1.0, 1.0), 1000.0).unwrap();
+        assert_eq!(hit_cell(&world, &hit), (2, 1));
+        let hit = world.raycast(Vec2::new(23.0, 24.0), Vec2::new(1.0, 1.0), 1000.0).unwrap();
+        assert_eq!(hit_cell(&world, &hit), (1, 2));
+    }
+
+    #[test]
+    fn a_ray_starting_inside_a_block_hits_it_right_away() {
+        let world = blocks_at(&[(2, 2), (5, 2)]);
+        let hit = world.raycast(Vec2::new(40.0, 40.0), Vec2::new(1.0, 0.0), 1000.0).unwrap();
+        assert_eq!(hit_cell(&world, &hit), (2, 2));
+        assert_eq!(hit.distance, 0.0);
+    }
+
+    #[test]
+    fn a_ray_ends_at_its_max_distance() {
+        let world = blocks_at(&[(5, 2)]);
+        assert!(world.raycast(Vec2::new(40.0, 40.0), Vec2::new(1.0, 0.0), 39.0).is_none());
+        let hit = world.raycast(Vec2::new(40.0, 40.0), Vec2::new(1.0, 0.0), 40.0).unwrap();
+        assert_eq!(hit_cell(&world, &hit), (5, 2));
+    }
+
+    #[test]
+    fn a_spiny_egg_hatches_once_it_lands_and_walks_the_way_it_was_thrown() {
+        let mut world = walled_floor(20, 6);
+        world.spawn_players(17 * TILE, 4 * TILE);
+        let egg = Spiny::new_egg(8 * TILE, TILE, -1.0);
+        let cells = World::grid_cells(&egg.object);
+        let id = world.entities.insert(Entity::Spiny(egg));
+        world.grid.insert(cells, ObjectReference::Entity(id));
+        let Entity::Spiny(egg) = &world.entities[id] else { unreachable!() };
+        assert!(egg.velocity.y < 0.0);
+        let mut frames_in_the_air = 0;
+        while !spinies(&world)[0].is_grounded {
+            assert!(!spinies(&world)[0].hatched);
+            world.step(InputState::default());
+            frames_in_the_air += 1;
+            assert!(frames_in_the_air < 120, "the egg never landed");
+        }
+        let spiny = spinies(&world)[0];
+        assert!(spiny.hatched);
+        assert_eq!(spiny.velocity.x, -1.0);
+        let x = spiny.object.pos.x;
+        for _ in 0..10 {
+            world.step(InputState::default());
+        }
+        let spiny = spinies(&world)[0];
+        assert!(spiny.hatched);
+        assert!(spiny.velocity.x < 0.0);
+        assert!(spiny.object.pos.x < x - 9.0, "{} walked from {}", spiny.object.pos.x, x);
+    }
+
+    #[test]
+    fn a_lakitu_waits_for_the_camera_then_keeps_over_the_middle_of_the_view() {
+        let mut world = walled_floor(120, 6);
+        world.spawn_players(2 * TILE, 4 * TILE);
+        world.add_object(Object::new(90 * TILE, TILE, ObjectType::Enemy(EnemyType::Lakitu))).unwrap();
+        world.step(InputState::default());
+        // further than two screens from the camera it doesn't move
+        assert_eq!(lakitu(&world).target_x, None);
+        assert_eq!(lakitu(&world).object.pos.x, (90 * TILE) as f32);
+
+        let mut world = walled_floor(120, 6);
+        world.spawn_players(2 * TILE, 4 * TILE);
+        world.add_object(Object::new(50 * TILE, TILE, ObjectType::Enemy(EnemyType::Lakitu))).unwrap();
+        let middle = world.camera.x as f32 + (world.camera.width - TILE) as f32 / 2.0;
+        let mut x = lakitu(&world).object.pos.x;
+        for _ in 0..200 {
+            world.step(InputState::default());
+            let lakitu = lakitu(&world);
+            assert_eq!(lakitu.target_x, Some(middle));
+            // no faster than LAKITU_SPEED, never past the middle
+            assert!(lakitu.object.pos.x <= x);
+            assert!(x - lakitu.object.pos.x <= crate::config::LAKITU_SPEED);
+            assert!(lakitu.object.pos.x >= middle);
+            x = lakitu.object.pos.x;
+        }
+        assert!(x - middle < 0.5, "{} stopped short of {}", x, middle);
+    }
+
+    #[test]
+    fn a_lakitu_throws_an_egg_towards_the_player_every_few_seconds_up_to_the_limit() {
+        // the player stands on a ledge, the Spinies walk on under it
+        let mut world = walled_floor(120, 8);
+        let ledge: Vec<u8> = (0..120 * 8).map(|index| if index % 120 < 5 && index / 120 == 5 { 10 } else { 0 }).collect();
+        assert!(world.load_tiles(&ledge).is_empty());
+        world.spawn_players(2 * TILE, 4 * TILE);
+        world.add_object(Object::new(20 * TILE, 0, ObjectType::Enemy(EnemyType::Lakitu))).unwrap();
+        for _ in 1..LAKITU_THROW_FRAMES {
+            world.step(InputState::default());
+        }
+        assert!(spinies(&world).is_empty());
+        world.step(InputState::default());
+        let eggs = spinies(&world);
+        assert_eq!(eggs.len(), 1);
+        // the player is on the left
+        assert!(!eggs[0].hatched);
+        assert!(eggs[0].velocity.x < 0.0);
+        assert_eq!(eggs[0].direction, -1.0);
+        for _ in 0..(MAX_SPINIES as u32 + 2) * LAKITU_THROW_FRAMES {
+            world.step(InputState::default());
+        }
+        assert_eq!(spinies(&world).len(), MAX_SPINIES);
+    }
+
+    #[test]
+    fn a_jump_rising_with_the_key_held_floats_on_lighter_gravity() {
+        let mut world = walled_floor(20, 6);
+        world.spawn_players(2 * TILE, TILE);
+        let physics = world.physics;
+        let gravity = physics.gravity * physics.frame_time;
+        let player = &mut world.players[0];
+        for (boosting, vy, expected) in [
+            (true, -2.0, -2.0 + gravity * physics.rising_gravity_factor),
+            (false, -2.0, -2.0 + gravity),
+            // on the way down it is the full gravity, held or not
+            (true, 1.0, 1.0 + gravity),
+        ] {
+            player.boosting = boosting;
+            player.velocity.y = vy;
+            player.apply_gravity(&physics);
+            assert!((player.velocity.y - expected).abs() < 1e-5, "boosting {} from {}: {}", boosting, vy, player.velocity.y);
+        }
+        player.boosting = false;
+        player.velocity.y = physics.max_fall_speed - gravity / 2.0;
+        player.apply_gravity(&physics);
+        assert_eq!(player.velocity.y, physics.max_fall_speed);
+    }
+
+    #[test]
+    fn going_down_a_pipe_plays_the_pipe_sound() {
+        let mut world = walled_floor(40, 6);
+        world.spawn_players(2 * TILE, 4 * TILE);
+        world.rooms = vec![
+            Room { name: "main".to_owned(), x: 0, width: 20 * TILE },
+            Room { name: "bonus".to_owned(), x: 20 * TILE, width: 20 * TILE },
+        ];
+        world.warps = vec![Warp {
+            x: 2 * TILE,
+            y: 4 * TILE,
+            width: TILE,
+            height: TILE,
+            trigger: WarpTrigger::Down,
+            room: 1,
+            to_x: 22 * TILE,
+            to_y: 4 * TILE,
+        }];
+        for _ in 0..10 {
+            world.step(InputState::default());
+        }
+        world.step(InputState { down: true, ..InputState::default() });
+        assert_eq!(world.current_room, 1);
+        assert!(world.audio.played_sfx().contains(&SfxId::Pipe));
+        assert!(!world.audio.played_sfx().contains(&SfxId::PowerDown));
+    }
+
+    #[test]
+    fn a_player_straddling_two_ground_blocks_stands_still_and_runs_off_without_a_snag() {
+        let mut world = walled_floor(20, 6);
+        let start = Vec2::new((5 * TILE - TILE / 2) as f32, (4 * TILE) as f32);
+        world.spawn_players(start.x as usize, start.y as usize);
+        for _ in 0..30 {
+            world.step(InputState::default());
+        }
+        assert_eq!(world.players[0].object.pos, start);
+        assert_eq!(world.players[0].velocity, Vec2::ZERO);
+        assert!(world.players[0].is_grounded);
+        // across the seams the speed only builds up, and nothing lifts the player or pulls them down
+        let run = InputState { right: true, run: true, ..InputState::default() };
+        let mut speed = 0.0;
+        for _ in 0..60 {
+            world.step(run);
+            let player = &world.players[0];
+            assert_eq!(player.object.pos.y, start.y);
+            assert_eq!(player.velocity.y, 0.0);
+            assert!(player.velocity.x >= speed, "slowed from {} to {} at {}", speed, player.velocity.x, player.object.pos.x);
+            speed = player.velocity.x;
+        }
+        assert!(world.players[0].object.pos.x > start.x + 2.0 * TILE as f32, "only got to {}", world.players[0].object.pos.x);
+    }
+
+    // a player stands on a lone block at (5, 6) for a second with `overlap` pixels of its hitbox over the block's left or
+    // right edge; whether it is still on top of it
+    fn stays_on_the_block(overlap: f32, left_edge: bool, big: bool) -> bool {
+        let mut world = blocks_at(&[(5, 6)]);
+        world.spawn_players(0, 0);
+        if big {
+            world.players[0].power_up(PowerupType::Mushroom);
+        }
+        let player = &world.players[0].object;
+        let (inset, hitbox_width) = (player.hitbox().x - player.pos.x, player.hitbox().w);
+        let x = if left_edge {
+            (5 * TILE) as f32 + overlap - hitbox_width - inset
+        } else {
+            (6 * TILE) as f32 - overlap - inset
+        };
+        let y = (6 * TILE - player.height) as f32;
+        world.players[0].object.pos = Vec2::new(x, y);
+        for _ in 0..60 {
+            world.step(InputState::default());
+            let player = &world.players[0];
+            if player.object.pos != Vec2::new(x, y) {
+                return false;
+            }
+            assert!(player.is_grounded);
+        }
+        true
+    }
+
+    #[test]
+    fn a_player_half_on_a_ledge_stands_on_it() {
+        for big in [false, true] {
+            for left_edge in [true, false] {
+                let half = PLAYER_HITBOX_WIDTH / 2.0;
+                assert!(stays_on_the_block(half, left_edge, big), "big {} on the {} edge", big, if left_edge { "left" } else { "right" });
+            }
+        }
+    }
+
+    #[test]
+    fn one_pixel_on_a_ledge_is_enough_to_stand_on_it() {
+        for left_edge in [true, false] {
+            assert!(stays_on_the_block(1.0, left_edge, false));
+            // just past the edge there is nothing to stand on
+            assert!(!stays_on_the_block(0.0, left_edge, false));
+        }
+    }
+
+    #[test]
+    fn falling_onto_a_block_corner_never_sinks_into_it() {
+        let left = InputState { left: true, ..InputState::default() };
+        let right = InputState { right: true, ..InputState::default() };
+        // every pixel from clear off the left corner to clear off the right one, drifting either way
+        for x in 56..=104 {
+            for input in [left, right] {
+                let mut world = blocks_at(&[(5, 6)]);
+                world.spawn_players(0, 0);
+                world.players[0].object.pos = Vec2::new(x as f32, (2 * TILE) as f32);
+                for frame in 0..90 {
+                    world.step(input);
+                    let overlap = overlap_area(&world.players[0].object, &world.blocks[0].object);
+                    assert!(overlap < 1e-3, "from x {} at frame {} the player is {} into the block", x, frame, overlap);
+                }
+            }
+        }
+    }
+
+    // a player jumps straight up from x on the floor, into a powerup block at (4, 5) with a plain block right of it.
+    // whether the jump bumped anything and whether the powerup block let its mushroom out
+    fn jump_under_the_seam(x: f32) -> (bool, bool) {
+        let mut tiles = vec![0; 100];
+        tiles[90..].fill(10);
+        tiles[5 * 10 + 4] = 9;
+        tiles[5 * 10 + 5] = 10;
+        let mut world = World::new_headless(10 * TILE, 10 * TILE);
+        assert!(world.load_tiles(&tiles).is_empty());
+        world.spawn_players(0, 0);
+        world.players[0].object.pos = Vec2::new(x, (8 * TILE) as f32);
+        world.step(InputState::default());
+        world.step(InputState { jump: true, jump_pressed: true, ..InputState::default() });
+        for _ in 0..60 {
+            world.step(InputState { jump: true, ..InputState::default() });
+        }
+        assert_eq!(world.players[0].object.pos.x, x, "the jump drifted");
+        let counts = world.entity_counts();
+        (world.audio.played_sfx().contains(&SfxId::Bump), counts.spawning + counts.powerups > 0)
+    }
+
+    #[test]
+    fn only_the_block_over_the_middle_of_the_head_gets_bumped() {
+        let seam = (5 * TILE) as f32;
+        let head = PLAYER_HITBOX_WIDTH;
+        let inset = (TILE as f32 - head) / 2.0;
+        // the head mostly under the powerup block, partly under the plain one
+        assert_eq!(jump_under_the_seam(seam - inset - head / 2.0 - 2.0), (true, true));
+        // mostly under the plain one, the powerup block is only grazed at its corner
+        assert_eq!(jump_under_the_seam(seam - inset - head / 2.0 + 2.0), (true, false));
+        // all under the powerup block, and all under the plain one
+        assert_eq!(jump_under_the_seam(seam - TILE as f32), (true, true));
+        assert_eq!(jump_under_the_seam(seam), (true, false));
+    }
+
+    #[test]
+    fn two_goombas_walking_into_each_other_turn_around_once() {
+        let mut world = walled_floor(12, 6);
+        world.players[0].object.pos = Vec2::new(TILE as f32, 0.0); // out of the way
+        let (left, right) = (insert_goomba(&mut world, 5 * TILE, 4 * TILE), insert_goomba(&mut world, 6 * TILE, 4 * TILE));
+        world.entities[right].updatable_mut().mut_velocity().x = -1.0;
+        let mut turns = [0; 2];
+        let mut directions = [1.0, -1.0];
+        for frame in 0..30 {
+            world.step(InputState::default());
+            let (left_goomba, right_goomba) = (&world.entities[left], &world.entities[right]);
+            // resolved against where the other one is now, not where it started the frame
+            let overlap = overlap_area(left_goomba.object(), right_goomba.object());
+            assert!(overlap <= 0.0, "overlapping by {} at frame {}", overlap, frame);
+            for (index, goomba) in [left_goomba, right_goomba].into_iter().enumerate() {
+                let direction = goomba.updatable().velocity().x.signum();
+                if direction != directions[index] {
+                    turns[index] += 1;
+                    directions[index] = direction;
+                }
+            }
+        }
+        assert_eq!(turns, [1, 1]);
+        assert_eq!(directions, [-1.0, 1.0]);
+        assert!(world.entities[left].object().pos.x < (5 * TILE) as f32);
+        assert!(world.entities[right].object().pos.x > (6 * TILE) as f32);
+    }
+
+    // there are no moving platforms yet, the test moves a block back and forth the way one would
+    fn move_block(world: &mut World, index: usize, pos: Vec2) {
+        let reference = ObjectReference::Block(index);
+        world.grid.remove(World::grid_cells(&world.blocks[index].object), reference);
+        let block = &mut world.blocks[index].object;
+        block.displacement = pos - block.pos;
+        block.pos = pos;
+        world.grid.insert(World::grid_cells(&world.blocks[index].object), reference);
+    }
+
+    #[test]
+    fn a_player_rides_a_platform_swinging_over_a_pit() {
+        let mut world = blocks_at(&[(4, 6)]);
+        let middle = (4 * TILE) as f32;
+        world.spawn_players(0, 0);
+        world.players[0].object.pos = Vec2::new(middle, (5 * TILE) as f32);
+        world.step(InputState::default());
+        assert!(world.players[0].is_grounded);
+        for frame in 1..=600 {
+            let swing = 2.0 * std::f32::consts::PI * frame as f32 / 120.0;
+            move_block(&mut world, 0, Vec2::new(middle + 24.0 * swing.sin(), (6 * TILE) as f32));
+            world.step(InputState::default());
+            let (player, platform) = (&world.players[0].object, &world.blocks[0].object);
+            assert!((player.pos.x - platform.pos.x).abs() < 0.5, "{} off the middle at frame {}", player.pos.x - platform.pos.x, frame);
+            assert_eq!(player.pos.y, (5 * TILE) as f32, "frame {}", frame);
+        }
+        assert_eq!(*world.game_state(), GameState::Playing);
+    }
+
+    // a headless world sized for level 1, with the level given as its json loaded into it
+    fn load_level_json(level_data: serde_json::Value) -> World {
+        let files = LevelFiles {
+            level_data: serde_json::to_vec(&level_data).unwrap(),
+            tile_types: read_level_file(Path::new(TILE_TYPES_PATH)).unwrap(),
+        };
+        let mut world = World::new_headless(20 * TILE, 300 * TILE);
+        world.load_level_from(Path::new("test.json"), &files).unwrap();
+        world
+    }
+
+    #[test]
+    fn a_small_level_puts_its_blocks_where_its_tiles_are() {
+        // 20x14 tiles, far narrower than the default world: a floor, a block on it and a powerup block up right
+        let (columns, rows) = (20, 14);
+        let mut tiles = vec![0u8; columns * rows];
+        tiles[columns * (rows - 1)..].fill(10);
+        tiles[columns * 12 + 3] = 10;
+        tiles[columns * 5 + 17] = 9;
+        let world = load_level_json(serde_json::json!({ "height": rows * TILE, "width": columns * TILE, "tiles": tiles }));
+        assert_eq!((world.width, world.height), (columns * TILE, rows * TILE));
+        assert_eq!(world.blocks.len(), columns + 2);
+        let block_at = |column: usize, row: usize| {
+            world.blocks.iter().find(|block| block.object.pos == Vec2::new((column * TILE) as f32, (row * TILE) as f32))
+        };
+        for column in 0..columns {
+            assert!(block_at(column, rows - 1).is_some(), "no floor in column {}", column);
+        }
+        assert_eq!(block_at(3, 12).unwrap().object.object_type, ObjectType::Block(BlockType::Block));
+        assert_eq!(block_at(17, 5).unwrap().object.object_type, ObjectType::Block(BlockType::PowerupBlock));
+        // and the grid knows them by those cells, a row off would put them somewhere else
+        for (index, block) in world.blocks.iter().enumerate() {
+            let (x, y) = (block.object.pos.x as usize / TILE, block.object.pos.y as usize / TILE);
+            assert!(world.grid.get(x, y).unwrap_or_default().contains(&ObjectReference::Block(index)));
+        }
+        assert!(world.grid.get(columns, 0).is_none());
+    }
+
+    #[test]
+    fn a_level_without_meta_plays_like_level_1() {
+        let world = load_level_json(serde_json::json!({ "height": 14 * TILE, "tiles": vec![0; 14 * 20] }));
+        assert_eq!(world.meta, LevelMeta::default());
+        assert_eq!(world.time_left(), LEVEL_TIME);
+        assert_eq!(world.music, MusicId::Overworld);
+        assert!(!world.camera.backward_scroll);
+    }
+
+    #[test]
+    fn a_levels_meta_reaches_the_clock_music_and_camera() {
+        let world = load_level_json(serde_json::json!({
+            "meta": { "name": "1-2", "music": "star", "time_limit": 300, "backward_scroll": true },
+            "height": 14 * TILE,
+            "tiles": vec![0; 14 * 20],
+        }));
+        assert_eq!(world.meta.name, "1-2");
+        assert_eq!(world.meta.theme, "overworld"); // left out, so the default
+        assert_eq!(world.time_left(), 300);
+        assert_eq!(world.music, MusicId::Star);
+        assert!(world.camera.backward_scroll);
+    }
+
+    #[test]
+    fn unknown_level_music_plays_the_overworld_theme() {
+        let world = load_level_json(serde_json::json!({ "meta": { "music": "castle" }, "height": 14 * TILE, "tiles": vec![0; 14 * 20] }));
+        assert_eq!(world.music, MusicId::Overworld);
+        assert_eq!(world.meta.music, "castle");
+    }
+
+    #[test]
+    fn objects_outside_the_level_are_refused() {
+        let mut world = blocks_at(&[]);
+        for (x, y) in [(10 * TILE, 0), (0, 10 * TILE), (40 * TILE, 40 * TILE)] {
+            let goomba = Object::new(x, y, ObjectType::Enemy(EnemyType::Goomba));
+            assert!(matches!(world.add_object(goomba), Err(WorldError::OutOfBounds { x: ex, y: ey }) if (ex, ey) == (x, y)));
+            let block = Object::new(x, y, ObjectType::Block(BlockType::Block));
+            assert!(matches!(world.add_block(block, 10), Err(WorldError::OutOfBounds { .. })));
+        }
+        assert_eq!((world.entities.len(), world.blocks.len()), (0, 0));
+    }
+
+    #[test]
+    fn a_taken_cell_is_refused_and_says_what_holds_it() {
+        let mut world = blocks_at(&[(3, 4)]);
+        let block = Object::new(3 * TILE, 4 * TILE, ObjectType::Block(BlockType::Block));
+        assert!(matches!(
+            world.add_block(block, 10),
+            Err(WorldError::CellOccupied { x, y, existing: ObjectReference::Block(0) }) if (x, y) == (3 * TILE, 4 * TILE)
+        ));
+        let goomba = Object::new(3 * TILE, 4 * TILE, ObjectType::Enemy(EnemyType::Goomba));
+        assert!(matches!(world.add_object(goomba), Err(WorldError::CellOccupied { existing: ObjectReference::Block(0), .. })));
+        // a few pixels off still lands in the same cell
+        let coin = Object::new(6 * TILE, 2 * TILE, ObjectType::Coin);
+        world.add_object(coin).unwrap();
+        let coin = Object::new(6 * TILE + 3, 2 * TILE, ObjectType::Coin);
+        assert!(matches!(world.add_object(coin), Err(WorldError::CellOccupied { existing: ObjectReference::Coin(_), .. })));
+        assert_eq!((world.entities.len(), world.blocks.len(), world.coins.len()), (0, 1, 1));
+    }
+
+    #[test]
+    fn blocks_and_objects_only_go_in_their_own_way() {
+        let mut world = blocks_at(&[]);
+        for object_type in [ObjectType::Block(BlockType::Block), ObjectType::Block(BlockType::Launcher), ObjectType::Fireball] {
+            let wrong = world.add_object(Object::new(TILE, TILE, object_type));
+            assert!(matches!(wrong, Err(WorldError::WrongType(found)) if found == object_type));
+        }
+        for object_type in [ObjectType::Enemy(EnemyType::Goomba), ObjectType::Coin, ObjectType::Player] {
+            let wrong = world.add_block(Object::new(TILE, TILE, object_type), 10);
+            assert!(matches!(wrong, Err(WorldError::WrongType(found)) if found == object_type));
+        }
+        // nothing was put in the cell on the way
+        assert!(world.grid.get(1, 1).unwrap_or_default().is_empty());
+    }
+
+    #[test]
+    fn a_level_with_bad_entries_loads_the_rest() {
+        let mut tiles = vec![0u8; 14 * 20];
+        tiles[13 * 20..].fill(10);
+        let world = load_level_json(serde_json::json!({
+            "height": 14 * TILE,
+            "tiles": tiles,
+            "entities": [
+                { "kind": "Goomba", "x": 5 * TILE, "y": 12 * TILE },
+                { "kind": "Goomba", "x": 5 * TILE, "y": 12 * TILE }, // the same cell
+                { "kind": "Koopa", "x": 8 * TILE, "y": 13 * TILE }, // in the floor
+                { "kind": "Coin", "x": 30 * TILE, "y": 2 * TILE }, // past the right end
+                { "kind": "Coin", "x": 9 * TILE, "y": 2 * TILE },
+            ],
+        }));
+        let counts = world.entity_counts();
+        assert_eq!((counts.enemies, counts.blocks), (1, 20));
+        assert_eq!(world.coins.len(), 1);
+    }
+
+    // every reference in the grid is in a cell its object covers, and every player is in all of the cells it covers
+    fn assert_grid_consistent(world: &World) {
+        for (x, y, reference) in world.grid.occupied() {
+            let object = match reference {
+                ObjectReference::Player(index) => world.players[index].object,
+                ObjectReference::Entity(id) => *world.entities.get(id).unwrap_or_else(|| panic!("{:?} at {},{} is gone", id, x, y)).object(),
+                ObjectReference::Block(index) => world.blocks[index].object,
+                _ => continue,
+            };
+            assert!(World::grid_cells(&object).iter().any(|cell| cell == (x, y)), "{:?} is still at {},{}", reference, x, y);
+        }
+        for (index, player) in world.players.iter().enumerate() {
+            for (x, y) in World::grid_cells(&player.object).iter() {
+                assert!(world.grid.get(x, y).unwrap_or_default().contains(&ObjectReference::Player(index)), "player {} missing at {},{}", index, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn the_grid_stays_consistent_when_a_bump_and_a_death_share_a_frame() {
+        let jump = |world: &mut World, frame: usize| {
+            world.step(InputState { jump: true, jump_pressed: frame == 0, ..InputState::default() })
+        };
+        // a first jump finds the frame the head hits the block, and where the player is then
+        let mut world = walled_floor(10, 10);
+        world.add_block(Object::new(4 * TILE, 4 * TILE, ObjectType::Block(BlockType::Block)), 10).unwrap();
+        world.spawn_players(4 * TILE, 8 * TILE);
+        let start = world.save_state();
+        let bump_frame = (0..60)
+            .find(|&frame| {
+                jump(&mut world, frame);
+                world.audio.played_sfx().contains(&SfxId::Bump)
+            })
+            .expect("the jump reaches the block");
+        let bump_pos = world.players[0].object.pos;
+        // the same jump again, with a Goomba walking into the player as the head hits
+        world.load_state(start);
+        world.audio = AudioManager::recording();
+        for frame in 0..bump_frame {
+            jump(&mut world, frame);
+        }
+        let goomba = insert_goomba(&mut world, bump_pos.x as usize + TILE - 2, bump_pos.y as usize);
+        let goomba = world.entities[goomba].updatable_mut();
+        goomba.mut_object().gravity_scale = 0.0;
+        goomba.mut_velocity().x = -1.0;
+        jump(&mut world, bump_frame);
+        assert!(world.audio.played_sfx().contains(&SfxId::Bump));
+        assert!(matches!(world.game_state, GameState::Dying(_)), "{:?}", world.game_state);
+        assert_grid_consistent(&world);
+    }
+
+    // holds nothing, for stepping through frame
+    struct Idle;
+    impl StepDriver for Idle {
+        fn next_input(&mut self) -> InputState {
+            InputState::default()
+        }
+        fn stepped(&mut self, _world: &World, _input: &InputState) {}
+    }
+
+    fn deaths_played(world: &World) -> usize {
+        world.audio.played_sfx().iter().filter(|sfx| **sfx == SfxId::Death).count()
+    }
+
+    #[test]
+    fn dying_stops_the_music_and_turns_into_game_over_after_the_jingle() {
+        let mut world = walled_floor(10, 6);
+        world.audio.play_music(MusicId::Overworld);
+        world.transition_to(GameState::Dying(DYING_TIME));
+        assert_eq!(world.audio.current_music(), None);
+        assert_eq!(deaths_played(&world), 1);
+        let player = world.players[0].object.pos;
+        let frame_time = world.physics.frame_time;
+        let jingle_frames = (DYING_TIME / frame_time).round() as usize;
+        for _ in 1..jingle_frames {
+            world.frame(frame_time, &mut Idle);
+        }
+        assert!(matches!(world.game_state, GameState::Dying(_)));
+        world.frame(frame_time, &mut Idle);
+        assert_eq!(world.game_state, GameState::GameOver);
+        // game over takes no more steps, the time isn't kept for later either
+        world.frame(1.0, &mut Idle);
+        assert_eq!(world.elapsed_time, 0.0);
+        assert_eq!(world.players[0].object.pos, player);
+    }
+
+    #[test]
+    fn the_menu_over_a_state_neither_leaves_nor_re_enters_it() {
+        let mut world = walled_floor(10, 6);
+        world.transition_to(GameState::Dying(DYING_TIME));
+        world.elapsed_time = 0.01;
+        world.set_menu_open(true);
+        assert_eq!(world.game_state, GameState::Menu(Box::new(GameState::Dying(DYING_TIME))));
+        assert_eq!(world.elapsed_time, 0.0);
+        // the dying time stands still while the menu is open
+        world.frame(5.0, &mut Idle);
+        world.set_menu_open(false);
+        assert_eq!(world.game_state, GameState::Dying(DYING_TIME));
+        assert_eq!(world.elapsed_time, 0.0);
+        // closing it didn't die a second time
+        assert_eq!(deaths_played(&world), 1);
+        // only one menu at a time, and closing one that isn't open does nothing
+        world.set_menu_open(true);
+        world.set_menu_open(true);
+        assert_eq!(world.game_state, GameState::Menu(Box::new(GameState::Dying(DYING_TIME))));
+        world.set_menu_open(false);
+        world.set_menu_open(false);
+        assert_eq!(world.game_state, GameState::Dying(DYING_TIME));
+    }
+
+    #[test]
+    fn playing_steps_the_world_and_the_menu_does_not() {
+        let mut world = walled_floor(10, 6);
+        world.spawn_players(2 * TILE, 2 * TILE);
+        world.set_menu_open(true);
+        world.frame(0.5, &mut Idle);
+        assert_eq!(world.players[0].object.pos.y, (2 * TILE) as f32);
+        world.set_menu_open(false);
+        assert_eq!(world.game_state, GameState::Playing);
+        world.frame(0.5, &mut Idle);
+        assert!(world.players[0].object.pos.y > (2 * TILE) as f32);
+    }
+
+    // runs right, counting the steps it was asked for
+    #[derive(Default)]
+    struct RunRight {
+        steps: usize,
+    }
+    impl StepDriver for RunRight {
+        fn next_input(&mut self) -> InputState {
+            InputState { right: true, ..InputState::default() }
+        }
+        fn stepped(&mut self, _world: &World, _input: &InputState) {
+            self.steps += 1;
+        }
+    }
+
+    #[test]
+    fn ten_seconds_are_600_steps_however_the_frames_cut_them_up() {
+        let seconds = 10.0;
+        let uneven: Vec<f32> = [0.004, 0.031, 0.017, 0.05, 0.0009].into_iter().cycle().scan(0.0, |total, dt| {
+            *total += dt;
+            (*total <= seconds).then_some(dt)
+        }).collect();
+        let uneven_rest = seconds - uneven.iter().sum::<f32>();
+        let chunkings: Vec<Vec<f32>> = vec![
+            vec![1.0 / 60.0; 600],
+            vec![1.0 / 30.0; 300],
+            vec![1.0 / 144.0; 1440],
+            vec![1.0 / 240.0; 2400],
+            uneven.into_iter().chain([uneven_rest]).collect(),
+        ];
+        let mut runs = Vec::new();
+        for frames in &chunkings {
+            let mut world = walled_floor(200, 6);
+            world.spawn_players(2 * TILE, 4 * TILE);
+            insert_goomba(&mut world, 150 * TILE, 4 * TILE); // walking ahead, too far to be caught up with
+            let mut driver = RunRight::default();
+            for &dt in frames {
+                world.frame(dt, &mut driver);
+            }
+            runs.push((driver.steps, world.time_left_frames, world.state_hash()));
+        }
+        assert_eq!(runs[0].0, 600);
+        for (frames, run) in chunkings.iter().zip(&runs) {
+            assert_eq!(*run, runs[0], "{} frames of about {}", frames.len(), frames[0]);
+        }
+    }
+
+    // what has to come back exactly after a rewind: the state hash and where every animation is
+    fn fingerprint(world: &World) -> (u64, Vec<(usize, u32)>) {
+        let animations = world.players.iter().map(|player| &player.animate)
+            .chain(world.entities.values().map(|entity| entity.updatable().animate()))
+            .map(|animate| (animate.current_frame_index, animate.time_elapsed.to_bits()))
+            .collect();
+        (world.state_hash(), animations)
+    }
+
+    #[test]
+    fn rewinding_and_replaying_the_same_inputs_comes_back_to_the_same_state() {
+        // runs back and forth, jumping every now and then, with Goombas walking about
+        let input = |frame: usize| InputState {
+            right: frame % 120 < 70,
+            left: frame % 120 >= 80,
+            jump: frame % 45 < 12,
+            jump_pressed: frame.is_multiple_of(45),
+            ..InputState::default()
+        };
+        let mut world = walled_floor(40, 8);
+        world.spawn_players(4 * TILE, 6 * TILE);
+        for column in [20, 28, 33] {
+            insert_goomba(&mut world, column * TILE, 6 * TILE);
+        }
+        let mut rewind = crate::rewind::Rewind::new();
+        let mut fingerprints = Vec::new();
+        for frame in 0..300 {
+            world.step(input(frame));
+            rewind.stepped(|| world.save_state(), input(frame));
+            fingerprints.push(fingerprint(&world));
+        }
+        assert_eq!(*world.game_state(), GameState::Playing);
+        let snapshot = rewind.rewind(1.0, world.physics.frame_time).expect("a second back is still kept");
+        let frame = snapshot.frame as usize;
+        assert!((230..=250).contains(&frame), "{}", frame);
+        world.load_state(snapshot.state);
+        assert_eq!(fingerprint(&world), fingerprints[frame - 1]);
+        for frame in frame..300 {
+            world.step(input(frame));
+        }
+        assert_eq!(fingerprint(&world), fingerprints[299]);
+    }
+
+    #[test]
+    fn a_world_standing_still_picks_up_exactly_where_it_stopped() {
+        // the hit freeze is gone, the menu is what stops everything now
+        let level = || {
+            let mut world = walled_floor(40, 8);
+            world.spawn_players(4 * TILE, 6 * TILE);
+            for column in [20, 28] {
+                insert_goomba(&mut world, column * TILE, 6 * TILE);
+            }
+            world
+        };
+        let (mut stopped, mut running) = (level(), level());
+        let mut driver = RunRight::default();
+        for world in [&mut stopped, &mut running] {
+            for _ in 0..20 {
+                world.frame(PHYSICS_FRAME_TIME, &mut driver);
+            }
+        }
+        // mid run, with the player and the Goombas moving and animating
+        assert!(stopped.players[0].velocity.x > 0.0);
+        let before = (fingerprint(&stopped), stopped.time_left_frames);
+        stopped.set_menu_open(true);
+        for _ in 0..120 {
+            stopped.frame(PHYSICS_FRAME_TIME, &mut driver);
+        }
+        stopped.set_menu_open(false);
+        assert_eq!((fingerprint(&stopped), stopped.time_left_frames), before);
+        // nothing was held over, the velocities included: both go on alike from there
+        for _ in 0..60 {
+            stopped.frame(PHYSICS_FRAME_TIME, &mut driver);
+            running.frame(PHYSICS_FRAME_TIME, &mut driver);
+            assert_eq!(fingerprint(&stopped), fingerprint(&running));
+        }
+    }
+
+    #[test]
+    fn the_dying_time_lasts_its_steps_at_any_frame_rate() {
+        // the timed state left since the hit freeze went, counted in physics steps like the freeze was
+        let steps = (DYING_TIME / PHYSICS_FRAME_TIME).round() as usize;
+        let mut world = walled_floor(10, 6);
+        world.transition_to(GameState::Dying(DYING_TIME));
+        let mut stepped = 0;
+        while world.game_state != GameState::GameOver {
+            assert!(world.run_step(&mut Idle));
+            stepped += 1;
+            assert!(stepped <= steps, "still dying after {} steps", stepped);
+        }
+        assert_eq!(stepped, steps);
+        // and through frame, however the render frames cut up the time
+        for fps in [30.0, 60.0, 144.0, 240.0, 1000.0] {
+            let mut world = walled_floor(10, 6);
+            world.transition_to(GameState::Dying(DYING_TIME));
+            let mut frames = 0;
+            while world.game_state != GameState::GameOver {
+                world.frame(1.0 / fps, &mut Idle);
+                frames += 1;
+            }
+            let seconds = frames as f32 / fps;
+            assert!(seconds >= DYING_TIME - 1.0 / fps && seconds < DYING_TIME + 1.0 / fps, "{} seconds at {} fps", seconds, fps);
+        }
+    }
+
+    #[test]
+    fn a_goomba_in_a_one_tile_pocket_settles_without_overlap() {
+        let mut world = walled_floor(12, 6);
+        world.players[0].object.pos = Vec2::new(TILE as f32, 0.0); // out of the way
+        for column in [4, 6] {
+            world.add_block(Object::new(column * TILE, 4 * TILE, ObjectType::Block(BlockType::Block)), 10).unwrap();
+        }
+        // dropped in a little into the left wall
+        let goomba = insert_goomba(&mut world, 5 * TILE - 3, 4 * TILE);
+        let walls: Vec<Object> = world.blocks.iter().map(|block| block.object).filter(|wall| wall.pos.y == (4 * TILE) as f32).collect();
+        for frame in 0..120 {
+            world.step(InputState::default());
+            let goomba = world.entities[goomba].object();
+            for wall in &walls {
+                assert!(overlap_area(goomba, wall) <= 0.0, "{} into the wall at {} at frame {}", overlap_area(goomba, wall), wall.pos.x, frame);
+            }
+            assert_eq!(goomba.pos.x, (5 * TILE) as f32, "frame {}", frame);
+        }
+    }
+
+    #[test]
+    fn a_goomba_squeezed_between_two_walls_turns_around_once() {
+        // narrower than a tile, only possible with the walls placed by hand
+        let mut goomba = Goomba::new(5 * TILE, 4 * TILE, 2);
+        goomba.is_grounded = true;
+        let wall = |x: f32, direction: isize| {
+            let mut wall = Object::new(0, 4 * TILE, ObjectType::Block(BlockType::Block));
+            wall.pos.x = x;
+            SurroundingObject::new(wall, ObjectReference::Block(0), (0, direction))
+        };
+        let mut surrounding = vec![wall((4 * TILE + 2) as f32, -1), wall((6 * TILE - 3) as f32, 1)];
+        let bounds = WorldBounds { min_x: 0, max_x: 20 * TILE, max_y: 10 * TILE, goal_x: None };
+        let mut events = Vec::new();
+        goomba.update(&mut surrounding, bounds, &PhysicsConfig::default(), &mut events);
+        // it hit the right wall first, the deeper one, and the left one didn't send it back into it
+        assert!(goomba.velocity.x < 0.0, "{}", goomba.velocity.x);
+    }
+
+    fn bump_the_powerup_block_in_column(column: usize) -> World {
+        let mut tiles = vec![0; 100];
+        tiles[90..].fill(10);
+        tiles[5 * 10 + column] = 9;
+        let mut world = World::new_headless(10 * TILE, 10 * TILE);
+        assert!(world.load_tiles(&tiles).is_empty());
+        // the goal defaults to a tile before the right end, standing in the last column would win
+        world.goal_x = 10 * TILE;
+        world.spawn_players(0, 0);
+        world.players[0].object.pos = Vec2::new((column * TILE) as f32, (8 * TILE) as f32);
+        world.step(InputState::default());
+        world.step(InputState { jump: true, jump_pressed: true, ..InputState::default() });
+        for _ in 0..60 {
+            world.step(InputState { jump: true, ..InputState::default() });
+        }
+        world
+    }
+
+    #[test]
+    fn powerup_blocks_in_the_first_and_last_column_can_be_bumped() {
+        for column in [0, 9] {
+            let world = bump_the_powerup_block_in_column(column);
+            let counts = world.entity_counts();
+            assert_eq!(counts.spawning + counts.powerups, 1, "column {}", column);
+            assert!(world.audio.played_sfx().contains(&SfxId::Bump));
+            assert_grid_consistent(&world);
+        }
+    }
+
+    #[test]
+    fn an_enemy_killed_in_the_last_column_and_row_leaves_nothing_behind() {
+        let mut world = blocks_at(&[]);
+        world.goal_x = 10 * TILE;
+        world.spawn_players(4 * TILE, 0);
+        world.players[0].object.gravity_scale = 0.0;
+        let corner = insert_goomba(&mut world, 9 * TILE, 9 * TILE);
+        let first = insert_goomba(&mut world, 0, 9 * TILE);
+        assert!(referenced_entities(&world).contains(&corner));
+        kill(&mut world, corner);
+        kill(&mut world, first);
+        for _ in 0..120 {
+            world.step(InputState::default());
+        }
+        let referenced = referenced_entities(&world);
+        assert!(!referenced.contains(&corner) && !referenced.contains(&first));
+        assert!(!world.entities.contains_key(corner) && !world.entities.contains_key(first));
+        assert_grid_consistent(&world);
+    }
+
+    type Cells = Vec<(usize, usize)>;
+
+    // the cells the player's reference is in, and the cells it covers
+    fn player_registration(world: &World, index: usize) -> (Cells, Cells) {
+        let registered = world
+            .grid
+            .occupied()
+            .filter(|(_, _, reference)| *reference == ObjectReference::Player(index))
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        (registered, World::grid_cells(&world.players[index].object).iter().collect())
+    }
+
+    #[test]
+    fn the_player_stays_in_the_grid_through_a_hit_the_menu_and_a_state_load() {
+        let mut world = walled_floor(20, 6);
+        world.spawn_players(9 * TILE, 4 * TILE);
+        world.players[0].power_up(PowerupType::Mushroom);
+        insert_goomba(&mut world, 4 * TILE, 4 * TILE);
+        let mut steps = 0;
+        while world.players[0].hit_invincible_frames == 0 {
+            world.step(InputState::default());
+            steps += 1;
+            assert!(steps < 120, "the Goomba never reached the player");
+        }
+        // checked in the frame of the hit itself
+        let (registered, covered) = player_registration(&world, 0);
+        assert_eq!(registered, covered);
+        let saved = world.save_state();
+        world.set_menu_open(true);
+        world.frame(1.0, &mut Idle);
+        world.set_menu_open(false);
+        assert_eq!(player_registration(&world, 0).0, covered);
+        world.load_state(saved);
+        assert_eq!(player_registration(&world, 0).0, covered);
+        // once the invincibility is over the Goomba, back from the wall, hits the player again
+        let mut steps = 0;
+        while !matches!(world.game_state, GameState::Dying(_)) {
+            world.step(InputState::default());
+            let (registered, covered) = player_registration(&world, 0);
+            assert_eq!(registered, covered);
+            steps += 1;
+            assert!(steps < 1200, "the Goomba never hit the player again");
+        }
+        assert!(matches!(world.players[0].power_state, PlayerState::Dead));
+    }
+
+    #[test]
+    fn the_player_stays_in_the_grid_in_the_frame_the_level_is_won() {
+        let mut world = walled_floor(20, 6);
+        world.goal_x = 12 * TILE;
+        world.spawn_players(10 * TILE, 4 * TILE);
+        let mut steps = 0;
+        while world.game_state != GameState::GameWon {
+            world.step(InputState { right: true, ..InputState::default() });
+            steps += 1;
+            assert!(steps < 120, "the player never got to the goal");
+        }
+        let (registered, covered) = player_registration(&world, 0);
+        assert!(!registered.is_empty());
+        assert_eq!(registered, covered);
+    }
+
+    // the player has just bumped the powerup block at (5, 3), the mushroom is rising into (5, 2)
+    fn a_mushroom_rising_into_5_2(blocks: &[(usize, usize)]) -> World {
+        let mut world = walled_floor(12, 8);
+        world.add_block(Object::new(5 * TILE, 3 * TILE, ObjectType::Block(BlockType::PowerupBlock)), 9).unwrap();
+        for &(x, y) in blocks {
+            world.add_block(Object::new(x * TILE, y * TILE, ObjectType::Block(BlockType::Block)), 10).unwrap();
+        }
+        world.spawn_players(5 * TILE, 6 * TILE);
+        world.step(InputState::default());
+        world.step(InputState { jump: true, jump_pressed: true, ..InputState::default() });
+        while world.spawning_objects.is_empty() {
+            assert!(world.players[0].velocity.y <= 0.0, "came down without reaching the block");
+            world.step(InputState { jump: true, ..InputState::default() });
+        }
+        world
+    }
+
+    fn powerup_cells(world: &World) -> Vec<(usize, usize)> {
+        world
+            .entities
+            .values()
+            .filter(|entity| matches!(entity, Entity::PowerUp(_)))
+            .flat_map(|powerup| World::grid_cells(powerup.object()).iter())
+            .collect()
+    }
+
+    #[test]
+    fn a_player_standing_where_the_mushroom_comes_out_collects_it() {
+        let mut world = a_mushroom_rising_into_5_2(&[]);
+        // onto the block, right on top of the rising mushroom, for the whole of the rise
+        world.players[0].object.pos = Vec2::new((5 * TILE) as f32, (2 * TILE) as f32);
+        world.players[0].velocity = Vec2::ZERO;
+        world.register_players();
+        let mut steps = 0;
+        while !world.spawning_objects.is_empty() {
+            world.step(InputState::default());
+            assert_eq!(world.players[0].object.pos.x, (5 * TILE) as f32, "the player didn't stay on the spawn cell");
+            steps += 1;
+            assert!(steps < 120, "the mushroom never finished rising");
+        }
+        assert_eq!(world.players[0].power_state, PlayerState::Big);
+        assert_eq!(world.entity_counts().powerups, 0);
+        assert_grid_consistent(&world);
+    }
+
+    #[test]
+    fn a_mushroom_coming_out_onto_an_enemy_moves_a_tile_to_the_side() {
+        let mut world = a_mushroom_rising_into_5_2(&[(4, 2)]);
+        // a Goomba walks onto the block just as the mushroom is done rising
+        let rise = &mut world.spawning_objects[0];
+        rise.animation_progress = rise.animation_finish - 0.01;
+        insert_goomba(&mut world, 5 * TILE, 2 * TILE);
+        world.step(InputState::default());
+        assert!(world.spawning_objects.is_empty());
+        // the left is a wall, it came out on the right
+        assert_eq!(powerup_cells(&world), [(6, 2)]);
+        assert_grid_consistent(&world);
+    }
+
+    #[test]
+    fn a_mushroom_boxed_in_waits_on_the_block_until_its_cell_is_free() {
+        let mut world = a_mushroom_rising_into_5_2(&[(4, 2), (6, 2)]);
+        // walking back and forth between the two blocks, it never leaves the cell
+        let goomba = insert_goomba(&mut world, 5 * TILE, 2 * TILE);
+        for _ in 0..120 {
+            world.step(InputState::default());
+        }
+        assert_eq!(world.entity_counts().spawning, 1);
+        assert!(powerup_cells(&world).is_empty());
+        assert_grid_consistent(&world);
+        kill(&mut world, goomba);
+        for _ in 0..60 {
+            world.step(InputState::default());
+            if world.spawning_objects.is_empty() {
+                break;
+            }
+        }
+        assert_eq!(powerup_cells(&world), [(5, 2)]);
+        assert_grid_consistent(&world);
+    }
+
+    // a floor the whole way, the player standing on it near the left end
+    fn flat_level(columns: usize, meta: serde_json::Value) -> World {
+        let mut tiles = vec![0u8; columns * 14];
+        tiles[columns * 13..].fill(10);
+        let mut world = load_level_json(serde_json::json!({ "meta": meta, "height": 14 * TILE, "width": columns * TILE, "tiles": tiles }));
+        world.spawn_players(2 * TILE, 12 * TILE);
+        world.register_players();
+        world
+    }
+
+    // how far the player's right edge got past the goal in the step it won
+    fn run_right_to_the_win(world: &mut World) -> f32 {
+        for _ in 0..900 {
+            let right_edge = world.players[0].object.pos.x + world.players[0].object.width as f32;
+            assert!(right_edge <= world.goal_x as f32, "walked past the goal without winning");
+            world.step(InputState { right: true, ..InputState::default() });
+            if world.game_state == GameState::GameWon {
+                return world.players[0].object.pos.x + world.players[0].object.width as f32 - world.goal_x as f32;
+            }
+        }
+        panic!("never won running right");
+    }
+
+    #[test]
+    fn without_a_goal_the_player_wins_a_tile_before_the_right_end() {
+        let mut world = flat_level(20, serde_json::json!({}));
+        assert_eq!(world.goal_x, 19 * TILE);
+        let past = run_right_to_the_win(&mut world);
+        assert!(past > 0.0 && past <= world.physics.max_speed, "{}", past);
+    }
+
+    #[test]
+    fn a_goal_from_the_meta_wins_there_and_not_at_the_right_end() {
+        let mut world = flat_level(40, serde_json::json!({ "goal_x": 10 * TILE }));
+        assert_eq!(world.goal_x, 10 * TILE);
+        let past = run_right_to_the_win(&mut world);
+        assert!(past > 0.0 && past <= world.physics.max_speed, "{}", past);
+    }
+
+    #[test]
+    fn a_goal_outside_the_level_is_refused() {
+        for goal_x in [0, 21 * TILE] {
+            let level_data = serde_json::json!({ "meta": { "goal_x": goal_x }, "height": 14 * TILE, "width": 20 * TILE, "tiles": vec![0; 14 * 20] });
+            let files = LevelFiles {
+                level_data: serde_json::to_vec(&level_data).unwrap(),
+                tile_types: read_level_file(Path::new(TILE_TYPES_PATH)).unwrap(),
+            };
+            let mut world = World::new_headless(20 * TILE, 300 * TILE);
+            assert!(world.load_level_from(Path::new("test.json"), &files).is_err(), "goal at {}", goal_x);
+        }
+    }
+
+    #[test]
+    fn the_left_wall_follows_the_camera_and_never_wins() {
+        let mut world = flat_level(80, serde_json::json!({}));
+        for _ in 0..240 {
+            world.step(InputState { right: true, ..InputState::default() });
+        }
+        let camera_x = world.camera.x;
+        assert!(camera_x > 0, "the camera didn't move");
+        for _ in 0..240 {
+            world.step(InputState { left: true, ..InputState::default() });
+        }
+        // held against the wall, the camera doesn't let the player back and it doesn't end the level
+        assert!(world.camera.x >= camera_x);
+        assert_eq!(world.players[0].object.pos.x, world.camera.x as f32);
+        assert_eq!(world.game_state, GameState::Playing);
+    }
+
+    #[test]
+    fn two_goombas_side_by_side_take_only_one_power_down() {
+        let mut world = walled_floor(20, 6);
+        world.spawn_players(9 * TILE, 4 * TILE);
+        world.players[0].power_up(PowerupType::Mushroom);
+        // walking into the player from both sides, the right one a pixel further out so they hit a frame apart
+        let goombas = [insert_goomba(&mut world, 7 * TILE, 4 * TILE), insert_goomba(&mut world, 11 * TILE + 1, 4 * TILE)];
+        world.entities[goombas[1]].updatable_mut().mut_velocity().x = -1.0;
+        let mut touched = [false; 2];
+        let mut touch = |world: &World| {
+            let player = world.players[0].object.hitbox().center().x;
+            for (touched, id) in touched.iter_mut().zip(goombas) {
+                *touched |= (world.entities[id].object().hitbox().center().x - player).abs() <= TILE as f32;
+            }
+        };
+        let mut steps = 0;
+        while world.players[0].hit_invincible_frames == 0 {
+            world.step(InputState::default());
+            touch(&world);
+            steps += 1;
+            assert!(steps < 120, "the Goombas never reached the player");
+        }
+        for step in 1..HIT_INVINCIBLE_FRAMES {
+            world.step(InputState::default());
+            touch(&world);
+            // one less every physics step
+            assert_eq!(world.players[0].hit_invincible_frames, HIT_INVINCIBLE_FRAMES - step);
+        }
+        assert_eq!(touched, [true, true], "the Goombas didn't both walk into the player");
+        assert_eq!(world.players[0].power_state, PlayerState::Small);
+        assert_eq!(world.audio.played_sfx().iter().filter(|sfx| **sfx == SfxId::PowerDown).count(), 1);
+        assert_eq!(world.game_state, GameState::Playing);
+    }
+
+    // a floor with a wall at each end, the player standing under a powerup block
+    fn under_a_powerup_block() -> World {
+        let (columns, rows) = (12, 8);
+        let tile = MARIO_SPRITE_BLOCK_SIZE;
+        let mut world = World::new_headless(rows * tile, columns * tile);
+        let mut tiles = vec![0; columns * rows];
+        tiles[columns * (rows - 1)..].fill(10);
+        tiles[columns * (rows - 2)] = 10;
+        tiles[columns * (rows - 1) - 1] = 10;
+        tiles[columns * 3 + 5] = 9;
+        assert!(world.load_tiles(&tiles).is_empty());
+        world.spawn_players(5 * tile, 6 * tile);
+        world
+    }
+
+    #[test]
+    fn the_one_mushroom_out_of_a_block_is_in_the_grid_and_can_be_collected() {
+        let mut world = under_a_powerup_block();
+        world.step(InputState::default());
+        world.step(InputState { jump: true, jump_pressed: true, ..InputState::default() });
+        while world.spawning_objects.is_empty() {
+            assert!(world.players[0].velocity.y <= 0.0, "came down without reaching the block");
+            world.step(InputState { jump: true, ..InputState::default() });
+        }
+        for _ in 0..60 {
+            world.step(InputState::default());
+            if world.entity_counts().powerups == 1 {
+                break;
+            }
+        }
+        // the only powerup, where the grid says it is
+        let (id, mushroom) = world.entities.iter().find(|(_, entity)| matches!(entity, Entity::PowerUp(_))).expect("the mushroom came out");
+        let grid = world.save_state().grid;
+        for (x, y) in World::grid_cells(mushroom.object()).iter() {
+            assert!(grid.contains(&(x, y, ObjectReference::Entity(id))));
+        }
+        // it comes back off the right wall and runs into the player standing under the block
+        for _ in 0..600 {
+            world.step(InputState::default());
+            if matches!(world.players[0].power_state, PlayerState::Big) {
+                break;
+            }
+        }
+        assert!(matches!(world.players[0].power_state, PlayerState::Big));
+        assert!(!world.entities.contains_key(id));
+        assert!(!world.save_state().grid.iter().any(|(_, _, reference)| *reference == ObjectReference::Entity(id)));
+    }
+
+    // the player standing on a long flat floor, tall enough above it for any jump
+    fn standing_on_a_floor() -> World {
+        let (columns, rows) = (20, 14);
+        let tile = MARIO_SPRITE_BLOCK_SIZE;
+        let mut world = World::new_headless(rows * tile, columns * tile);
+        let mut tiles = vec![0; columns * rows];
+        tiles[columns * (rows - 1)..].fill(10);
+        assert!(world.load_tiles(&tiles).is_empty());
+        world.spawn_players(4 * tile, (rows - 2) * tile);
+        for _ in 0..30 {
+            world.step(InputState::default());
+        }
+        world
+    }
+
+    fn held_jump(first_frame: bool) -> InputState {
+        InputState { jump: true, jump_pressed: first_frame, ..InputState::default() }
+    }
+
+    #[test]
+    fn holding_jump_never_goes_higher_than_a_single_jump() {
+        // one jump, held while it rises and let go once it falls
+        let mut world = standing_on_a_floor();
+        let ground = world.players[0].object.pos.y;
+        world.step(held_jump(true));
+        let mut single_apex = world.players[0].object.pos.y;
+        for _ in 0..120 {
+            let input = if world.players[0].velocity.y <= 0.0 { held_jump(false) } else { InputState::default() };
+            world.step(input);
+            single_apex = single_apex.min(world.players[0].object.pos.y);
+        }
+        assert!(single_apex < ground);
+
+        let mut world = standing_on_a_floor();
+        for frame in 0..120 {
+            world.step(held_jump(frame == 0));
+            let y = world.players[0].object.pos.y;
+            assert!(y >= single_apex, "{} above the single jump's {}", y, single_apex);
+        }
+        assert_eq!(world.players[0].object.pos.y, ground);
+    }
+
+    // how high the player bounces off a Goomba stuck in a pocket under it, with jump held through the fall or not
+    fn stomp_bounce_apex(jump: bool) -> f32 {
+        let (columns, rows) = (20, 13);
+        let tile = MARIO_SPRITE_BLOCK_SIZE;
+        let mut world = World::new_headless(rows * tile, columns * tile);
+        let mut tiles = vec![0; columns * rows];
+        tiles[columns * (rows - 2)..].fill(10);
+        tiles[columns * (rows - 3) + 3] = 10;
+        tiles[columns * (rows - 3) + 5] = 10;
+        assert!(world.load_tiles(&tiles).is_empty());
+        world.add_object(Object::new(4 * tile, (rows - 3) * tile, ObjectType::Enemy(EnemyType::Goomba))).unwrap();
+        world.spawn_players(4 * tile, tile);
+        let mut frame = 0;
+        while !world.audio.played_sfx().contains(&SfxId::Stomp) {
+            world.step(InputState { jump, jump_pressed: jump && frame == 0, ..InputState::default() });
+            frame += 1;
+            assert!(frame < 120, "the player never landed on the Goomba");
+        }
+        let mut apex = world.players[0].object.pos.y;
+        for _ in 0..60 {
+            world.step(InputState { jump, ..InputState::default() });
+            apex = apex.min(world.players[0].object.pos.y);
+        }
+        assert!(apex < ((rows - 4) * tile) as f32, "no bounce, the apex was {}", apex);
+        apex
+    }
+
+    #[test]
+    fn holding_jump_through_a_stomp_does_not_bounce_higher() {
+        assert_eq!(stomp_bounce_apex(true), stomp_bounce_apex(false));
+    }
+}
@@ -0,0 +1,97 @@
+// shared by the test files, each uses only some of it
+#![allow(dead_code)]
+
+use rustario_2d::config::MARIO_SPRITE_BLOCK_SIZE;
+use rustario_2d::input::InputState;
+use rustario_2d::preparation::{EntityKind, EntitySpawn};
+use rustario_2d::world::{StepDriver, World};
+
+pub const TILE: usize = MARIO_SPRITE_BLOCK_SIZE;
+const SOLID_TILE: u8 = 10;
+const POWERUP_BLOCK_TILE: u8 = 9;
+const SKY_TILE: u8 = 0;
+
+// a headless world from rows of tiles, one character each: '#' ground, '?' a powerup block, 'M' the player start,
+// 'g' a Goomba, 'k' a Koopa, 'p' a Piranha Plant (on the left tile above a pipe), 'L' a Bullet Bill launcher,
+// 'W' a Lakitu, 's' a Starman, 'c' a coin and 'F' the flagpole. anything else is sky
+pub fn world_from(rows: &[&str]) -> World {
+    let mut world = World::new_headless(rows.len() * TILE, rows[0].len() * TILE);
+    let mut tiles = Vec::new();
+    let mut spawns = Vec::new();
+    for (row, line) in rows.iter().enumerate() {
+        assert_eq!(line.len(), rows[0].len(), "row {} is {} tiles wide", row, line.len());
+        for (column, tile) in line.chars().enumerate() {
+            tiles.push(match tile {
+                '#' => SOLID_TILE,
+                '?' => POWERUP_BLOCK_TILE,
+                _ => SKY_TILE,
+            });
+            let kind = match tile {
+                'M' => EntityKind::PlayerStart,
+                'g' => EntityKind::Goomba,
+                'k' => EntityKind::Koopa,
+                'p' => EntityKind::PiranhaPlant,
+                'L' => EntityKind::Launcher,
+                'W' => EntityKind::Lakitu,
+                's' => EntityKind::Star,
+                'c' => EntityKind::Coin,
+                'F' => EntityKind::Flagpole,
+                _ => continue,
+            };
+            spawns.push(EntitySpawn { kind, x: column * TILE, y: row * TILE });
+        }
+    }
+    let errors = world.load_tiles(&tiles);
+    assert!(errors.is_empty(), "{:?}", errors);
+    let errors = world.spawn_entities(&spawns);
+    assert!(errors.is_empty(), "{:?}", errors);
+    world
+}
+
+pub fn idle() -> InputState {
+    InputState::default()
+}
+
+pub fn right() -> InputState {
+    InputState { right: true, ..InputState::default() }
+}
+
+pub fn run_right() -> InputState {
+    InputState { right: true, run: true, ..InputState::default() }
+}
+
+pub fn jump() -> InputState {
+    InputState { jump: true, ..InputState::default() }
+}
+
+// steps a world the way the game does, dying counts down and the presses are taken against the step before
+#[derive(Default)]
+pub struct Pad {
+    held: InputState,
+    previous: InputState,
+}
+
+impl StepDriver for Pad {
+    fn next_input(&mut self) -> InputState {
+        let mut input = self.held;
+        input.update_edges(&self.previous);
+        input
+    }
+
+    fn stepped(&mut self, _world: &World, input: &InputState) {
+        self.previous = *input;
+    }
+}
+
+impl Pad {
+    pub fn step(&mut self, world: &mut World, input: InputState) {
+        self.held = input;
+        world.run_step(self);
+    }
+
+    pub fn hold(&mut self, world: &mut World, input: InputState, frames: usize) {
+        for _ in 0..frames {
+            self.step(world, input);
+        }
+    }
+}
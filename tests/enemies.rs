@@ -0,0 +1,171 @@
+mod common;
+
+use common::{idle, world_from, Pad, TILE};
+use macroquad::math::Vec2;
+use rustario_2d::config::{LAUNCHER_COOLDOWN_FRAMES, PLANT_HIDDEN_FRAMES};
+use rustario_2d::entities::{EnemyType, PlantState};
+use rustario_2d::world::{GameState, World};
+
+fn spinies(world: &World) -> usize {
+    world.enemies().filter(|(enemy, _)| *enemy == EnemyType::Spiny).count()
+}
+
+// the Lakitus leave once a player gets within LAKITU_LEAVES_BEFORE_GOAL of the goal, the levels here are wide enough
+// to keep them around
+
+#[test]
+fn a_lakitu_holds_its_egg_while_the_player_is_under_a_ceiling() {
+    let mut world = world_from(&[
+        "                                        ",
+        "          W                             ",
+        "                                        ",
+        "                                        ",
+        "                                        ",
+        "                                        ",
+        "                                        ",
+        "########################################",
+        "                                        ",
+        "                                        ",
+        "                                        ",
+        "   M                                    ",
+        "########################################",
+        "########################################",
+    ]);
+    Pad::default().hold(&mut world, idle(), 300);
+    assert_eq!(spinies(&world), 0);
+}
+
+#[test]
+fn a_lakitu_throws_an_egg_at_a_player_in_the_open() {
+    let mut world = world_from(&[
+        "                                        ",
+        "          W                             ",
+        "                                        ",
+        "                                        ",
+        "                                        ",
+        "                                        ",
+        "                                        ",
+        "                                        ",
+        "                                        ",
+        "                                        ",
+        "                                        ",
+        "   M                                    ",
+        "########################################",
+        "########################################",
+    ]);
+    Pad::default().hold(&mut world, idle(), 300);
+    assert_eq!(spinies(&world), 1);
+}
+
+fn plant_state(world: &World) -> PlantState {
+    world
+        .enemies()
+        .find_map(|(enemy, _)| match enemy {
+            EnemyType::PiranhaPlant(state) => Some(state),
+            _ => None,
+        })
+        .expect("a Piranha Plant")
+}
+
+#[test]
+fn a_piranha_plant_stays_in_its_pipe_while_the_player_is_beside_or_on_it() {
+    for player_row in ["       M##          ", "        ##          "] {
+        let on_pipe = !player_row.contains('M');
+        let mut world = world_from(&[
+            "                    ",
+            "                    ",
+            "                    ",
+            "                    ",
+            "                    ",
+            "                    ",
+            "                    ",
+            "                    ",
+            if on_pipe { "        pM          " } else { "        p           " },
+            "        ##          ",
+            "        ##          ",
+            player_row,
+            "####################",
+            "####################",
+        ]);
+        let mut pad = Pad::default();
+        for _ in 0..PLANT_HIDDEN_FRAMES * 4 {
+            pad.step(&mut world, idle());
+            assert_eq!(plant_state(&world), PlantState::Hidden, "with the player {}", if on_pipe { "on the pipe" } else { "beside it" });
+        }
+    }
+}
+
+#[test]
+fn a_piranha_plant_comes_out_with_the_player_away_from_its_pipe() {
+    let mut world = world_from(&[
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "        p           ",
+        "        ##          ",
+        "        ##          ",
+        "  M     ##          ",
+        "####################",
+        "####################",
+    ]);
+    let mut pad = Pad::default();
+    pad.hold(&mut world, idle(), PLANT_HIDDEN_FRAMES as usize + 1);
+    assert_eq!(plant_state(&world), PlantState::Rising);
+}
+
+fn bullets(world: &World) -> Vec<Vec2> {
+    world.enemies().filter(|(enemy, _)| *enemy == EnemyType::BulletBill).map(|(_, pos)| pos).collect()
+}
+
+// the player waits on a ledge, the bullets fly along the ground under it
+const LAUNCHER_LEVEL: [&str; 14] = [
+    "                                        ",
+    "                                        ",
+    "                                        ",
+    "                                        ",
+    "   M                                    ",
+    "###########                             ",
+    "                                        ",
+    "                                        ",
+    "                                        ",
+    "                                        ",
+    "                                        ",
+    "                         #    L         ",
+    "########################################",
+    "########################################",
+];
+
+#[test]
+fn a_launcher_waits_out_its_cooldown_between_shots() {
+    let mut world = world_from(&LAUNCHER_LEVEL);
+    let mut pad = Pad::default();
+    let cooldown = LAUNCHER_COOLDOWN_FRAMES as usize;
+    pad.hold(&mut world, idle(), cooldown - 1);
+    assert_eq!(bullets(&world).len(), 0);
+    pad.step(&mut world, idle());
+    assert_eq!(bullets(&world).len(), 1);
+    pad.hold(&mut world, idle(), cooldown - 1);
+    assert_eq!(bullets(&world).len(), 1);
+    pad.step(&mut world, idle());
+    assert_eq!(bullets(&world).len(), 2);
+}
+
+#[test]
+fn a_bullet_flies_straight_through_blocks() {
+    let mut world = world_from(&LAUNCHER_LEVEL);
+    let mut pad = Pad::default();
+    pad.hold(&mut world, idle(), LAUNCHER_COOLDOWN_FRAMES as usize);
+    let fired = bullets(&world)[0];
+    // towards the player, through the block between them
+    pad.hold(&mut world, idle(), 120);
+    let bullet = bullets(&world)[0];
+    assert!(bullet.x < (24 * TILE) as f32, "{} is still behind the block", bullet.x);
+    assert!(bullet.x < fired.x);
+    assert_eq!(bullet.y, fired.y);
+    assert_eq!(*world.game_state(), GameState::Playing);
+}
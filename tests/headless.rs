@@ -0,0 +1,299 @@
+mod common;
+
+use common::{idle, jump, right, run_right, world_from, Pad, TILE};
+use rustario_2d::audio::{MusicId, SfxId};
+use rustario_2d::entities::PlayerState;
+use rustario_2d::physics::PhysicsConfig;
+use rustario_2d::world::GameState;
+
+#[test]
+fn stomping_a_goomba_kills_it() {
+    let mut world = world_from(&[
+        "                    ",
+        "   M                ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "  g                 ",
+        "####################",
+        "####################",
+    ]);
+    let mut pad = Pad::default();
+    for _ in 0..60 {
+        // keeps over the Goomba as it walks off
+        let input = match world.enemies().next() {
+            Some((_, goomba)) if goomba.x > world.player_position().x + 1.0 => right(),
+            _ => idle(),
+        };
+        pad.step(&mut world, input);
+    }
+    assert_eq!(world.entity_counts().enemies, 0);
+    assert_eq!(world.player_state(), PlayerState::Small);
+    assert_eq!(*world.game_state(), GameState::Playing);
+    assert!(world.audio.played_sfx().contains(&SfxId::Stomp));
+    assert!(world.score().score() > 0);
+}
+
+#[test]
+fn bumping_a_block_from_below_stops_the_jump() {
+    let mut world = world_from(&[
+        "          ",
+        "          ",
+        "          ",
+        "          ",
+        "          ",
+        "          ",
+        "          ",
+        "   #      ",
+        "          ",
+        "          ",
+        "          ",
+        "   M      ",
+        "##########",
+        "##########",
+    ]);
+    let mut pad = Pad::default();
+    let blocks = world.entity_counts().blocks;
+    let block_bottom = 8.0 * TILE as f32;
+    let mut highest = world.player_position().y;
+    for _ in 0..60 {
+        pad.step(&mut world, jump());
+        highest = highest.min(world.player_position().y);
+    }
+    assert!(world.audio.played_sfx().contains(&SfxId::Bump));
+    assert!((highest - block_bottom).abs() < 0.01, "{}", highest);
+    // back on the ground below the block, which is still there
+    assert_eq!(world.player_position().y, 11.0 * TILE as f32);
+    assert_eq!(world.entity_counts().blocks, blocks);
+}
+
+#[test]
+fn bumping_a_powerup_block_lets_a_mushroom_out_that_makes_the_player_big() {
+    let mut world = world_from(&[
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "   ?                ",
+        "                    ",
+        "                    ",
+        "         #          ",
+        "   M     #          ",
+        "####################",
+        "####################",
+    ]);
+    let mut pad = Pad::default();
+    while !world.audio.played_sfx().contains(&SfxId::Bump) {
+        assert!(world.player_velocity().y <= 0.0, "came down without reaching the block");
+        pad.step(&mut world, jump());
+    }
+    assert_eq!(world.entity_counts().spawning, 1);
+    pad.hold(&mut world, idle(), 60);
+    assert_eq!(world.entity_counts().spawning, 0);
+    assert_eq!(world.entity_counts().powerups, 1);
+    // it slides off the block to the right and comes back off the wall
+    pad.hold(&mut world, idle(), 240);
+    assert_eq!(world.entity_counts().powerups, 0);
+    assert_eq!(world.player_state(), PlayerState::Big);
+    assert!(world.audio.played_sfx().contains(&SfxId::Powerup));
+}
+
+#[test]
+fn reaching_the_flagpole_wins_the_level() {
+    let mut world = world_from(&[
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                  F ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        " M                  ",
+        "####################",
+        "####################",
+    ]);
+    let mut pad = Pad::default();
+    pad.hold(&mut world, run_right(), 30);
+    assert_eq!(*world.game_state(), GameState::Playing);
+    pad.hold(&mut world, run_right(), 300);
+    assert_eq!(*world.game_state(), GameState::GameWon);
+}
+
+#[test]
+fn falling_into_a_pit_loses_a_life() {
+    let mut world = world_from(&[
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        " M                  ",
+        "######   ###########",
+        "######   ###########",
+    ]);
+    let mut pad = Pad::default();
+    pad.hold(&mut world, right(), 120);
+    assert!(matches!(world.game_state(), GameState::Dying(_)));
+    assert!(world.audio.played_sfx().contains(&SfxId::Death));
+    pad.hold(&mut world, idle(), 600);
+    assert_eq!(*world.game_state(), GameState::GameOver);
+}
+
+#[test]
+fn running_over_flat_ground_keeps_full_speed() {
+    let sky = " ".repeat(150);
+    let start = format!(" M{}", " ".repeat(148));
+    let ground = "#".repeat(150);
+    let mut rows = vec![sky.as_str(); 11];
+    rows.extend([start.as_str(), ground.as_str(), ground.as_str()]);
+    let mut world = world_from(&rows);
+    let mut pad = Pad::default();
+    pad.hold(&mut world, run_right(), 300);
+    let top_speed = world.player_velocity().x;
+    // friction takes its bit after the clamp, the top speed sits just under the limit
+    assert!(top_speed > world.physics().max_speed * 0.95, "{}", top_speed);
+    for _ in 0..100 {
+        pad.step(&mut world, run_right());
+        assert_eq!(world.player_velocity().x, top_speed);
+        assert_eq!(world.player_position().y, 11.0 * TILE as f32);
+    }
+}
+
+#[test]
+fn a_star_makes_the_player_knock_out_what_they_touch() {
+    let mut world = world_from(&[
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        "                    ",
+        " s                  ",
+        "                  # ",
+        "    M           g # ",
+        "####################",
+        "####################",
+    ]);
+    let mut pad = Pad::default();
+    // it bounces along to the right straight into the player
+    pad.hold(&mut world, idle(), 60);
+    assert_eq!(world.entity_counts().powerups, 0);
+    assert_eq!(world.player_state(), PlayerState::Small);
+    assert_eq!(world.audio.current_music(), Some(MusicId::Star));
+    // running into the Goomba takes it out instead of the player
+    pad.hold(&mut world, right(), 240);
+    assert_eq!(world.entity_counts().enemies, 0);
+    assert_eq!(world.player_state(), PlayerState::Small);
+    assert_eq!(*world.game_state(), GameState::Playing);
+}
+
+#[test]
+fn a_long_fall_never_gets_faster_than_the_cap() {
+    let mut rows = vec!["                    "; 40];
+    rows[1] = "  M       g         ";
+    rows.extend(["####################"; 2]);
+    let mut world = world_from(&rows);
+    let max_fall_speed = world.physics().max_fall_speed;
+    let mut pad = Pad::default();
+    let mut goomba_y = world.enemies().next().unwrap().1.y;
+    let mut fastest: f32 = 0.0;
+    for _ in 0..120 {
+        pad.step(&mut world, idle());
+        fastest = fastest.max(world.player_velocity().y);
+        let y = world.enemies().next().unwrap().1.y;
+        assert!(y - goomba_y <= max_fall_speed + 1e-3, "the Goomba fell {} in a frame", y - goomba_y);
+        goomba_y = y;
+    }
+    assert_eq!(fastest, max_fall_speed);
+}
+
+// from walking speed on flat ground, how far the player slides after letting go
+fn stop_distance(ground_friction: f32) -> f32 {
+    let sky = " ".repeat(40);
+    let start = format!("  M{}", " ".repeat(37));
+    let ground = "#".repeat(40);
+    let mut rows = vec![sky.as_str(); 11];
+    rows.extend([start.as_str(), ground.as_str(), ground.as_str()]);
+    let mut world = world_from(&rows);
+    // quick to speed up whatever the friction, so every run lets go at about the walking cap
+    world.set_physics(PhysicsConfig { ground_friction, acceleration: 10.0, ..*world.physics() });
+    let mut pad = Pad::default();
+    pad.hold(&mut world, idle(), 30);
+    pad.hold(&mut world, right(), 60);
+    assert!(world.player_velocity().x > world.physics().max_walk_speed * 0.95);
+    let let_go_at = world.player_position().x;
+    for _ in 0..600 {
+        pad.step(&mut world, idle());
+        if world.player_velocity().x == 0.0 {
+            return world.player_position().x - let_go_at;
+        }
+    }
+    panic!("still sliding after 10 seconds with a friction of {}", ground_friction);
+}
+
+#[test]
+fn stop_distance_follows_the_ground_friction() {
+    let default = PhysicsConfig::default().ground_friction;
+    let (slippery, normal, grippy) = (stop_distance(default / 2.0), stop_distance(default), stop_distance(default * 2.0));
+    assert!(slippery > normal && normal > grippy, "{} {} {}", slippery, normal, grippy);
+    // the same speed lost each step, so twice the friction stops in about half the distance
+    assert!((normal / grippy - 2.0).abs() < 0.5, "{} {}", normal, grippy);
+}
+
+// the player standing on flat ground, tall enough above it for any jump
+fn standing_on_flat_ground() -> (rustario_2d::world::World, Pad) {
+    let mut rows = vec!["                    "; 11];
+    rows.extend(["    M               ", "####################", "####################"]);
+    let mut world = world_from(&rows);
+    let mut pad = Pad::default();
+    pad.hold(&mut world, idle(), 30);
+    assert_eq!(world.player_position().y, 11.0 * TILE as f32);
+    (world, pad)
+}
+
+fn jump_sounds(world: &rustario_2d::world::World) -> usize {
+    world.audio.played_sfx().iter().filter(|sfx| **sfx == SfxId::Jump).count()
+}
+
+#[test]
+fn holding_jump_for_two_seconds_on_flat_ground_jumps_once() {
+    let (mut world, mut pad) = standing_on_flat_ground();
+    let ground = world.player_position().y;
+    let mut take_offs = 0;
+    let mut previous = ground;
+    for _ in 0..120 {
+        pad.step(&mut world, jump());
+        let y = world.player_position().y;
+        if previous == ground && y < ground {
+            take_offs += 1;
+        }
+        previous = y;
+    }
+    assert_eq!(take_offs, 1);
+    assert_eq!(jump_sounds(&world), 1);
+    // back down after the one arc, and still standing there with jump held
+    assert_eq!(world.player_position().y, ground);
+    assert_eq!(world.player_velocity().y, 0.0);
+}
@@ -0,0 +1,74 @@
+// plays the committed recording headlessly and compares the run to golden values, so a change to the physics or
+// collisions that alters gameplay fails with the first frame that went differently.
+// after an intended change, `BLESS=1 cargo test --test replay` writes the golden values from this run instead
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use rustario_2d::config::MARIO_WORLD_SIZE;
+use rustario_2d::input::InputState;
+use rustario_2d::replay::Replay;
+use rustario_2d::world::{StepDriver, World};
+
+const REPLAY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/replays/golden.rpl");
+const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/replays/golden.json");
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Golden {
+    collected_coins: usize,
+    score: u32,
+    player: (f32, f32),
+    state_hash: u64,
+    frame_hashes: Vec<u64>, // World::state_hash after every recorded frame
+}
+
+struct Playback {
+    replay: Replay,
+    frame_hashes: Vec<u64>,
+}
+
+impl StepDriver for Playback {
+    fn next_input(&mut self) -> InputState {
+        self.replay.next_input().unwrap_or_default()
+    }
+
+    fn stepped(&mut self, world: &World, _input: &InputState) {
+        self.replay.verify(world.checksum());
+        self.frame_hashes.push(world.state_hash());
+    }
+}
+
+#[test]
+fn the_golden_run_plays_out_the_same() {
+    let replay = Replay::load(REPLAY).expect("the golden recording loads");
+    let mut world = World::new_headless(MARIO_WORLD_SIZE.height, MARIO_WORLD_SIZE.width);
+    // the level path is relative to the repository
+    let level = Path::new(env!("CARGO_MANIFEST_DIR")).join(&replay.level_id);
+    world.load_level(&level).expect("the recording's level loads");
+    let mut playback = Playback { replay, frame_hashes: Vec::new() };
+    // dying frames run without input, the game ending stops the playback early
+    while !playback.replay.is_finished() && world.run_step(&mut playback) {}
+
+    let player = world.player_position();
+    let run = Golden {
+        collected_coins: world.collected_coins(),
+        score: world.score().score(),
+        player: (player.x, player.y),
+        state_hash: world.state_hash(),
+        frame_hashes: playback.frame_hashes,
+    };
+    if std::env::var_os("BLESS").is_some() {
+        fs::write(GOLDEN, serde_json::to_string(&run).expect("golden values serialize")).expect("golden values are written");
+        return;
+    }
+    let golden: Golden = serde_json::from_slice(&fs::read(GOLDEN).expect("golden values are committed")).expect("golden values parse");
+    if let Some(index) = run.frame_hashes.iter().zip(&golden.frame_hashes).position(|(hash, expected)| hash != expected) {
+        panic!("frame {} is the first that went differently", index + 1);
+    }
+    assert_eq!(run.frame_hashes.len(), golden.frame_hashes.len(), "the run lasted a different number of frames");
+    assert_eq!(run.state_hash, golden.state_hash, "the world ended up differently");
+    assert_eq!(run.collected_coins, golden.collected_coins);
+    assert_eq!(run.score, golden.score);
+    assert_eq!(run.player, golden.player);
+}